@@ -15,6 +15,7 @@ pub fn convert_to_string(value: &Value) -> Cow<'_, str> {
     match *value {
         Value::String(ref s) => Cow::from(s.as_str()),
         Value::Number(ref i) if i.is_u64() => Cow::from(i.as_u64().unwrap().to_string()),
+        Value::Number(ref i) if i.is_i64() => Cow::from(i.as_i64().unwrap().to_string()),
         Value::Number(ref i) if i.is_f64() => Cow::from(i.as_f64().unwrap().to_string()),
         Value::Bool(ref i) => Cow::from(i.to_string()),
         _ => Cow::from(""),
@@ -3,6 +3,7 @@ mod operator;
 
 pub use operator::Operator;
 pub use leaf::UserFilter;
+pub use ordered_float::OrderedFloat;
 
 use std::{convert::From, fmt};
 use std::collections::HashSet;
@@ -12,12 +13,20 @@ pub enum UserAST {
     Attributed(String, Box<UserAST>),
     BinaryClause(Box<UserAST>, Operator, Box<UserAST>),
     Leaf(Box<UserFilter>),
+    /// Matches documents with a value in the field, e.g. `_exists_:ean`
+    Exists(String),
+    /// Multiplies the score of the term or group with the boost, e.g. `fancy^2` or `(super cool)^2`
+    Boosted(Box<UserAST>, OrderedFloat<f32>),
 }
 
 // conversion used in tests
 impl From<&'static str> for UserAST {
     fn from(item: &str) -> Self {
-        let mut filter = UserFilter { phrase: item.to_string(), levenshtein: None };
+        let mut filter = UserFilter {
+            phrase: item.to_string(),
+            levenshtein: None,
+            wildcard: false,
+        };
         if item.chars().next().map(|c| c != '\"').unwrap_or(false) {
             let parts_field = item.splitn(2, ':').collect::<Vec<_>>();
             if parts_field.len() > 1 {
@@ -31,6 +40,8 @@ impl From<&'static str> for UserAST {
                 filter.levenshtein = Some(parts_leven[1].parse().unwrap());
             }
 
+            filter.wildcard = filter.phrase.contains(|c| c == '*' || c == '?');
+
             if parts_field.len() > 1 {
                 return UserAST::Attributed(parts_field[0].to_string(), Box::new(UserAST::Leaf(Box::new(filter))));
             }
@@ -51,6 +62,8 @@ impl fmt::Debug for UserAST {
             UserAST::Attributed(attr, ast) => write!(formatter, "{}:{:?}", attr, ast),
             UserAST::BinaryClause(ast1, op, ast2) => write!(formatter, "({:?} {} {:?})", ast1, op, ast2),
             UserAST::Leaf(filter) => write!(formatter, "{:?}", filter),
+            UserAST::Exists(field) => write!(formatter, "_exists_:{}", field),
+            UserAST::Boosted(ast, boost) => write!(formatter, "{:?}^{}", ast, boost),
         }
     }
 }
@@ -88,7 +101,9 @@ impl UserAST {
                     (None, None) => None,
                 };
             }
+            UserAST::Boosted(ast, boost) => return UserAST::filter_ast(ast, should_filter, current_attr).map(|ast| UserAST::Boosted(ast.into(), *boost)),
             UserAST::Leaf(_filter) => {}
+            UserAST::Exists(_field) => {}
         }
 
         Some(self.clone())
@@ -110,7 +125,9 @@ impl UserAST {
                 *ast1 = Box::new(UserAST::map_ast(*ast1.clone(), map_fn, current_attr));
                 *ast2 = Box::new(UserAST::map_ast(*ast2.clone(), map_fn, current_attr));
             }
+            UserAST::Boosted(ref mut ast, _boost) => *ast = Box::new(UserAST::map_ast(*ast.clone(), map_fn, current_attr)),
             UserAST::Leaf(ref _filter) => {}
+            UserAST::Exists(ref _field) => {}
         }
 
         let new = map_fn(self, current_attr);
@@ -137,12 +154,15 @@ impl UserAST {
                 ast1._get_phrase_pairs(collect, last_term, curr_attr);
                 ast2._get_phrase_pairs(collect, last_term, curr_attr);
             }
+            UserAST::Boosted(ast, _boost) => ast._get_phrase_pairs(collect, last_term, curr_attr),
             UserAST::Leaf(filter) => {
                 if let Some(last_term) = last_term {
                     collect.insert([last_term, &filter.phrase]);
                 }
                 *last_term = Some(&filter.phrase)
             }
+            // the field name is not a term, the terms before and after are not adjacent
+            UserAST::Exists(_field) => *last_term = None,
         }
     }
 
@@ -159,7 +179,9 @@ impl UserAST {
                 ast1.walk_terms(cb);
                 ast2.walk_terms(cb);
             }
+            UserAST::Boosted(ast, _boost) => ast.walk_terms(cb),
             UserAST::Leaf(filter) => cb(&filter.phrase),
+            UserAST::Exists(_field) => {}
         }
     }
 }
@@ -176,13 +198,15 @@ mod test_ast {
         let external_term_1 = "a".to_string();
         let filter_1 = UserFilter {
             phrase: external_term_1,
-            levenshtein: None
+            levenshtein: None,
+            wildcard: false,
         };
         let left_ast: UserAST = UserAST::Leaf(Box::new(filter_1));
         let external_term_2 = "b".to_string();
         let filter_2 = UserFilter {
             phrase: external_term_2,
-            levenshtein: None
+            levenshtein: None,
+            wildcard: false,
         };
         let right_ast: UserAST = UserAST::Leaf(Box::new(filter_2));
 
@@ -225,7 +249,7 @@ mod test_ast {
         let ast = ast.map_ast(
             &mut |ast: UserAST, _attr: Option<&str>| {
                 match ast {
-                    UserAST::Leaf(ref map) if map.phrase == "cool" => UserAST::Leaf(Box::new(UserFilter { phrase: "coolcool".to_string(), levenshtein: None })),
+                    UserAST::Leaf(ref map) if map.phrase == "cool" => UserAST::Leaf(Box::new(UserFilter { phrase: "coolcool".to_string(), levenshtein: None, wildcard: false })),
                     _ => ast,
                 }
             },
@@ -241,8 +265,8 @@ mod test_ast {
             &mut |ast: UserAST, _attr: Option<&str>| {
                 match ast {
                     UserAST::Leaf(ref map) if map.phrase == "kawaii" => {
-                        let leftast = UserAST::Leaf(Box::new(UserFilter { phrase: "kawaii".to_string(), levenshtein: None }));
-                        let rightast = UserAST::Leaf(Box::new(UserFilter { phrase: "かわいい".to_string(), levenshtein: None }));
+                        let leftast = UserAST::Leaf(Box::new(UserFilter { phrase: "kawaii".to_string(), levenshtein: None, wildcard: false }));
+                        let rightast = UserAST::Leaf(Box::new(UserFilter { phrase: "かわいい".to_string(), levenshtein: None, wildcard: false }));
 
                         UserAST::BinaryClause(Box::new(leftast), Or, Box::new(rightast))
                     },
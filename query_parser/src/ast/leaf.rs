@@ -5,6 +5,8 @@ pub struct UserFilter {
     pub phrase: String,
     /// levenshtein edit distance https://en.wikipedia.org/wiki/Levenshtein_distance
     pub levenshtein: Option<u8>,
+    /// the phrase contains the wildcards `*` or `?`
+    pub wildcard: bool,
 }
 
 impl std::fmt::Debug for UserFilter {
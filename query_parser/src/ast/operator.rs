@@ -1,3 +1,5 @@
+use crate::error::{ParseError, ParseErrorKind};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Operator {
     Or,
@@ -12,14 +14,23 @@ impl std::fmt::Display for Operator {
     }
 }
 
-impl From<&str> for Operator {
-    fn from(item: &str) -> Self {
-        if item == "OR" {
-            return Operator::Or;
-        }
-        if item == "AND" {
-            return Operator::And;
+impl std::str::FromStr for Operator {
+    type Err = ParseError;
+
+    fn from_str(item: &str) -> Result<Self, Self::Err> {
+        match item {
+            "OR" => Ok(Operator::Or),
+            "AND" => Ok(Operator::And),
+            _ => Err(
+                ParseError::new(ParseErrorKind::UnexpectedTokenType, format!("could not convert {:?} to operator", item), item, 0)
+                    .with_expected(vec!["OR".to_string(), "AND".to_string()]),
+            ),
         }
-        panic!("could not convert {:?} to operator", item);
     }
 }
+
+#[test]
+fn test_operator_from_str() {
+    assert_eq!("AND".parse(), Ok(Operator::And));
+    assert_eq!("or".parse::<Operator>().unwrap_err().expected, ["OR", "AND"]);
+}
@@ -1,10 +1,63 @@
-#[derive(Debug, Eq, PartialEq)]
-pub enum ParseError {
-    EmptyParentheses(String),
-    UnexpectedTokenType(String, String),
-    ExpectedNumber(String),
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    EmptyParentheses,
+    UnexpectedTokenType,
+    ExpectedNumber,
+}
+
+/// Error with the byte offset of the offending token in the query.
+///
+/// The `Display` output marks the position with a caret, e.g.
+/// ```text
+/// Expecting a levenshtein number after a '~' at position 6, expected one of: term
+/// fancy~
+///       ^
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub message: String,
+    /// the query, which failed to parse
+    pub query: String,
+    /// byte offset of the offending token in the query, the length of the query if the query ended unexpectedly
+    pub position: usize,
+    /// the tokens, which would have been valid at the position, e.g. `["term", "'('"]`
+    pub expected: Vec<String>,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ParseErrorKind, message: impl Into<String>, query: &str, position: usize) -> Self {
+        ParseError {
+            kind,
+            message: message.into(),
+            query: query.to_string(),
+            position,
+            expected: vec![],
+        }
+    }
+
+    pub(crate) fn with_expected(mut self, expected: Vec<String>) -> Self {
+        self.expected = expected;
+        self
+    }
+
+    /// The query with a caret under the offending position
+    pub fn caret_message(&self) -> String {
+        let column = self.query.get(..self.position).map(|before| before.chars().count()).unwrap_or(0);
+        format!("{}\n{}^", self.query, " ".repeat(column))
+    }
 }
 
-pub(crate) fn marked_in_orig(text: &str, start: usize, stop: usize) -> String {
-    format!("{}﹏{}﹏{}", &text[..start], &text[start..stop], &text[stop..])
+impl fmt::Display for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{} at position {}", self.message, self.position)?;
+        if !self.expected.is_empty() {
+            write!(formatter, ", expected one of: {}", self.expected.join(", "))?;
+        }
+        write!(formatter, "\n{}", self.caret_message())
+    }
 }
+
+impl std::error::Error for ParseError {}
@@ -10,6 +10,7 @@ pub(crate) enum TokenType {
     ParenthesesOpen,
     ParenthesesClose,
     Tilde,
+    Caret,
 
     // 2-char
     Or,
@@ -18,6 +19,21 @@ pub(crate) enum TokenType {
     And,
 }
 
+impl std::fmt::Display for TokenType {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenType::AttributeLiteral => write!(formatter, "attribute"),
+            TokenType::Literal => write!(formatter, "term"),
+            TokenType::ParenthesesOpen => write!(formatter, "'('"),
+            TokenType::ParenthesesClose => write!(formatter, "')'"),
+            TokenType::Tilde => write!(formatter, "'~'"),
+            TokenType::Caret => write!(formatter, "'^'"),
+            TokenType::Or => write!(formatter, "OR"),
+            TokenType::And => write!(formatter, "AND"),
+        }
+    }
+}
+
 impl TokenType {
     fn from_single_char(cha: char, options: &Options) -> Option<Self> {
         match cha {
@@ -26,6 +42,7 @@ impl TokenType {
             // '"'  => Some(TokenType::DoubleQuotes),
             // '\'' => Some(TokenType::SingleQuotes),
             '~' if !options.no_levensthein => Some(TokenType::Tilde),
+            '^' if !options.no_boost => Some(TokenType::Caret),
             // ':' => Some(TokenType::Colon),
             _ => None,
         }
@@ -36,16 +53,23 @@ fn is_seperator(cha: char, options: &Options) -> bool {
     match cha {
         '(' | ')' if !options.no_parentheses => true,
         '~' if !options.no_levensthein => true,
+        '^' if !options.no_boost => true,
         ':' if !options.no_attributes => true,
         _ => false,
     }
 }
 
+pub(crate) fn is_wildcard(cha: char) -> bool {
+    cha == '*' || cha == '?'
+}
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct Token {
     pub(crate) byte_start_pos: u32,
     pub(crate) byte_stop_pos: u32,
     pub(crate) token_type: TokenType,
+    /// The literal contains the wildcards `*` or `?`
+    pub(crate) has_wildcard: bool,
     // pub(crate) in_quotes: bool,
 }
 
@@ -128,9 +152,16 @@ impl<'a> Lexer<'a> {
                 _ => None,
             };
 
+            if self.is_doublequote(self.current_pos) && self.options.lenient && !self.chars[self.current_pos as usize + 1..].contains(&'"') {
+                // strip the unbalanced quote
+                self.eat_char();
+                return self.next_token();
+            }
+
             if self.is_doublequote(self.current_pos) {
                 self.eat_char();
                 byte_start_pos += 1; // move behind quote
+                let start_pos = self.current_pos;
                 while self.cur_char().is_some() && !self.is_doublequote(self.current_pos) {
                     self.eat_char();
                 }
@@ -143,6 +174,7 @@ impl<'a> Lexer<'a> {
                 // }
 
                 let byte_stop_pos = self.current_byte_pos;
+                let has_wildcard = self.has_wildcard_between(start_pos, self.current_pos);
                 self.eat_char();
                 let token_type = if self.is_attr_colon_at(self.current_pos) {
                     self.eat_char();
@@ -155,6 +187,7 @@ impl<'a> Lexer<'a> {
                     token_type,
                     byte_start_pos,
                     byte_stop_pos,
+                    has_wildcard,
                 }));
             }
 
@@ -169,15 +202,18 @@ impl<'a> Lexer<'a> {
                     token_type,
                     byte_start_pos,
                     byte_stop_pos,
+                    has_wildcard: false,
                 });
                 return Ok(next_token);
             }
 
             // Literal
+            let start_pos = self.current_pos;
             self.eat_char();
             let opt = self.options;
             self.eat_while(|c| !c.is_whitespace() && !is_seperator(c, &opt));
             let byte_stop_pos = self.current_byte_pos;
+            let has_wildcard = self.has_wildcard_between(start_pos, self.current_pos);
             let token_type = if self.is_attr_colon_at(self.current_pos) {
                 self.eat_char();
                 TokenType::AttributeLiteral
@@ -188,6 +224,7 @@ impl<'a> Lexer<'a> {
                 token_type,
                 byte_start_pos,
                 byte_stop_pos,
+                has_wildcard,
             }))
         } else {
             Ok(None)
@@ -198,6 +235,11 @@ impl<'a> Lexer<'a> {
         !self.options.no_attributes && self.chars.get(pos as usize).map(|c| *c == ':').unwrap_or(false)
     }
 
+    /// Wildcards are also allowed in quotes, e.g. "*myfun(param1: Type1)*"
+    fn has_wildcard_between(&self, start_pos: u32, stop_pos: u32) -> bool {
+        !self.options.no_wildcards && self.chars[start_pos as usize..stop_pos as usize].iter().any(|c| is_wildcard(*c))
+    }
+
     // is quote
     pub fn is_doublequote(&self, pos: u32) -> bool {
         self.chars.get(pos as usize).cloned().map(|c| c == '"').unwrap_or(false)
@@ -294,6 +336,16 @@ mod tests {
         // assert_eq!(Lexer::new(r#""my quote""#).get_tokens().into_iter().map(|t|t.in_quotes).collect::<Vec<_>>(), [false, true, true, true, false]);
     }
 
+    #[test]
+    fn test_unbalanced_quotes_lenient() {
+        let opt = Options {
+            lenient: true,
+            ..Default::default()
+        };
+        assert_eq!(Lexer::new_with_opt(r#""my quote"#, opt).get_tokens_text(), ["my", "quote"]);
+        assert_eq!(Lexer::new_with_opt(r#""my quote" "#, opt).get_tokens_text(), ["my quote"]);
+    }
+
     //TODO there is no quote escaping
     #[test]
     fn test_escape_quotes() {
@@ -312,6 +364,33 @@ mod tests {
         assert_eq!(Lexer::new("~a~").get_token_types(), [TT::Tilde, TT::Literal, TT::Tilde]);
     }
 
+    #[test]
+    fn test_wildcard() {
+        let has_wildcard = |text, options| Lexer::new_with_opt(text, options).get_tokens().unwrap().iter().map(|t| t.has_wildcard).collect::<Vec<_>>();
+        assert_eq!(has_wildcard("foo* f?o bar", Options::default()), [true, true, false]);
+        assert_eq!(has_wildcard(r#""*my fun*" field*:foo"#, Options::default()), [true, true, false]);
+        assert_eq!(Lexer::new("fo?~1").get_token_types(), [TT::Literal, TT::Tilde, TT::Literal]);
+        let opt = Options {
+            no_wildcards: true,
+            ..Default::default()
+        };
+        assert_eq!(has_wildcard("foo* f?o", opt), [false, false]);
+    }
+
+    #[test]
+    fn test_caret() {
+        assert_eq!(Lexer::new("fancy^2").get_tokens_text(), ["fancy", "^", "2"]);
+        assert_eq!(
+            Lexer::new("(cool)^1.5").get_token_types(),
+            [TT::ParenthesesOpen, TT::Literal, TT::ParenthesesClose, TT::Caret, TT::Literal]
+        );
+        let opt = Options {
+            no_boost: true,
+            ..Default::default()
+        };
+        assert_eq!(Lexer::new_with_opt("fancy^2", opt).get_token_types(), [TT::Literal]);
+    }
+
     #[test]
     fn test_colon() {
         assert_eq!(Lexer::new("cool:nice").get_tokens_text(), ["cool", "nice"]);
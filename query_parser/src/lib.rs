@@ -22,15 +22,26 @@ quotes"inthemiddle"isstillonetoken
 name:fred
 title:(my booktitle)
 
+## Exists
+_exists_:ean matches all documents with a value in the field ean
+
 ## Parentheses
 (cool AND nice) OR (thick and woke)
 
+## Boost
+The score of a token or a group can be multiplied with ^
+e.g. title:(harry potter)^3 OR author:rowling
+
 ## Levenshtein Distance
 
 The levenshtein edit distance for a token can be defined with ~
 e.g. searchterm~2 means a edit distance of 2 for searchterm,
 that means searchtuam would be a hit, because the edit distance is 2.
 
+## Wildcards
+`*` matches any number of characters, `?` matches exactly one character, e.g.
+awes* or fo?bar. Wildcards are also applied in quotes.
+
 */
 #![warn(missing_debug_implementations, rust_2018_idioms)]
 pub mod ast;
@@ -51,5 +62,14 @@ pub struct Options {
     /// This setting will disable defining a levensthtein distance after a searchterm
     /// e.g. "searchterm~2"
     pub no_levensthein: bool,
+    /// This setting will disable the wildcards `*` and `?` in a searchterm
+    /// e.g. "fo?ba*"
+    pub no_wildcards: bool,
+    /// This setting will disable defining a boost after a searchterm or group
+    /// e.g. "searchterm^2"
+    pub no_boost: bool,
+    /// Repairs invalid queries instead of returning an error, e.g. unbalanced quotes and parentheses are removed
+    /// e.g. "(searchterm" is parsed as "searchterm"
+    pub lenient: bool,
     // pub no_quotes: bool
 }
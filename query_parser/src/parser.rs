@@ -1,6 +1,6 @@
 use crate::{
     ast::*,
-    error::{marked_in_orig, ParseError},
+    error::{ParseError, ParseErrorKind},
     lexer::{Lexer, Token, TokenType},
     Options,
 };
@@ -18,15 +18,132 @@ macro_rules! return_binary_clause {
     };
 }
 
+/// `_exists_:field` matches documents with a value in the field
+const EXISTS_ATTRIBUTE: &str = "_exists_";
+
 pub(crate) fn get_text_for_token(text: &str, start: u32, stop: u32) -> &str {
     &text[start as usize..stop as usize]
 }
 
 pub fn parse(text: &str) -> Result<UserAST, ParseError> {
-    Parser::new(text)?._parse()
+    Parser::new(text)?.parse_all()
 }
 pub fn parse_with_opt(text: &str, options: Options) -> Result<UserAST, ParseError> {
-    Parser::new_with_opt(text, options)?._parse()
+    let ast = Parser::new_with_opt(text, options)?.parse_all();
+    if options.lenient {
+        // the repaired tokens should always be valid, the plain terms are the last resort
+        return ast.or_else(|err| parse_as_terms(text).ok_or(err));
+    }
+    ast
+}
+
+fn is_operand_start(token_type: Option<TokenType>) -> bool {
+    match token_type {
+        Some(TokenType::Literal) | Some(TokenType::AttributeLiteral) | Some(TokenType::ParenthesesOpen) => true,
+        _ => false,
+    }
+}
+
+fn is_operand_end(token_type: Option<TokenType>) -> bool {
+    match token_type {
+        Some(TokenType::Literal) | Some(TokenType::ParenthesesClose) => true,
+        _ => false,
+    }
+}
+
+/// Removes unbalanced closing parentheses, empty parentheses and misplaced operators, unbalanced opening parentheses are closed at the end.
+/// Attributes without a term are treated as terms.
+///
+/// Returns true if a token was changed.
+fn repair_tokens(text: &str, tokens: &mut Vec<Token>) -> bool {
+    let token_text = |token: &Token| get_text_for_token(text, token.byte_start_pos, token.byte_stop_pos);
+    let mut remove = vec![false; tokens.len()];
+    let mut open_parentheses = vec![];
+    for (pos, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::ParenthesesOpen => open_parentheses.push(pos),
+            TokenType::ParenthesesClose => match open_parentheses.pop() {
+                Some(open_pos) if open_pos + 1 == pos => {
+                    remove[open_pos] = true;
+                    remove[pos] = true;
+                }
+                Some(_) => {}
+                None => remove[pos] = true,
+            },
+            _ => {}
+        }
+    }
+
+    let mut changed = remove.contains(&true) || !open_parentheses.is_empty();
+    let mut repaired: Vec<Token> = Vec::with_capacity(tokens.len());
+    for (pos, mut token) in tokens.iter().cloned().enumerate() {
+        if remove[pos] {
+            continue;
+        }
+        let prev = repaired.last().map(|token| token.token_type);
+        let next = tokens.get(pos + 1).filter(|_| !remove[pos + 1]);
+        let next_type = next.map(|token| token.token_type);
+        let is_valid = match token.token_type {
+            TokenType::Or | TokenType::And => is_operand_end(prev) && is_operand_start(next_type),
+            TokenType::Tilde => {
+                prev == Some(TokenType::Literal)
+                    && next
+                        .filter(|next| next.token_type == TokenType::Literal)
+                        .map(|next| token_text(next).parse::<u8>().is_ok())
+                        .unwrap_or(false)
+            }
+            TokenType::Caret => {
+                is_operand_end(prev)
+                    && next
+                        .filter(|next| next.token_type == TokenType::Literal)
+                        .and_then(|next| token_text(next).parse::<f32>().ok())
+                        .map(|boost| boost.is_finite() && boost >= 0.0)
+                        .unwrap_or(false)
+            }
+            TokenType::AttributeLiteral if token_text(&token) == EXISTS_ATTRIBUTE => next_type == Some(TokenType::Literal),
+            TokenType::AttributeLiteral => next_type == Some(TokenType::Literal) || next_type == Some(TokenType::ParenthesesOpen),
+            TokenType::Literal | TokenType::ParenthesesOpen | TokenType::ParenthesesClose => true,
+        };
+        if is_valid {
+            repaired.push(token);
+        } else if token.token_type == TokenType::AttributeLiteral {
+            token.token_type = TokenType::Literal;
+            repaired.push(token);
+            changed = true;
+        } else {
+            changed = true;
+        }
+    }
+    let end_pos = text.len() as u32;
+    repaired.extend(open_parentheses.iter().map(|_| Token {
+        token_type: TokenType::ParenthesesClose,
+        byte_start_pos: end_pos,
+        byte_stop_pos: end_pos,
+        has_wildcard: false,
+    }));
+    *tokens = repaired;
+    changed
+}
+
+/// All terms of the query OR connected, without any syntax
+fn parse_as_terms(text: &str) -> Option<UserAST> {
+    text.split_whitespace()
+        .map(|term| term.trim_matches(|c| "\"()~^:".contains(c)))
+        .filter(|term| !term.is_empty())
+        .map(|term| {
+            UserAST::Leaf(Box::new(UserFilter {
+                phrase: term.to_string(),
+                levenshtein: None,
+                wildcard: false,
+            }))
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .fold(None, |curr_ast, ast| match curr_ast {
+            Some(curr_ast) => Some(UserAST::BinaryClause(Box::new(ast), Operator::Or, Box::new(curr_ast))),
+            None => Some(ast),
+        })
 }
 
 impl<'a> Parser<'a> {
@@ -36,31 +153,39 @@ impl<'a> Parser<'a> {
     }
 
     pub fn new_with_opt(text: &'a str, options: Options) -> Result<Self, ParseError> {
-        let tokens = Lexer::new_with_opt(text, options).get_tokens()?;
+        let mut tokens = Lexer::new_with_opt(text, options).get_tokens()?;
+        if options.lenient {
+            while repair_tokens(text, &mut tokens) {}
+        }
         Ok(Parser { tokens, pos: 0, text })
     }
 
+    /// Parses the whole query. Unbalanced ')' at the end are ignored, as always.
+    /// Other tokens after an unbalanced ')' are an error, e.g. `cool) nice`, they were silently dropped before.
+    pub fn parse_all(&mut self) -> Result<UserAST, ParseError> {
+        let ast = self._parse()?;
+        if self.tokens[self.pos..].iter().any(|token| token.token_type != TokenType::ParenthesesClose) {
+            self.unexpected_token_type("", None)?;
+        }
+        Ok(ast)
+    }
+
+    fn get_position(&self) -> usize {
+        self.tokens.get(self.pos).map(|token| token.byte_start_pos as usize).unwrap_or_else(|| self.text.len())
+    }
+
     fn unexpected_token_type(&self, message: &'static str, allowed_types: Option<&[Option<TokenType>]>) -> Result<(), ParseError> {
-        // generate snippet
-        let [start, stop] = self
-            .tokens
-            .get(self.pos)
-            .map(|next_token| [next_token.byte_start_pos as usize, next_token.byte_stop_pos as usize])
-            .unwrap_or_else(|| [self.text.len(), self.text.len()]);
-        let marked_in_orig = marked_in_orig(self.text, start, stop);
-
-        let err = if message == "" {
-            let message = format!(
-                "{} Unexpected token_type, got {}{:?}",
-                message,
-                self.get_type().map(|el| format!("{:?}", el)).unwrap_or_else(|| "EOF".to_string()),
-                allowed_types.map(|el| format!(" allowed_types: {:?}", el)).unwrap_or_else(|| "".to_string())
-            );
-            ParseError::UnexpectedTokenType(marked_in_orig, message)
+        let message = if message == "" {
+            format!("Unexpected {}", self.get_type().map(|el| el.to_string()).unwrap_or_else(|| "end of query".to_string()))
         } else {
-            ParseError::UnexpectedTokenType(marked_in_orig, message.to_string())
+            message.to_string()
         };
-        Err(err)
+        let expected = allowed_types
+            .unwrap_or(&[])
+            .iter()
+            .map(|el| el.map(|el| el.to_string()).unwrap_or_else(|| "end of query".to_string()))
+            .collect();
+        Err(ParseError::new(ParseErrorKind::UnexpectedTokenType, message, self.text, self.get_position()).with_expected(expected))
     }
 
     fn assert_allowed_types(&self, message: &'static str, allowed_types: &[Option<TokenType>]) -> Result<(), ParseError> {
@@ -71,32 +196,71 @@ impl<'a> Parser<'a> {
     }
 
     fn next_token(&mut self) -> Result<Token, ParseError> {
-        let token = self.tokens.get(self.pos).unwrap();
+        let token = *self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::UnexpectedTokenType, "Unexpected end of query", self.text, self.text.len()))?;
         self.pos += 1;
-        Ok(*token)
+        Ok(token)
+    }
+
+    fn expected_number(&self, message: String, token: Token) -> ParseError {
+        ParseError::new(ParseErrorKind::ExpectedNumber, message, self.text, token.byte_start_pos as usize)
     }
 
     fn parse_user_filter(&mut self, curr_token: Token) -> Result<UserFilter, ParseError> {
         let mut curr_ast = UserFilter {
             levenshtein: None,
             phrase: get_text_for_token(self.text, curr_token.byte_start_pos, curr_token.byte_stop_pos).to_string(),
+            wildcard: curr_token.has_wildcard,
         };
 
         // Optional: Define Levenshtein distance
         if self.is_type(TokenType::Tilde) {
             self.next_token()?; // Remove Tilde
 
-            self.assert_allowed_types("Expecting a levenshtein number after a \'~\' ", &[Some(TokenType::Literal)])?;
+            self.assert_allowed_types("Expecting a levenshtein number after a '~'", &[Some(TokenType::Literal)])?;
 
             let lev_token = self.next_token()?; // Remove levenshtein number
-            let levenshtein: u8 = get_text_for_token(self.text, lev_token.byte_start_pos, lev_token.byte_stop_pos)
+            let lev_text = get_text_for_token(self.text, lev_token.byte_start_pos, lev_token.byte_stop_pos);
+            let levenshtein: u8 = lev_text
                 .parse()
-                .map_err(|_e| ParseError::ExpectedNumber(format!("Expected number after tilde to define levenshtein distance but got {:?}", lev_token)))?;
+                .map_err(|_e| self.expected_number(format!("Expected number after tilde to define levenshtein distance but got {:?}", lev_text), lev_token))?;
             curr_ast.levenshtein = Some(levenshtein);
         }
         Ok(curr_ast)
     }
 
+    /// Optional: Define a boost for the term or group, e.g. "fancy^2"
+    fn parse_boost(&mut self, curr_ast: UserAST) -> Result<UserAST, ParseError> {
+        if !self.is_type(TokenType::Caret) {
+            return Ok(curr_ast);
+        }
+        self.next_token()?; // Remove Caret
+
+        self.assert_allowed_types("Expecting a boost number after a '^'", &[Some(TokenType::Literal)])?;
+
+        let boost_token = self.next_token()?; // Remove boost number
+        let boost_text = get_text_for_token(self.text, boost_token.byte_start_pos, boost_token.byte_stop_pos);
+        let boost: f32 = boost_text
+            .parse()
+            .ok()
+            .filter(|boost: &f32| boost.is_finite() && *boost >= 0.0)
+            .ok_or_else(|| self.expected_number(format!("Expected positive number after caret to define boost but got {:?}", boost_text), boost_token))?;
+        Ok(UserAST::Boosted(Box::new(curr_ast), OrderedFloat(boost)))
+    }
+
+    /// Parses the group after the opening parentheses, including the closing parentheses and an optional boost
+    fn parse_parentheses(&mut self) -> Result<UserAST, ParseError> {
+        if self.is_type(TokenType::ParenthesesClose) {
+            return Err(ParseError::new(ParseErrorKind::EmptyParentheses, "Empty parentheses", self.text, self.get_position()));
+        }
+        let parenthesed_ast = self._parse()?;
+        self.assert_allowed_types("", &[Some(TokenType::ParenthesesClose)])?;
+        self.next_token()?;
+        self.parse_boost(parenthesed_ast)
+    }
+
     fn parse_sub_expression(&mut self, curr_ast: UserAST) -> Result<UserAST, ParseError> {
         self.assert_allowed_types(
             "",
@@ -113,7 +277,7 @@ impl<'a> Parser<'a> {
 
         if let Some(next_token_type) = self.get_type() {
             match next_token_type {
-                TokenType::AttributeLiteral | TokenType::Literal => {
+                TokenType::AttributeLiteral | TokenType::Literal | TokenType::ParenthesesOpen => {
                     return_binary_clause!(self, Operator::Or, curr_ast);
                 }
                 TokenType::Or => {
@@ -124,7 +288,7 @@ impl<'a> Parser<'a> {
                     self.next_token()?;
                     return_binary_clause!(self, Operator::And, curr_ast);
                 }
-                TokenType::ParenthesesOpen | TokenType::Tilde => unimplemented!(),
+                TokenType::Tilde | TokenType::Caret => unreachable!(), // covered by assert_allowed_types
                 TokenType::ParenthesesClose => Ok(curr_ast),
             }
         } else {
@@ -133,53 +297,58 @@ impl<'a> Parser<'a> {
     }
 
     fn _parse(&mut self) -> Result<UserAST, ParseError> {
+        self.assert_allowed_types("", &[Some(TokenType::AttributeLiteral), Some(TokenType::Literal), Some(TokenType::ParenthesesOpen)])?;
         let curr_token = self.next_token()?;
         match curr_token.token_type {
+            TokenType::AttributeLiteral if get_text_for_token(self.text, curr_token.byte_start_pos, curr_token.byte_stop_pos) == EXISTS_ATTRIBUTE => {
+                self.assert_allowed_types("only a field name allowed after '_exists_:'", &[Some(TokenType::Literal)])?;
+                let field_token = self.next_token()?;
+                let field = get_text_for_token(self.text, field_token.byte_start_pos, field_token.byte_stop_pos).to_string();
+                self.parse_sub_expression(UserAST::Exists(field))
+            }
             TokenType::AttributeLiteral => {
-                //Check if attribute covers whole ast or only next literal
+                //Check if attribute covers the group or only next literal
                 match self.get_type() {
                     Some(TokenType::ParenthesesOpen) => {
-                        return Ok(UserAST::Attributed(
+                        self.next_token()?;
+                        let attributed_ast = UserAST::Attributed(
                             get_text_for_token(self.text, curr_token.byte_start_pos, curr_token.byte_stop_pos).to_string(),
-                            Box::new(self._parse()?),
-                        ))
+                            Box::new(self.parse_parentheses()?),
+                        );
+                        self.parse_sub_expression(attributed_ast)
                     }
                     Some(TokenType::Literal) => {
                         let token2 = self.next_token()?;
-                        let curr_ast = self.parse_user_filter(token2)?;
+                        let curr_ast = UserAST::Leaf(Box::new(self.parse_user_filter(token2)?));
                         let attributed_ast = UserAST::Attributed(
                             get_text_for_token(self.text, curr_token.byte_start_pos, curr_token.byte_stop_pos).to_string(),
-                            Box::new(UserAST::Leaf(Box::new(curr_ast))),
+                            Box::new(self.parse_boost(curr_ast)?),
                         );
-                        return self.parse_sub_expression(attributed_ast);
+                        self.parse_sub_expression(attributed_ast)
                     }
-                    _ => self.unexpected_token_type(
-                        "only token or ( allowed after attribute ('attr:') ",
-                        Some(&[Some(TokenType::Literal), Some(TokenType::ParenthesesOpen)]),
-                    )?,
-                };
+                    _ => {
+                        self.unexpected_token_type(
+                            "only token or ( allowed after attribute ('attr:')",
+                            Some(&[Some(TokenType::Literal), Some(TokenType::ParenthesesOpen)]),
+                        )?;
+                        unreachable!()
+                    }
+                }
             }
             TokenType::Literal => {
-                let curr_ast = self.parse_user_filter(curr_token)?;
-                return self.parse_sub_expression(UserAST::Leaf(Box::new(curr_ast)));
+                let curr_ast = UserAST::Leaf(Box::new(self.parse_user_filter(curr_token)?));
+                let curr_ast = self.parse_boost(curr_ast)?;
+                self.parse_sub_expression(curr_ast)
             }
 
             TokenType::ParenthesesOpen => {
-                let parenthesed_ast = self._parse()?;
-                self.assert_allowed_types("", &[Some(TokenType::ParenthesesClose)])?;
-                self.next_token()?;
-                return self.parse_sub_expression(parenthesed_ast);
+                let parenthesed_ast = self.parse_parentheses()?;
+                self.parse_sub_expression(parenthesed_ast)
             }
-            TokenType::ParenthesesClose => unimplemented!(),
-            TokenType::Tilde => {
-                self.unexpected_token_type("", None)?; // IMPOSSIBURU!, should be covered by lookeaheads
-            }
-
-            TokenType::Or | TokenType::And => {
-                unimplemented!() // IMPOSSIBURU!, should be covered by lookeaheads
+            TokenType::ParenthesesClose | TokenType::Tilde | TokenType::Caret | TokenType::Or | TokenType::And => {
+                unreachable!() // covered by assert_allowed_types
             }
         }
-        unreachable!()
     }
 
     fn is_type(&self, token_type: TokenType) -> bool {
@@ -282,14 +451,12 @@ mod tests {
                 // field_name: None,
                 phrase: "fancy".to_string(),
                 levenshtein: Some(1),
+                wildcard: false,
             }))
         );
         assert_eq!(
-            parse("fancy~"),
-            Err(ParseError::UnexpectedTokenType(
-                "fancy~﹏﹏".to_string(),
-                "Expecting a levenshtein number after a \'~\' ".to_string()
-            ))
+            parse("fancy~").unwrap_err().to_string(),
+            "Expecting a levenshtein number after a '~' at position 6, expected one of: term\nfancy~\n      ^"
         );
         assert_eq!(parse("fancy~1").unwrap(), "fancy~1".into());
         assert_eq!(
@@ -310,6 +477,32 @@ mod tests {
                 // field_name: None,
                 phrase: "fancy~1".to_string(),
                 levenshtein: None,
+                wildcard: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_wildcard() {
+        assert_eq!(
+            parse("fo?ba*").unwrap(),
+            UserAST::Leaf(Box::new(UserFilter {
+                phrase: "fo?ba*".to_string(),
+                levenshtein: None,
+                wildcard: true,
+            }))
+        );
+        assert_eq!(parse("field:fo?ba*").unwrap(), "field:fo?ba*".into());
+        let opt = Options {
+            no_wildcards: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_opt("fo?ba*", opt).unwrap(),
+            UserAST::Leaf(Box::new(UserFilter {
+                phrase: "fo?ba*".to_string(),
+                levenshtein: None,
+                wildcard: false,
             }))
         );
     }
@@ -323,6 +516,7 @@ mod tests {
                 Box::new(UserAST::Leaf(Box::new(UserFilter {
                     phrase: "fancy".to_string(),
                     levenshtein: Some(1),
+                    wildcard: false,
                 })))
             )
         );
@@ -334,6 +528,7 @@ mod tests {
                 Box::new(UserAST::Leaf(Box::new(UserFilter {
                     phrase: "fancy".to_string(),
                     levenshtein: Some(1),
+                    wildcard: false,
                 })))
             )
         );
@@ -380,6 +575,7 @@ mod tests {
                 Box::new(UserAST::Leaf(Box::new(UserFilter {
                     phrase: "fancy".to_string(),
                     levenshtein: None,
+                    wildcard: false,
                 })))
             )
         );
@@ -395,6 +591,7 @@ mod tests {
             UserAST::Leaf(Box::new(UserFilter {
                 phrase: "field:fancy".to_string(),
                 levenshtein: None,
+                wildcard: false,
             }))
         );
     }
@@ -414,11 +611,8 @@ mod tests {
     #[test]
     fn test_attribute_errors() {
         assert_eq!(
-            parse("fancy:"),
-            Err(ParseError::UnexpectedTokenType(
-                "fancy:﹏﹏".to_string(),
-                "only token or ( allowed after attribute ('attr:') ".to_string()
-            )) // Err(UnexpectedTokenType("Expecting a levenshtein number after a \'~\' at position 2, but got None".to_string()))
+            parse("fancy:").unwrap_err().to_string(),
+            "only token or ( allowed after attribute ('attr:') at position 6, expected one of: term, '('\nfancy:\n      ^"
         );
     }
 
@@ -473,10 +667,97 @@ mod tests {
         test_parse_query_to_ast_helper("a OR b AND c", "(\"a\" OR (\"b\" AND \"c\"))");
     }
 
+    #[test]
+    fn test_exists() {
+        assert_eq!(parse("_exists_:ean").unwrap(), UserAST::Exists("ean".to_string()));
+        test_parse_query_to_ast_helper("shirt AND _exists_:ean", "(\"shirt\" AND _exists_:ean)");
+        test_parse_query_to_ast_helper("_exists_:\"variants[].size\" a", "(_exists_:variants[].size OR \"a\")");
+        let err = parse("_exists_:(ean)").unwrap_err();
+        assert_eq!(err.message, "only a field name allowed after '_exists_:'");
+        assert_eq!(err.position, 9);
+    }
+
+    #[test]
+    fn test_boost() {
+        assert_eq!(parse("fancy^2").unwrap(), UserAST::Boosted("fancy".into(), OrderedFloat(2.0)));
+        test_parse_query_to_ast_helper("fancy~1^2.5 cool", "(\"fancy\"~1^2.5 OR \"cool\")");
+        test_parse_query_to_ast_helper("(super cool)^3 fancy", "((\"super\" OR \"cool\")^3 OR \"fancy\")");
+        test_parse_query_to_ast_helper("title:harry^2", "title:\"harry\"^2");
+        test_parse_query_to_ast_helper("title:(harry potter)^3 OR author:rowling", "(title:(\"harry\" OR \"potter\")^3 OR author:\"rowling\")");
+        let err = parse("fancy^").unwrap_err();
+        assert_eq!(err.message, "Expecting a boost number after a '^'");
+        assert_eq!(err.position, 6);
+        let err = parse("fancy^-1").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::ExpectedNumber);
+        assert_eq!(err.message, "Expected positive number after caret to define boost but got \"-1\"");
+        assert_eq!(err.position, 6);
+        assert_eq!(parse("^2").is_err(), true);
+    }
+
+    #[test]
+    fn test_boost_disabled() {
+        let opt = Options {
+            no_boost: true,
+            ..Default::default()
+        };
+        assert_eq!(parse_with_opt("fancy^2", opt).unwrap(), "fancy^2".into());
+    }
+
+    #[test]
+    fn test_attributed_block_scope() {
+        // the attribute only applies to the group
+        test_parse_query_to_ast_helper("field:(fancy unlimited) cool", "(field:(\"fancy\" OR \"unlimited\") OR \"cool\")");
+        test_parse_query_to_ast_helper("field:(fancy unlimited) AND other:cool", "(field:(\"fancy\" OR \"unlimited\") AND other:\"cool\")");
+    }
+
     #[test]
     fn test_parse_multi_literals() {
         test_parse_query_to_ast_helper("a b", "(\"a\" OR \"b\")");
         test_parse_query_to_ast_helper("\"a b\"", "\"a b\"");
         test_parse_query_to_ast_helper("feld:10 b", "(feld:\"10\" OR \"b\")");
     }
+
+    #[test]
+    fn test_error_position() {
+        let err = parse("cool AND (nice").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnexpectedTokenType);
+        assert_eq!(err.message, "Unexpected end of query");
+        assert_eq!(err.position, 14);
+        assert_eq!(err.expected, ["')'"]);
+        assert_eq!(parse("cool AND ").unwrap_err().expected, ["attribute", "term", "'('"]);
+
+        // trailing unbalanced ')' are ignored, but not the tokens after them
+        assert_eq!(parse("cool))").unwrap(), "cool".into());
+        let err = parse("schön) cool").unwrap_err();
+        assert_eq!(err.to_string(), "Unexpected ')' at position 6\nschön) cool\n     ^");
+
+        assert_eq!(parse("cool ()").unwrap_err().kind, ParseErrorKind::EmptyParentheses);
+        assert_eq!(parse(" OR cool").unwrap_err().message, "Unexpected OR");
+        assert_eq!(parse("").unwrap_err().message, "Unexpected end of query");
+    }
+
+    #[test]
+    fn test_implicit_or_before_parentheses() {
+        test_parse_query_to_ast_helper("cool (nice)", "(\"cool\" OR \"nice\")");
+    }
+
+    #[test]
+    fn test_lenient() {
+        let opt = Options {
+            lenient: true,
+            ..Default::default()
+        };
+        let test_lenient = |query: &str, expected: &str| assert_eq!(format!("{:?}", parse_with_opt(query, opt).unwrap()), expected);
+        test_lenient("(cool AND nice", "(\"cool\" AND \"nice\")");
+        test_lenient("cool) nice)", "(\"cool\" OR \"nice\")");
+        test_lenient("cool () (()) nice", "(\"cool\" OR \"nice\")");
+        test_lenient("\"cool nice", "(\"cool\" OR \"nice\")");
+        test_lenient("cool AND ", "\"cool\"");
+        test_lenient(" OR cool AND OR nice", "(\"cool\" OR \"nice\")");
+        test_lenient("fancy~ cool^x", "(\"fancy\" OR (\"cool\" OR \"x\"))");
+        test_lenient("fancy: field:(cool)^2", "(\"fancy\" OR field:\"cool\"^2)");
+        test_lenient("_exists_:(ean)", "(\"_exists_\" OR \"ean\")");
+        test_lenient("title:(harry potter", "title:(\"harry\" OR \"potter\")");
+        assert_eq!(parse_with_opt("( )", opt).is_err(), true);
+    }
 }
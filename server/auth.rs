@@ -0,0 +1,237 @@
+use rocket::{
+    http::Status,
+    request::{self, FromRequest},
+    Outcome, Request, State,
+};
+use std::{
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+use veloci::error::VelociError;
+
+/// Environment variable with the path to the api key file. Authentication is disabled, when it is not set.
+pub(crate) const API_KEYS_ENV: &str = "VELOCI_API_KEYS";
+
+/// The operation classes a key can be scoped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Operation {
+    /// searching and loading documents
+    Search,
+    Suggest,
+    /// index management, inspecting internals and metrics
+    Admin,
+}
+
+/// An api key with the databases and operations it is allowed to access
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ApiKey {
+    key: String,
+    /// Database names, `"*"` allows all databases and the endpoints, which are not bound to a database
+    databases: Vec<String>,
+    operations: Vec<Operation>,
+    /// Mandatory filter query, e.g. `tenant:acme`, which is intersected into every search and suggest of the key
+    #[serde(default)]
+    filter: Option<String>,
+}
+
+impl ApiKey {
+    /// `database` is `None` for endpoints, which are not bound to a database, e.g. `/_databases`
+    pub(crate) fn allows(&self, database: Option<&str>, operation: Operation) -> bool {
+        let database_allowed = self.databases.iter().any(|allowed| match database {
+            Some(database) => allowed == "*" || allowed == database,
+            None => allowed == "*",
+        });
+        database_allowed && self.operations.contains(&operation)
+    }
+}
+
+/// The api key file, e.g.
+/// ```toml
+/// [[keys]]
+/// key = "secret"
+/// databases = ["products"]
+/// operations = ["search", "suggest"]
+/// filter = "tenant:acme"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct ApiKeys {
+    #[serde(default)]
+    keys: Vec<ApiKey>,
+}
+
+impl ApiKeys {
+    pub(crate) fn parse(config: &str) -> Result<ApiKeys, VelociError> {
+        Ok(toml::from_str(config)?)
+    }
+
+    fn find(&self, key: &str) -> Option<&ApiKey> {
+        self.keys.iter().find(|api_key| constant_time_eq(api_key.key.as_bytes(), key.as_bytes()))
+    }
+}
+
+/// Compares without returning early, so the time does not leak how much of a key matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Managed state of the server
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AuthConfig {
+    /// `None` disables authentication
+    pub(crate) api_keys: Option<ApiKeys>,
+    /// Name of a request header with a mandatory filter query, see `config::AuthSettings::filter_header`
+    pub(crate) filter_header: Option<String>,
+}
+
+impl AuthConfig {
+    /// Loads the api keys from `api_keys_file` or from the file in the env variable `VELOCI_API_KEYS`
+    pub(crate) fn load(api_keys_file: Option<&Path>, filter_header: Option<String>) -> Result<AuthConfig, VelociError> {
+        let path = api_keys_file.map(PathBuf::from).or_else(|| std::env::var_os(API_KEYS_ENV).map(PathBuf::from));
+        let api_keys = match path {
+            Some(path) => Some(ApiKeys::parse(&std::fs::read_to_string(&path)?)?),
+            None => None,
+        };
+        Ok(AuthConfig { api_keys, filter_header })
+    }
+}
+
+/// The database of the request, if the route is bound to a database
+pub(crate) fn database_param(request: &Request<'_>) -> Option<String> {
+    let route = request.route()?;
+    if route.uri.path().starts_with("/<database>") {
+        request.get_param::<String>(0).and_then(|database| database.ok())
+    } else {
+        None
+    }
+}
+
+/// Request guard for the api key, passed via the `X-Api-Key` header or as `Authorization: Bearer <key>`.
+///
+/// Responds with 401, if the key is missing or unknown. The key is `None`, if authentication is disabled.
+pub(crate) struct Authenticated {
+    api_key: Option<ApiKey>,
+    filters: Vec<String>,
+}
+
+impl Authenticated {
+    pub(crate) fn allows(&self, database: Option<&str>, operation: Operation) -> bool {
+        self.api_key.as_ref().map(|api_key| api_key.allows(database, operation)).unwrap_or(true)
+    }
+
+    /// The mandatory filter queries of the api key and the filter header, which need to be applied on every search and suggest
+    pub(crate) fn filters(&self) -> &[String] {
+        &self.filters
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Authenticated {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, ()> {
+        let config = match request.guard::<State<AuthConfig>>() {
+            Outcome::Success(config) => config,
+            _ => return Outcome::Failure((Status::InternalServerError, ())),
+        };
+        let headers = request.headers();
+        let header_filter = match &config.filter_header {
+            Some(filter_header) => match get_filter_header(headers, filter_header) {
+                Ok(filter) => filter,
+                Err(()) => return Outcome::Failure((Status::BadRequest, ())),
+            },
+            None => None,
+        };
+        let api_key = match &config.api_keys {
+            Some(api_keys) => {
+                let key = headers
+                    .get_one("X-Api-Key")
+                    .or_else(|| headers.get_one("Authorization").and_then(|value| value.trim_start().strip_prefix("Bearer ")))
+                    .map(str::trim);
+                match key.and_then(|key| api_keys.find(key)) {
+                    Some(api_key) => Some(api_key.clone()),
+                    None => return Outcome::Failure((Status::Unauthorized, ())),
+                }
+            }
+            None => None,
+        };
+        let filters = api_key
+            .as_ref()
+            .and_then(|api_key| api_key.filter.as_deref())
+            .into_iter()
+            .chain(header_filter)
+            .map(str::to_string)
+            .collect();
+        Outcome::Success(Authenticated { api_key, filters })
+    }
+}
+
+/// The filter of the trusted header, requests with multiple or empty values are rejected,
+/// so a client can't replace the filter of the proxy with its own value
+fn get_filter_header<'h>(headers: &'h rocket::http::HeaderMap<'_>, filter_header: &str) -> Result<Option<&'h str>, ()> {
+    let mut values = headers.get(filter_header);
+    let filter = match (values.next(), values.next()) {
+        (None, _) => return Ok(None),
+        (Some(filter), None) => filter.trim(),
+        (Some(_), Some(_)) => return Err(()),
+    };
+    if filter.is_empty() {
+        Err(())
+    } else {
+        Ok(Some(filter))
+    }
+}
+
+/// Operation class of an `Authorized` guard
+pub(crate) trait OperationClass {
+    const OPERATION: Operation;
+}
+
+pub(crate) struct Search;
+impl OperationClass for Search {
+    const OPERATION: Operation = Operation::Search;
+}
+
+pub(crate) struct Suggest;
+impl OperationClass for Suggest {
+    const OPERATION: Operation = Operation::Suggest;
+}
+
+pub(crate) struct Admin;
+impl OperationClass for Admin {
+    const OPERATION: Operation = Operation::Admin;
+}
+
+/// Request guard, which checks the api key is allowed to do `O` on the database of the route.
+///
+/// Responds with 401 for missing or unknown keys and with 403, if the key is not allowed.
+pub(crate) struct Authorized<O: OperationClass> {
+    filters: Vec<String>,
+    operation: PhantomData<O>,
+}
+
+impl<O: OperationClass> Authorized<O> {
+    /// See `Authenticated::filters`
+    pub(crate) fn filters(&self) -> &[String] {
+        &self.filters
+    }
+}
+
+impl<'a, 'r, O: OperationClass> FromRequest<'a, 'r> for Authorized<O> {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, ()> {
+        let authenticated = match Authenticated::from_request(request) {
+            Outcome::Success(authenticated) => authenticated,
+            Outcome::Failure(failure) => return Outcome::Failure(failure),
+            Outcome::Forward(forward) => return Outcome::Forward(forward),
+        };
+        if authenticated.allows(database_param(request).as_deref(), O::OPERATION) {
+            Outcome::Success(Authorized {
+                filters: authenticated.filters,
+                operation: PhantomData,
+            })
+        } else {
+            Outcome::Failure((Status::Forbidden, ()))
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+use veloci::indices::metadata::LoadingType;
+
+/// Environment variable with the path to the server config, defaults to `veloci-server.toml`
+pub(crate) const CONFIG_ENV: &str = "VELOCI_SERVER_CONFIG";
+const DEFAULT_CONFIG_FILE: &str = "veloci-server.toml";
+
+/// The server config, e.g.
+/// ```toml
+/// address = "0.0.0.0"
+/// port = 8000
+/// data_dir = "/var/lib/veloci"
+/// gzip_min_size = 1024
+/// default_top = 10
+/// max_top = 1000
+/// timeout_ms = 5000
+///
+/// [cors]
+/// allowed_origins = ["https://example.com"]
+///
+/// [auth]
+/// api_keys_file = "api_keys.toml"
+/// filter_header = "X-Veloci-Filter"
+///
+/// [query_log]
+/// path = "/var/log/veloci/queries.jsonl"
+/// slow_threshold_ms = 100
+/// plan_steps = true
+///
+/// [databases.products]
+/// loading_type = "Disk"
+/// ```
+/// All settings are optional, the defaults are the settings of the server without config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ServerConfig {
+    /// Bind address, defaults to the rocket default
+    pub(crate) address: Option<String>,
+    /// Port, defaults to the rocket default
+    pub(crate) port: Option<u16>,
+    /// Folder containing the databases
+    #[serde(default = "default_data_dir")]
+    pub(crate) data_dir: PathBuf,
+    /// Responses smaller than this many bytes are not compressed
+    #[serde(default)]
+    pub(crate) gzip_min_size: usize,
+    /// `top` of a search, if the request sets none. Defaults to 10
+    pub(crate) default_top: Option<usize>,
+    /// Upper limit of `top` of a search
+    pub(crate) max_top: Option<usize>,
+    /// Timeout of a search, if the request sets none
+    pub(crate) timeout_ms: Option<u64>,
+    /// Memory budget of a search in bytes, requests can only set a lower one. Defaults to the env variable VELOCI_MAX_MEMORY or 2GB
+    pub(crate) max_memory: Option<usize>,
+    #[serde(default)]
+    pub(crate) cors: CorsConfig,
+    #[serde(default)]
+    pub(crate) auth: AuthSettings,
+    /// Logs searches and suggests, disabled without
+    pub(crate) query_log: Option<QueryLogConfig>,
+    /// Databases to preload at startup
+    #[serde(default)]
+    pub(crate) databases: BTreeMap<String, DatabaseConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CorsConfig {
+    /// Allowed origins, `"*"` allows all origins
+    #[serde(default = "default_allowed_origins")]
+    pub(crate) allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AuthSettings {
+    /// Path to the api key file, see `auth::ApiKeys`. Defaults to the env variable VELOCI_API_KEYS, authentication is disabled without both.
+    pub(crate) api_keys_file: Option<PathBuf>,
+    /// Request header with a mandatory filter query, e.g. `X-Veloci-Filter: tenant:acme`, which is intersected into every search and suggest.
+    /// Only for servers behind a proxy, which sets the header, clients can just omit it. Requests with multiple or empty values are rejected.
+    pub(crate) filter_header: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct QueryLogConfig {
+    /// NDJSON file, the requests are appended as one `query_log::QueryLogEntry` per line
+    pub(crate) path: PathBuf,
+    /// Only requests taking at least this long are logged, all requests without
+    pub(crate) slow_threshold_ms: Option<u64>,
+    /// Logs the timings of the plan steps of searches. Every search is then profiled, which adds overhead, also with `slow_threshold_ms`
+    #[serde(default)]
+    pub(crate) plan_steps: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DatabaseConfig {
+    /// Overrules the loading type of the indices
+    pub(crate) loading_type: Option<LoadingType>,
+}
+
+fn default_data_dir() -> PathBuf {
+    PathBuf::from(".")
+}
+
+fn default_allowed_origins() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: default_allowed_origins(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        toml::from_str("").expect("the default config is valid")
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ConfigError {
+    path: PathBuf,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid server config {:?}: {}", self.path, self.message)
+    }
+}
+
+impl ServerConfig {
+    /// Loads the config from the path in `VELOCI_SERVER_CONFIG` or from `veloci-server.toml`, if it exists
+    pub(crate) fn load() -> Result<ServerConfig, ConfigError> {
+        match std::env::var_os(CONFIG_ENV) {
+            Some(path) => Self::from_file(PathBuf::from(path)),
+            None if PathBuf::from(DEFAULT_CONFIG_FILE).exists() => Self::from_file(PathBuf::from(DEFAULT_CONFIG_FILE)),
+            None => Ok(ServerConfig::default()),
+        }
+    }
+
+    pub(crate) fn from_file(path: PathBuf) -> Result<ServerConfig, ConfigError> {
+        let error = |message: String| ConfigError { path: path.clone(), message };
+        let content = std::fs::read_to_string(&path).map_err(|err| error(format!("could not read file: {}", err)))?;
+        let config: ServerConfig = toml::from_str(&content).map_err(|err| error(err.to_string()))?;
+        config.validate().map_err(error)?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.data_dir.is_dir() {
+            return Err(format!("data_dir {:?} is not a directory", self.data_dir));
+        }
+        if let (Some(default_top), Some(max_top)) = (self.default_top, self.max_top) {
+            if default_top > max_top {
+                return Err(format!("default_top {} is greater than max_top {}", default_top, max_top));
+            }
+        }
+        if self.timeout_ms == Some(0) {
+            return Err("timeout_ms needs to be greater than 0".to_string());
+        }
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && !origin.starts_with("http://") && !origin.starts_with("https://") {
+                return Err(format!("invalid cors origin {:?}, expected \"*\" or an url like \"https://example.com\"", origin));
+            }
+        }
+        if let Some(api_keys_file) = &self.auth.api_keys_file {
+            if !api_keys_file.is_file() {
+                return Err(format!("auth.api_keys_file {:?} does not exist", api_keys_file));
+            }
+        }
+        if let Some(filter_header) = &self.auth.filter_header {
+            if filter_header.is_empty() || !filter_header.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(format!("invalid auth.filter_header {:?}, expected a header name like \"X-Veloci-Filter\"", filter_header));
+            }
+        }
+        if let Some(query_log) = &self.query_log {
+            let folder = query_log.path.parent().filter(|folder| !folder.as_os_str().is_empty());
+            if query_log.path.is_dir() || !folder.map(|folder| folder.is_dir()).unwrap_or(true) {
+                return Err(format!("query_log.path {:?} needs to be a file in an existing folder", query_log.path));
+            }
+        }
+        for name in self.databases.keys() {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                return Err(format!("invalid database name {:?}, only a-z, A-Z, 0-9, '_' and '-' are allowed", name));
+            }
+            if !self.database_path(name).join("metaData.json").is_file() {
+                return Err(format!("database {:?} not found in data_dir {:?}", name, self.data_dir));
+            }
+        }
+        Ok(())
+    }
+
+    /// Folder of the database
+    pub(crate) fn database_path(&self, database: &str) -> PathBuf {
+        self.data_dir.join(database)
+    }
+
+    pub(crate) fn loading_type(&self, database: &str) -> Option<LoadingType> {
+        self.databases.get(database).and_then(|database| database.loading_type)
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{atomic::Ordering, Mutex},
+};
+use veloci::{error::VelociError, persistence::Persistence};
+
+/// Upper bounds of the search latency buckets in seconds
+const LATENCY_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+/// Upper bounds of the result count buckets
+const NUM_HITS_BUCKETS: &[f64] = &[0.0, 1.0, 10.0, 100.0, 1_000.0, 10_000.0, 100_000.0, 1_000_000.0];
+
+lazy_static! {
+    static ref METRICS: Mutex<Metrics> = Mutex::new(Metrics::default());
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    buckets: &'static [f64],
+    /// count per bucket, not cumulative
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Histogram {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(pos) = self.buckets.iter().position(|upper_bound| value <= *upper_bound) {
+            self.counts[pos] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (upper_bound, count) in self.buckets.iter().zip(self.counts.iter()) {
+            cumulative += count;
+            writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, upper_bound, cumulative).unwrap();
+        }
+        writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, self.count).unwrap();
+        writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum).unwrap();
+        writeln!(out, "{}_count{{{}}} {}", name, labels, self.count).unwrap();
+    }
+}
+
+/// The metrics of a loaded database, collected when the metrics are rendered
+#[derive(Debug)]
+pub(crate) struct DatabaseStats {
+    name: String,
+    num_docs: u64,
+    /// (size in bytes, type, path)
+    index_sizes: Vec<(usize, String, String)>,
+    term_boost_cache_hits: u64,
+    term_boost_cache_misses: u64,
+}
+
+impl DatabaseStats {
+    pub(crate) fn new(name: &str, persistence: &Persistence) -> Self {
+        DatabaseStats {
+            name: name.to_string(),
+            num_docs: persistence.get_number_of_documents(),
+            index_sizes: persistence.get_index_sizes(),
+            term_boost_cache_hits: persistence.term_boost_cache_stats.hits.load(Ordering::Relaxed),
+            term_boost_cache_misses: persistence.term_boost_cache_stats.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    /// (database, endpoint, status) -> count
+    requests: BTreeMap<(String, String, u16), u64>,
+    /// (database, endpoint) -> search execution time
+    search_latency: BTreeMap<(String, String), Histogram>,
+    /// (database, endpoint) -> number of hits
+    search_num_hits: BTreeMap<(String, String), Histogram>,
+    /// (database, endpoint, error variant) -> count
+    errors: BTreeMap<(String, String, &'static str), u64>,
+}
+
+pub(crate) fn record_request(database: &str, endpoint: &str, status: u16) {
+    let mut metrics = METRICS.lock().unwrap();
+    *metrics.requests.entry((database.to_string(), endpoint.to_string(), status)).or_insert(0) += 1;
+}
+
+pub(crate) fn record_search(database: &str, endpoint: &str, execution_time_ns: u64, num_hits: u64) {
+    let key = (database.to_string(), endpoint.to_string());
+    let mut metrics = METRICS.lock().unwrap();
+    metrics
+        .search_latency
+        .entry(key.clone())
+        .or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+        .observe(execution_time_ns as f64 / 1_000_000_000.0);
+    metrics
+        .search_num_hits
+        .entry(key)
+        .or_insert_with(|| Histogram::new(NUM_HITS_BUCKETS))
+        .observe(num_hits as f64);
+}
+
+pub(crate) fn record_error(database: &str, endpoint: &str, err: &VelociError) {
+    let mut metrics = METRICS.lock().unwrap();
+    *metrics.errors.entry((database.to_string(), endpoint.to_string(), err.variant_name())).or_insert(0) += 1;
+}
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, metric_type: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, metric_type).unwrap();
+}
+
+/// Renders the metrics in the prometheus text format
+pub(crate) fn render(databases: &[DatabaseStats]) -> String {
+    let metrics = METRICS.lock().unwrap();
+    let mut out = String::new();
+
+    write_header(&mut out, "veloci_http_requests_total", "Number of http requests.", "counter");
+    for ((database, endpoint, status), count) in &metrics.requests {
+        writeln!(
+            out,
+            "veloci_http_requests_total{{database=\"{}\",endpoint=\"{}\",status=\"{}\"}} {}",
+            escape_label_value(database),
+            escape_label_value(endpoint),
+            status,
+            count
+        )
+        .unwrap();
+    }
+
+    write_header(&mut out, "veloci_search_duration_seconds", "Execution time of the searches.", "histogram");
+    for ((database, endpoint), histogram) in &metrics.search_latency {
+        let labels = format!("database=\"{}\",endpoint=\"{}\"", escape_label_value(database), escape_label_value(endpoint));
+        histogram.write(&mut out, "veloci_search_duration_seconds", &labels);
+    }
+
+    write_header(&mut out, "veloci_search_hits", "Number of hits of the searches.", "histogram");
+    for ((database, endpoint), histogram) in &metrics.search_num_hits {
+        let labels = format!("database=\"{}\",endpoint=\"{}\"", escape_label_value(database), escape_label_value(endpoint));
+        histogram.write(&mut out, "veloci_search_hits", &labels);
+    }
+
+    // other failed requests, e.g. invalid requests or admin endpoints, are only in veloci_http_requests_total by status
+    write_header(&mut out, "veloci_errors_total", "Number of failed searches, suggests, explains and query generations by error.", "counter");
+    for ((database, endpoint, error), count) in &metrics.errors {
+        writeln!(
+            out,
+            "veloci_errors_total{{database=\"{}\",endpoint=\"{}\",error=\"{}\"}} {}",
+            escape_label_value(database),
+            escape_label_value(endpoint),
+            error,
+            count
+        )
+        .unwrap();
+    }
+
+    write_header(&mut out, "veloci_documents", "Number of documents of the loaded databases.", "gauge");
+    for database in databases {
+        writeln!(out, "veloci_documents{{database=\"{}\"}} {}", escape_label_value(&database.name), database.num_docs).unwrap();
+    }
+
+    write_header(&mut out, "veloci_index_size_bytes", "Size of the fsts in memory and of the other indices and the document store on disk.", "gauge");
+    for database in databases {
+        for (size, index_type, path) in &database.index_sizes {
+            writeln!(
+                out,
+                "veloci_index_size_bytes{{database=\"{}\",type=\"{}\",path=\"{}\"}} {}",
+                escape_label_value(&database.name),
+                escape_label_value(index_type),
+                escape_label_value(path),
+                size
+            )
+            .unwrap();
+        }
+    }
+
+    write_header(&mut out, "veloci_cache_hits_total", "Number of cache hits.", "counter");
+    for database in databases {
+        writeln!(
+            out,
+            "veloci_cache_hits_total{{database=\"{}\",cache=\"term_boost\"}} {}",
+            escape_label_value(&database.name),
+            database.term_boost_cache_hits
+        )
+        .unwrap();
+    }
+
+    write_header(&mut out, "veloci_cache_misses_total", "Number of cache misses.", "counter");
+    for database in databases {
+        writeln!(
+            out,
+            "veloci_cache_misses_total{{database=\"{}\",cache=\"term_boost\"}} {}",
+            escape_label_value(&database.name),
+            database.term_boost_cache_misses
+        )
+        .unwrap();
+    }
+
+    out
+}
@@ -0,0 +1,111 @@
+use crate::config::QueryLogConfig;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    sync::Mutex,
+    time::Duration,
+};
+use veloci::{error::VelociError, plan_creator::profile::PlanStepProfile, search, search_field::SuggestFieldResult};
+
+/// One line of the query log
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct QueryLogEntry {
+    /// RFC 3339 timestamp of the end of the request
+    pub(crate) timestamp: String,
+    pub(crate) database: String,
+    pub(crate) endpoint: String,
+    /// The request as executed, with the defaults and mandatory filters of the server applied
+    pub(crate) request: search::Request,
+    pub(crate) num_hits: u64,
+    pub(crate) execution_time_ns: u64,
+    /// Timings of the plan steps, only for searches and with `plan_steps` in the config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) plan_steps: Option<PlanStepProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) error: Option<String>,
+}
+
+/// Appends searches and suggests as NDJSON lines to a file, optionally only the ones above a latency threshold
+#[derive(Debug)]
+pub(crate) struct QueryLog {
+    file: Mutex<File>,
+    slow_threshold: Option<Duration>,
+    plan_steps: bool,
+}
+
+impl QueryLog {
+    pub(crate) fn open(config: &QueryLogConfig) -> Result<QueryLog, VelociError> {
+        let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+        Ok(QueryLog {
+            file: Mutex::new(file),
+            slow_threshold: config.slow_threshold_ms.map(Duration::from_millis),
+            plan_steps: config.plan_steps,
+        })
+    }
+
+    /// Searches need to be profiled to log the timings of the plan steps
+    pub(crate) fn logs_plan_steps(&self) -> bool {
+        self.plan_steps
+    }
+
+    pub(crate) fn log_search(&self, database: &str, endpoint: &str, request: search::Request, result: &Result<search::SearchResult, VelociError>, elapsed: Duration) {
+        let entry = match result {
+            Ok(result) => QueryLogEntry {
+                num_hits: result.num_hits,
+                execution_time_ns: result.execution_time_ns,
+                plan_steps: if self.plan_steps { result.profile.clone() } else { None },
+                ..QueryLogEntry::new(database, endpoint, request, elapsed)
+            },
+            Err(err) => QueryLogEntry {
+                error: Some(err.to_string()),
+                ..QueryLogEntry::new(database, endpoint, request, elapsed)
+            },
+        };
+        self.write(&entry);
+    }
+
+    pub(crate) fn log_suggest(&self, database: &str, endpoint: &str, request: search::Request, result: &Result<SuggestFieldResult, VelociError>, elapsed: Duration) {
+        let entry = match result {
+            Ok(hits) => QueryLogEntry {
+                num_hits: hits.len() as u64,
+                ..QueryLogEntry::new(database, endpoint, request, elapsed)
+            },
+            Err(err) => QueryLogEntry {
+                error: Some(err.to_string()),
+                ..QueryLogEntry::new(database, endpoint, request, elapsed)
+            },
+        };
+        self.write(&entry);
+    }
+
+    fn write(&self, entry: &QueryLogEntry) {
+        if let Some(slow_threshold) = self.slow_threshold {
+            if u128::from(entry.execution_time_ns) < slow_threshold.as_nanos() {
+                return;
+            }
+        }
+        let mut line = serde_json::to_string(entry).unwrap();
+        line.push('\n');
+        // one write per line, so concurrent requests don't interleave
+        if let Err(err) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            warn!("Could not write query log: {}", err);
+        }
+    }
+}
+
+impl QueryLogEntry {
+    fn new(database: &str, endpoint: &str, request: search::Request, elapsed: Duration) -> Self {
+        QueryLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            database: database.to_string(),
+            endpoint: endpoint.to_string(),
+            request,
+            num_hits: 0,
+            execution_time_ns: elapsed.as_nanos() as u64,
+            plan_steps: None,
+            error: None,
+        }
+    }
+}
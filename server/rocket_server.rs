@@ -16,27 +16,35 @@ extern crate serde_derive;
 #[macro_use]
 extern crate measure_time;
 
+mod auth;
+mod config;
+mod metrics;
+mod query_log;
 #[cfg(test)]
 mod tests;
 
 use chashmap::CHashMap;
 use flate2::read::GzEncoder;
-// use multipart::server::{
-//     save::{Entries, SaveResult::*},
-//     Multipart,
-// };
+use multipart::server::{
+    save::{Entries, SaveResult::*},
+    Multipart,
+};
+use rayon::prelude::*;
 use rocket::{
     fairing,
     http::{ContentType, Method, Status},
     request::LenientForm,
-    response::{self, status::Custom, Responder, Response},
-    Request,
+    response::{self, content::Content, status::Custom, Responder, Response},
+    Data, Request,
 };
 use rocket_contrib::json::Json;
 use rocket_cors::{AllowedHeaders, AllowedOrigins};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
     io::{prelude::*, Cursor},
+    sync::Mutex,
+    time::{Duration, Instant},
 };
 use veloci::{
     doc_store::*,
@@ -46,7 +54,21 @@ use veloci::{
 };
 
 lazy_static! {
+    static ref CONFIG: config::ServerConfig = config::ServerConfig::load().unwrap_or_else(|err| panic!("{}", err));
     static ref PERSISTENCES: CHashMap<String, Persistence> = CHashMap::default();
+    /// Index creation jobs by job id, finished jobs are kept for `FINISHED_JOB_RETENTION` to query their status
+    static ref INDEX_JOBS: CHashMap<String, IndexJob> = CHashMap::default();
+    /// Databases reserved by a running index job, delete or reload, see `DatabaseReservation`
+    static ref RESERVED_DATABASES: Mutex<HashSet<String>> = Mutex::default();
+    /// Memory budget of a search in bytes, can be set in the config or with the env variable VELOCI_MAX_MEMORY. Requests can only lower it.
+    static ref MAX_MEMORY: usize = CONFIG
+        .max_memory
+        .or_else(|| std::env::var("VELOCI_MAX_MEMORY").ok().and_then(|val| val.parse().ok()))
+        .unwrap_or_else(|| usize::try_from(DEFAULT_MAX_MEMORY).unwrap_or(usize::max_value()));
+    static ref QUERY_LOG: Option<query_log::QueryLog> = CONFIG
+        .query_log
+        .as_ref()
+        .map(|config| query_log::QueryLog::open(config).unwrap_or_else(|err| panic!("Could not open query log {:?}: {}", config.path, err)));
     // static ref SHARDS: CHashMap<String, Shards> = { CHashMap::default() };
 }
 
@@ -54,6 +76,38 @@ lazy_static! {
 struct SearchResult(search::SearchResultWithDoc);
 // struct SearchErroro(VelociError);
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct IndexJob {
+    id: String,
+    database: String,
+    status: JobStatus,
+    error: Option<String>,
+    /// duration of the index creation, set when the job is finished
+    duration_ms: Option<u64>,
+    #[serde(skip)]
+    finished_at: Option<Instant>,
+}
+
+/// Memory budget of a search, if neither the config nor the env sets one
+const DEFAULT_MAX_MEMORY: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Finished jobs are removed after this time
+const FINISHED_JOB_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Serialize)]
+struct DatabaseInfo {
+    name: String,
+    num_docs: u64,
+}
+
 #[derive(Debug)]
 struct SuggestResult(search_field::SuggestFieldResult);
 
@@ -106,8 +160,11 @@ struct QueryParams {
     boost_queries: Option<String>,
     phrase_pairs: Option<String>,
     explain: Option<String>,
+    profile: Option<String>,
     text_locality: Option<String>,
     filter: Option<String>,
+    /// repair invalid queries instead of returning an error, e.g. for search boxes
+    lenient: Option<bool>,
 }
 
 // struct MyParam {
@@ -152,7 +209,8 @@ fn query_param_to_vec(name: Option<String>) -> Option<Vec<String>> {
 
 fn ensure_database(database: &String) -> Result<(), VelociError> {
     if !PERSISTENCES.contains_key(database) {
-        PERSISTENCES.insert(database.clone(), persistence::Persistence::load(database.clone())?);
+        let persistence = persistence::Persistence::load_with_loading_type(CONFIG.database_path(database), CONFIG.loading_type(database))?;
+        PERSISTENCES.insert(database.clone(), persistence);
     }
     Ok(())
 }
@@ -170,13 +228,55 @@ fn version() -> String {
     "0.7".to_string()
 }
 
-fn search_in_persistence(persistence: &Persistence, request: veloci::search::Request) -> Result<SearchResult, VelociError> {
+/// Metrics in the prometheus text format
+#[get("/metrics")]
+fn metrics_get(_auth: auth::Authorized<auth::Admin>) -> Content<String> {
+    let databases = std::cell::RefCell::new(vec![]);
+    PERSISTENCES.retain(|name, persistence| {
+        databases.borrow_mut().push(metrics::DatabaseStats::new(name, persistence));
+        true
+    });
+    Content(
+        ContentType::new("text", "plain").with_params(("version", "0.0.4")),
+        metrics::render(&databases.into_inner()),
+    )
+}
+
+/// Requests can lower the memory budget of the server, but not raise it
+fn apply_max_memory(request: &mut search::Request) {
+    request.max_memory = Some(request.max_memory.map_or(*MAX_MEMORY, |max_memory| max_memory.min(*MAX_MEMORY)));
+}
+
+/// `database` and `endpoint` are used as labels for the search metrics
+fn search_in_persistence(persistence: &Persistence, database: &str, endpoint: &str, mut request: veloci::search::Request) -> Result<SearchResult, VelociError> {
     // info!("Searching ... ");
+    apply_max_memory(&mut request);
+    request.timeout_ms = request.timeout_ms.or(CONFIG.timeout_ms);
+    let top = request.top.or(CONFIG.default_top).unwrap_or(10);
+    request.top = Some(CONFIG.max_top.map(|max_top| top.min(max_top)).unwrap_or(top));
     let select = request.select.clone();
-    let hits = {
+    // the query log may contain the plan step timings, the profile is only returned if requested
+    let return_profile = request.profile;
+    let logged_request = QUERY_LOG.as_ref().map(|_| request.clone());
+    if QUERY_LOG.as_ref().map_or(false, |query_log| query_log.logs_plan_steps()) {
+        request.profile = true;
+    }
+    let start = std::time::Instant::now();
+    let result = {
         info_time!("Searching ... ");
-        search::search(request, &persistence)?
+        search::search(request, &persistence)
     };
+    if let (Some(query_log), Some(logged_request)) = (QUERY_LOG.as_ref(), logged_request) {
+        query_log.log_search(database, endpoint, logged_request, &result, start.elapsed());
+    }
+    let mut hits = result.map_err(|err| {
+        metrics::record_error(database, endpoint, &err);
+        err
+    })?;
+    if !return_profile {
+        hits.profile = None;
+    }
+    metrics::record_search(database, endpoint, hits.execution_time_ns, hits.num_hits);
     info!("Loading Documents... ");
     let doc = {
         info_time!("Loading Documents...  ");
@@ -186,33 +286,155 @@ fn search_in_persistence(persistence: &Persistence, request: veloci::search::Req
     Ok(doc)
 }
 
-fn excute_suggest(persistence: &Persistence, struct_body: search::Request, _flame: bool) -> Result<SuggestResult, VelociError> {
+/// Intersects the mandatory filters of the api key and the filter header into the request, so the client can't remove them
+fn apply_mandatory_filters(persistence: &Persistence, request: &mut search::Request, filters: &[String]) -> Result<(), VelociError> {
+    for filter in filters {
+        request.add_filter(query_generator::filter_query(persistence, filter, None)?);
+    }
+    Ok(())
+}
+
+/// Routes returning documents or terms without a search can't apply mandatory filters, they are forbidden with one
+fn check_no_mandatory_filter(filters: &[String]) -> Result<(), Custom<String>> {
+    if filters.is_empty() {
+        Ok(())
+    } else {
+        Err(Custom(Status::Forbidden, "Not allowed with a mandatory filter".to_string()))
+    }
+}
+
+/// `database` and `endpoint` are used in the query log and as labels for the error metrics
+fn excute_suggest(persistence: &Persistence, database: &str, endpoint: &str, struct_body: search::Request) -> Result<SuggestResult, VelociError> {
     info_time!("search total");
     info!("Suggesting ... ");
-    let hits = search_field::suggest_multi(persistence, struct_body)?;
+    let logged_request = QUERY_LOG.as_ref().map(|_| struct_body.clone());
+    let start = std::time::Instant::now();
+    let result = search_field::suggest_multi(persistence, struct_body);
+    if let (Some(query_log), Some(logged_request)) = (QUERY_LOG.as_ref(), logged_request) {
+        query_log.log_suggest(database, endpoint, logged_request, &result, start.elapsed());
+    }
+    if let Err(err) = &result {
+        metrics::record_error(database, endpoint, err);
+    }
     debug!("Returning ... ");
-    Ok(SuggestResult(hits))
+    Ok(SuggestResult(result?))
 }
 
 #[post("/<database>/search", format = "application/json", data = "<request>")]
-fn search_post(database: String, request: Json<search::Request>) -> Result<SearchResult, VelociError> {
+fn search_post(auth: auth::Authorized<auth::Search>, database: String, request: Json<search::Request>) -> Result<SearchResult, VelociError> {
     ensure_database(&database)?;
     let persistence = PERSISTENCES.get(&database).unwrap();
 
-    search_in_persistence(&persistence, request.0)
+    let mut request = request.0;
+    apply_mandatory_filters(&persistence, &mut request, auth.filters())?;
+    search_in_persistence(&persistence, &database, "/<database>/search", request)
+}
+
+/// One search of a `/_msearch` batch
+#[derive(Debug, Deserialize)]
+struct MultiSearchItem {
+    database: String,
+    request: search::Request,
+}
+
+/// The result of one search of a `/_msearch` batch, failed searches don't fail the batch
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum MultiSearchResponse {
+    Ok(search::SearchResultWithDoc),
+    Err { error: String, status: u16 },
+}
+
+#[derive(Debug, Serialize)]
+struct MultiSearchResult {
+    responses: Vec<MultiSearchResponse>,
+}
+
+const MSEARCH_BODY_LIMIT: u64 = 16 * 1024 * 1024;
+
+fn execute_msearch_item(auth: &auth::Authenticated, item: Result<MultiSearchItem, serde_json::Error>) -> MultiSearchResponse {
+    let item = match item {
+        Ok(item) => item,
+        Err(err) => {
+            return MultiSearchResponse::Err {
+                error: format!("Invalid search: {}", err),
+                status: Status::BadRequest.code,
+            }
+        }
+    };
+    if !auth.allows(Some(&item.database), auth::Operation::Search) {
+        return MultiSearchResponse::Err {
+            error: format!("Not allowed to search in {:?}", item.database),
+            status: Status::Forbidden.code,
+        };
+    }
+    let mut request = item.request;
+    let res = ensure_database(&item.database).and_then(|_| {
+        // the database may have been deleted since `ensure_database`
+        let persistence = match PERSISTENCES.get(&item.database) {
+            Some(persistence) => persistence,
+            None => return Ok(None),
+        };
+        apply_mandatory_filters(&persistence, &mut request, auth.filters())?;
+        search_in_persistence(&persistence, &item.database, "/_msearch", request).map(Some)
+    });
+    match res {
+        Ok(Some(result)) => MultiSearchResponse::Ok(result.0),
+        Ok(None) => MultiSearchResponse::Err {
+            error: format!("Database {:?} not found", item.database),
+            status: Status::NotFound.code,
+        },
+        Err(err) => {
+            let Custom(status, error) = search_error_to_rocket_error(err);
+            MultiSearchResponse::Err { error, status: status.code }
+        }
+    }
+}
+
+/// Parses a json array or ndjson body. Each ndjson line is parsed on its own, so a malformed line only fails its search.
+fn parse_msearch_body(body: &str) -> Result<Vec<Result<MultiSearchItem, serde_json::Error>>, Custom<String>> {
+    if body.trim_start().starts_with('[') {
+        let values: Vec<serde_json::Value> = serde_json::from_str(body).map_err(|err| Custom(Status::BadRequest, format!("Invalid json: {}", err)))?;
+        return Ok(values.into_iter().map(serde_json::from_value).collect());
+    }
+    Ok(body.lines().filter(|line| !line.trim().is_empty()).map(serde_json::from_str).collect())
+}
+
+/// Runs a batch of searches in parallel. The body is a json array or ndjson of `{"database": "..", "request": {..}}` objects.
+///
+/// The responses are in the order of the searches, an invalid or failing search returns an error object at its position.
+/// Bodies larger than `MSEARCH_BODY_LIMIT` are rejected with 413.
+#[post("/_msearch", data = "<data>")]
+fn msearch(auth: auth::Authenticated, data: Data) -> Result<Json<MultiSearchResult>, Custom<String>> {
+    let mut body = String::new();
+    // read one byte more than the limit, to detect larger bodies
+    data.open()
+        .take(MSEARCH_BODY_LIMIT + 1)
+        .read_to_string(&mut body)
+        .map_err(|err| Custom(Status::BadRequest, format!("Could not read body: {}", err)))?;
+    if body.len() as u64 > MSEARCH_BODY_LIMIT {
+        return Err(Custom(Status::PayloadTooLarge, format!("The body is larger than {} bytes", MSEARCH_BODY_LIMIT)));
+    }
+
+    let items = parse_msearch_body(&body)?;
+    info_time!("msearch {} searches", items.len());
+    let responses = items.into_par_iter().map(|item| execute_msearch_item(&auth, item)).collect();
+    Ok(Json(MultiSearchResult { responses }))
 }
 
 #[get("/<database>/_idtree/<id>")]
-fn get_doc_for_id_tree(database: String, id: u32) -> Json<serde_json::Value> {
+fn get_doc_for_id_tree(auth: auth::Authorized<auth::Search>, database: String, id: u32) -> Result<Json<serde_json::Value>, Custom<String>> {
+    check_no_mandatory_filter(auth.filters())?;
     ensure_database(&database).unwrap();
     let persistence = PERSISTENCES.get(&database).unwrap();
     let all_fields = persistence.metadata.get_all_fields();
 
-    Json(search::read_data(&persistence, id, &all_fields).unwrap())
+    Ok(Json(search::read_data(&persistence, id, &all_fields).unwrap()))
 }
 
 #[get("/<database>/_id/<id>")]
-fn get_doc_for_id_direct(database: String, id: u32) -> Json<serde_json::Value> {
+fn get_doc_for_id_direct(auth: auth::Authorized<auth::Search>, database: String, id: u32) -> Result<Json<serde_json::Value>, Custom<String>> {
+    check_no_mandatory_filter(auth.filters())?;
     // let persistence = PERSISTENCES.get(&database).unwrap();
     // let fields = persistence.get_all_fields();
     // let tree = search::get_read_tree_from_fields(&persistence, &fields);
@@ -221,7 +443,7 @@ fn get_doc_for_id_direct(database: String, id: u32) -> Json<serde_json::Value> {
 
     let offsets = persistence.indices.doc_offsets.as_ref().unwrap();
     let f = persistence.get_mmap_handle("data").unwrap(); // TODO No unwrapo
-    Json(serde_json::from_str(&DocLoader::get_doc(&f, offsets, id as usize).unwrap()).unwrap())
+    Ok(Json(serde_json::from_str(&DocLoader::get_doc(&f, offsets, id as usize).unwrap()).unwrap()))
 }
 
 // #[get("/<database>/<id>")]
@@ -232,7 +454,7 @@ fn get_doc_for_id_direct(database: String, id: u32) -> Json<serde_json::Value> {
 //     search::read_tree(&persistence, 25000, &tree)
 // }
 
-fn search_from_query_params(database: String, params: QueryParams) -> Result<SearchResult, Custom<String>> {
+fn search_from_query_params(database: String, params: QueryParams, filters: &[String]) -> Result<SearchResult, Custom<String>> {
     ensure_database(&database).map_err(search_error_to_rocket_error)?;
     let persistence = PERSISTENCES.get(&database).unwrap();
 
@@ -284,10 +506,11 @@ fn search_from_query_params(database: String, params: QueryParams) -> Result<Sea
         boost_fields: boost_fields.transpose()?,
         boost_terms: boost_terms,
         explain: params.explain.map(|el| el.to_lowercase() == "true"),
+        profile: params.profile.map(|el| el.to_lowercase() == "true"),
         boost_queries: None,
         select: None,
         filter: params.filter,
-        parser_options: None,
+        parser_options: params.lenient.map(|lenient| query_generator::QueryParserOptions { lenient, ..Default::default() }),
         filter_parser_options: None,
     };
 
@@ -296,12 +519,16 @@ fn search_from_query_params(database: String, params: QueryParams) -> Result<Sea
         println!("{:?}", q_params.boost_queries);
     }
 
-    let mut request = query_generator::search_query(&persistence, q_params).map_err(|err| Custom(Status::BadRequest, format!("query_generation failed: {:?}", err)))?;
+    let mut request = query_generator::search_query(&persistence, q_params).map_err(|err| {
+        metrics::record_error(&database, "/<database>/search", &err);
+        Custom(Status::BadRequest, format!("query_generation failed: {}", err))
+    })?;
 
     request.select = query_param_to_vec(params.select);
+    apply_mandatory_filters(&persistence, &mut request, filters).map_err(search_error_to_rocket_error)?;
 
     debug!("{}", serde_json::to_string(&request).unwrap());
-    search_in_persistence(&persistence, request).map_err(search_error_to_rocket_error)
+    search_in_persistence(&persistence, &database, "/<database>/search", request).map_err(search_error_to_rocket_error)
 }
 
 // #[post("/<database>/search_smart", format = "application/json", data = "<request>")]
@@ -310,31 +537,68 @@ fn search_from_query_params(database: String, params: QueryParams) -> Result<Sea
 // }
 
 #[post("/<database>/search_query_params/explain_plan", format = "application/json", data = "<request>")]
-fn search_post_query_params_explain(database: String, request: Json<query_generator::SearchQueryGeneratorParameters>) -> Result<String, Custom<String>> {
+fn search_post_query_params_explain(
+    auth: auth::Authorized<auth::Search>,
+    database: String,
+    request: Json<query_generator::SearchQueryGeneratorParameters>,
+) -> Result<String, Custom<String>> {
     let q_params = request.0;
     ensure_database(&database).unwrap();
     let persistence = PERSISTENCES.get(&database).unwrap();
 
-    let mut request = query_generator::search_query(&persistence, q_params.clone()).map_err(|err| Custom(Status::BadRequest, format!("query_generation failed: {:?}", err)))?;
+    let endpoint = "/<database>/search_query_params/explain_plan";
+    let mut request = query_generator::search_query(&persistence, q_params.clone()).map_err(|err| {
+        metrics::record_error(&database, endpoint, &err);
+        Custom(Status::BadRequest, format!("query_generation failed: {}", err))
+    })?;
 
     request.select = query_param_to_vec(q_params.select);
+    apply_mandatory_filters(&persistence, &mut request, auth.filters()).map_err(search_error_to_rocket_error)?;
 
     debug!("{}", serde_json::to_string(&request).unwrap());
-    search::explain_plan(request, &persistence).map_err(search_error_to_rocket_error)
+    search::explain_plan(request, &persistence).map_err(|err| {
+        metrics::record_error(&database, endpoint, &err);
+        search_error_to_rocket_error(err)
+    })
+}
+
+#[post("/<database>/explain/<id>", format = "application/json", data = "<request>")]
+fn explain_hit_post(auth: auth::Authorized<auth::Search>, database: String, id: u32, request: Json<search::Request>) -> Result<Json<search::ExplainNode>, Custom<String>> {
+    ensure_database(&database).map_err(search_error_to_rocket_error)?;
+    let persistence = PERSISTENCES.get(&database).unwrap();
+
+    let mut request = request.0;
+    apply_max_memory(&mut request);
+    apply_mandatory_filters(&persistence, &mut request, auth.filters()).map_err(search_error_to_rocket_error)?;
+    search::explain_hit(request, id, &persistence)
+        .map_err(|err| {
+            metrics::record_error(&database, "/<database>/explain/<id>", &err);
+            search_error_to_rocket_error(err)
+        })?
+        .map(Json)
+        .ok_or_else(|| Custom(Status::NotFound, format!("document {} is not hit by the request", id)))
 }
 
 #[post("/<database>/search_query_params", format = "application/json", data = "<request>")]
-fn search_post_query_params(database: String, request: Json<query_generator::SearchQueryGeneratorParameters>) -> Result<SearchResult, Custom<String>> {
+fn search_post_query_params(
+    auth: auth::Authorized<auth::Search>,
+    database: String,
+    request: Json<query_generator::SearchQueryGeneratorParameters>,
+) -> Result<SearchResult, Custom<String>> {
     let q_params = request.0;
     ensure_database(&database).unwrap();
     let persistence = PERSISTENCES.get(&database).unwrap();
 
-    let mut request = query_generator::search_query(&persistence, q_params.clone()).map_err(|err| Custom(Status::BadRequest, format!("query_generation failed: {:?}", err)))?;
+    let mut request = query_generator::search_query(&persistence, q_params.clone()).map_err(|err| {
+        metrics::record_error(&database, "/<database>/search_query_params", &err);
+        Custom(Status::BadRequest, format!("query_generation failed: {}", err))
+    })?;
 
     request.select = query_param_to_vec(q_params.select);
+    apply_mandatory_filters(&persistence, &mut request, auth.filters()).map_err(search_error_to_rocket_error)?;
 
     debug!("{}", serde_json::to_string(&request).unwrap());
-    search_in_persistence(&persistence, request).map_err(search_error_to_rocket_error)
+    search_in_persistence(&persistence, &database, "/<database>/search_query_params", request).map_err(search_error_to_rocket_error)
 }
 
 // #[get("/<database>/search/explain_plan?<params..>")]
@@ -345,7 +609,7 @@ fn search_post_query_params(database: String, request: Json<query_generator::Sea
 //     let q_params = request.0;
 //     let persistence = PERSISTENCES.get(&database).unwrap();
 
-//     let mut request = query_generator::search_query(&persistence, q_params.clone()).map_err(|err| Custom(Status::BadRequest, format!("query_generation failed: {:?}", err)))?;
+//     let mut request = query_generator::search_query(&persistence, q_params.clone()).map_err(|err| Custom(Status::BadRequest, format!("query_generation failed: {}", err)))?;
 
 //     request.select = query_param_to_vec(q_params.select);
 
@@ -353,10 +617,10 @@ fn search_post_query_params(database: String, request: Json<query_generator::Sea
 // }
 
 #[get("/<database>/search?<params..>")]
-fn search_get(database: String, params: LenientForm<QueryParams>) -> Result<SearchResult, Custom<String>> {
+fn search_get(auth: auth::Authorized<auth::Search>, database: String, params: LenientForm<QueryParams>) -> Result<SearchResult, Custom<String>> {
     // let params = params.map_err(|err| Custom(Status::BadRequest, format!("{:let params: QueryParams = params.into_inner();?}", err)))?;
     let params: QueryParams = params.into_inner();
-    search_from_query_params(database, params)
+    search_from_query_params(database, params, auth.filters())
 }
 
 // #[get("/<database>/search_shard?<params..>")]
@@ -438,132 +702,255 @@ fn search_get(database: String, params: LenientForm<QueryParams>) -> Result<Sear
 fn search_error_to_rocket_error(err: VelociError) -> Custom<String> {
     match err {
         VelociError::StringError(msg) => Custom(Status::BadRequest, msg),
+        VelociError::QueryParserError(_) => Custom(Status::BadRequest, format!("{}", err)),
+        VelociError::Timeout { .. } => Custom(Status::RequestTimeout, format!("{}", err)),
+        VelociError::MemoryLimitExceeded { .. } => Custom(Status::BadRequest, format!("{}", err)),
         _ => Custom(Status::InternalServerError, format!("SearchError: {:?}", err)),
     }
 }
 
 // ******************************************** PERMISSION CRITICAL START ********************************************
 // ******************************************** UPLOAD UPLOAD ********************************************
-// #[post("/<database>", data = "<data>")]
-// // signature requires the request to have a `Content-Type`
-// fn multipart_upload(database: String, cont_type: &ContentType, data: Data) -> Result<String, Custom<String>> {
-//     // this and the next check can be implemented as a request guard but it seems like just
-//     // more boilerplate than necessary
-//     if !cont_type.is_form_data() {
-//         return Err(Custom(Status::BadRequest, "Content-Type not multipart/form-data".into()));
-//     }
 
-//     let (_, boundary) = cont_type
-//         .params()
-//         .find(|&(k, _)| k == "boundary")
-//         .ok_or_else(|| Custom(Status::BadRequest, "`Content-Type: multipart/form-data` boundary param not provided".into()))?;
-
-//     let resp = process_upload(boundary, data).map_err(search_error_to_rocket_error)?;
-
-//     veloci::create::create_indices_from_str(
-//         &mut veloci::persistence::Persistence::create(database.to_string()).unwrap(),
-//         &resp.0,
-//         &resp.1.unwrap_or("[]".to_string()),
-//         None,
-//         false,
-//     )
-//     .unwrap();
-//     Ok(format!("created {:?}", &database))
-// }
+/// Database names are folders relative to the working directory, names starting with `_` are reserved for the api
+fn check_database_name(database: &str) -> Result<(), Custom<String>> {
+    let valid = !database.is_empty() && !database.starts_with('_') && database.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(Custom(
+            Status::BadRequest,
+            format!(
+                "Invalid database name {:?}, only a-z, A-Z, 0-9, '_' and '-' are allowed and it may not start with '_'",
+                database
+            ),
+        ))
+    }
+}
 
-// fn process_upload(boundary: &str, data: Data) -> Result<(String, Option<String>), VelociError> {
-//     // let mut out = Vec::new();
-
-//     // saves all fields, any field longer than 10kB goes to a temporary directory
-//     // Entries could implement FromData though that would give zero control over
-//     // how the files are saved; Multipart would be a good impl candidate though
-//     match Multipart::with_body(data.open(), boundary).save().size_limit(500_000_000).temp() {
-//         Full(entries) => process_entries(entries),
-//         Partial(partial, reason) => {
-//             error!("Request partially processed: {:?}", reason);
-//             // writeln!(out, "Request partially processed: {:?}", reason)?;
-//             // if let Some(field) = partial.partial {
-//             //     writeln!(out, "Stopped on field: {:?}", field.source.headers)?;
-//             // }
-
-//             process_entries(partial.entries)
-//         }
-//         Error(e) => return Err(VelociError::Io(e)),
-//     }
+/// Exclusive access to a database for index jobs, deletes and reloads, released on drop
+struct DatabaseReservation(String);
 
-//     // Ok(out)
-// }
+impl DatabaseReservation {
+    fn acquire(database: &str) -> Result<Self, Custom<String>> {
+        // checked and inserted under one lock, so two requests can't both reserve the database
+        if RESERVED_DATABASES.lock().unwrap().insert(database.to_string()) {
+            Ok(DatabaseReservation(database.to_string()))
+        } else {
+            Err(Custom(Status::Conflict, format!("An index job, delete or reload of {:?} is still running", database)))
+        }
+    }
+}
 
-// fn process_entries(entries: Entries) -> Result<(String, Option<String>), VelociError> {
-//     if entries.fields_count() == 2 {
-//         let mut config = String::new();
-//         entries
-//             .fields
-//             .get("config")
-//             .ok_or_else(|| VelociError::StringError(format!("expecting content field, but got {:?}", entries.fields.keys().collect::<Vec<_>>())))?[0]
-//             .data
-//             .readable()?
-//             .read_to_string(&mut config)?;
-
-//         let data_reader = entries
-//             .fields
-//             .get("data")
-//             .ok_or_else(|| VelociError::StringError(format!("expecting data field, but got {:?}", entries.fields.keys().collect::<Vec<_>>())))?[0]
-//             .data
-//             .readable()?;
-
-//         let mut data: Vec<u8> = vec![];
-//         veloci::create::convert_any_json_data_to_line_delimited(data_reader, &mut data)?;
-//         return Ok((unsafe { String::from_utf8_unchecked(data) }, Some(config)));
-//     }
+impl Drop for DatabaseReservation {
+    fn drop(&mut self) {
+        RESERVED_DATABASES.lock().unwrap().remove(&self.0);
+    }
+}
 
-//     let mut data: Vec<u8> = vec![];
-//     let data_reader = entries
-//         .fields
-//         .get("data")
-//         .ok_or_else(|| VelociError::StringError(format!("expecting data field, but got {:?}", entries.fields.keys().collect::<Vec<_>>())))?[0]
-//         .data
-//         .readable()?;
-//     veloci::create::convert_any_json_data_to_line_delimited(data_reader, &mut data)?;
-//     Ok((unsafe { String::from_utf8_unchecked(data) }, None))
-// }
+fn remove_expired_jobs() {
+    INDEX_JOBS.retain(|_, job| job.finished_at.map_or(true, |finished_at| finished_at.elapsed() < FINISHED_JOB_RETENTION));
+}
 
-// // #[post("/<database>", data = "<data>", format = "application/json")]
-// // fn create_db(database: String, data: rocket::data::Data) -> Result<String, VelociError> {
-// //     if PERSISTENCES.contains_key(&database) {
-// //         //TODO @BUG @FixMe ERROR OWASP
-// //         PERSISTENCES.remove(&database);
-// //     }
-
-// //     let mut out: Vec<u8> = vec![];
-// //     veloci::create::convert_any_json_data_to_line_delimited(data.open(), &mut out).unwrap();
-
-// //     veloci::create::create_indices_from_str(
-// //         &mut veloci::persistence::Persistence::create(database).unwrap(),
-// //         unsafe { std::str::from_utf8_unchecked(&out) },
-// //         "[]",
-// //         None,
-// //         false,
-// //     )
-// //     .unwrap();
-// //     Ok("created".to_string())
-// // }
-
-// #[delete("/<database>")]
-// fn delete_db(database: String) -> Result<String, VelociError> {
-//     if PERSISTENCES.contains_key(&database) {
-//         PERSISTENCES.remove(&database);
-//     }
-//     use std::path::Path;
-//     if Path::new(&database).exists() {
-//         std::fs::remove_dir_all(&database)?; //TODO @BUG @FixMe ERROR OWASP
-//     }
+/// Creates the database from the multipart form fields `data` (json or ndjson) and the optional `config` (toml field config).
+///
+/// The index is created in a background job, the response contains the job, which can be polled via `/_jobs/<job_id>`.
+#[post("/<database>", data = "<data>")]
+fn create_db(_auth: auth::Authorized<auth::Admin>, database: String, cont_type: &ContentType, data: Data) -> Result<Custom<Json<IndexJob>>, Custom<String>> {
+    check_database_name(&database)?;
+    if !cont_type.is_form_data() {
+        return Err(Custom(Status::BadRequest, "Content-Type not multipart/form-data".into()));
+    }
 
-//     Ok("deleted".to_string())
-// }
+    let (_, boundary) = cont_type
+        .params()
+        .find(|&(k, _)| k == "boundary")
+        .ok_or_else(|| Custom(Status::BadRequest, "`Content-Type: multipart/form-data` boundary param not provided".into()))?;
+
+    let (data, config) = process_upload(boundary, data).map_err(|err| Custom(Status::BadRequest, format!("{}", err)))?;
+
+    let reservation = DatabaseReservation::acquire(&database)?;
+    remove_expired_jobs();
+    let job = IndexJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        database: database.to_string(),
+        status: JobStatus::Running,
+        error: None,
+        duration_ms: None,
+        finished_at: None,
+    };
+    INDEX_JOBS.insert(job.id.to_string(), job.clone());
+
+    let job_id = job.id.to_string();
+    std::thread::spawn(move || {
+        let start = Instant::now();
+        let res = create_database(&database, &job_id, &data, &config.unwrap_or_default());
+        drop(reservation);
+        if let Some(mut job) = INDEX_JOBS.get_mut(&job_id) {
+            let duration = start.elapsed();
+            job.duration_ms = Some(duration.as_secs() * 1000 + u64::from(duration.subsec_millis()));
+            job.finished_at = Some(Instant::now());
+            match res {
+                Ok(()) => job.status = JobStatus::Done,
+                Err(err) => {
+                    info!("Creating database {:?} failed: {}", database, err);
+                    job.status = JobStatus::Failed;
+                    job.error = Some(format!("{}", err));
+                }
+            }
+        }
+    });
+
+    Ok(Custom(Status::Accepted, Json(job)))
+}
+
+/// The index is created in a temporary folder, so the currently loaded database stays searchable until the new one is complete
+fn create_database(database: &str, job_id: &str, data: &str, config: &str) -> Result<(), VelociError> {
+    let temp_db = CONFIG.database_path(&format!("{}.{}.tmp", database, job_id));
+    let res = Persistence::create(temp_db.to_string_lossy().to_string())
+        .map_err(VelociError::from)
+        .and_then(|mut persistence| veloci::create::create_indices_from_str(&mut persistence, data, config, false));
+    if let Err(err) = res {
+        std::fs::remove_dir_all(&temp_db).ok();
+        return Err(err);
+    }
+    let res = swap_database_folder(database, job_id, &temp_db);
+    if res.is_err() {
+        std::fs::remove_dir_all(&temp_db).ok();
+    }
+    res
+}
+
+/// Moves the old folder aside and the new one in, the loaded database is replaced only after the new one was loaded successfully.
+///
+/// On failure the old folder is restored.
+fn swap_database_folder(database: &str, job_id: &str, new_db: &std::path::Path) -> Result<(), VelociError> {
+    let db_path = CONFIG.database_path(database);
+    let old_db = CONFIG.database_path(&format!("{}.{}.old", database, job_id));
+    let has_old_db = db_path.exists();
+    if has_old_db {
+        std::fs::rename(&db_path, &old_db)?;
+    }
+    let restore_old_db = || {
+        if has_old_db {
+            if let Err(err) = std::fs::rename(&old_db, &db_path) {
+                error!("Could not restore database {:?} from {:?}: {}", database, old_db, err);
+            }
+        }
+    };
+    if let Err(err) = std::fs::rename(new_db, &db_path) {
+        restore_old_db();
+        return Err(err.into());
+    }
+    let persistence = match Persistence::load_with_loading_type(&db_path, CONFIG.loading_type(database)) {
+        Ok(persistence) => persistence,
+        Err(err) => {
+            // move the new folder back, so it is cleaned up by the caller
+            std::fs::rename(&db_path, new_db).ok();
+            restore_old_db();
+            return Err(err);
+        }
+    };
+    PERSISTENCES.insert(database.to_string(), persistence);
+    if has_old_db {
+        // the files of the replaced database stay readable for running requests, until they are unmapped
+        if let Err(err) = std::fs::remove_dir_all(&old_db) {
+            warn!("Could not delete old database folder {:?}: {}", old_db, err);
+        }
+    }
+    Ok(())
+}
+
+fn process_upload(boundary: &str, data: Data) -> Result<(String, Option<String>), VelociError> {
+    // saves all fields, any field longer than 10kB goes to a temporary directory
+    match Multipart::with_body(data.open(), boundary).save().size_limit(500_000_000).temp() {
+        Full(entries) => process_entries(entries),
+        Partial(_, reason) => Err(VelociError::StringError(format!("Upload could not be processed completely: {:?}", reason))),
+        Error(e) => Err(VelociError::Io(e)),
+    }
+}
+
+fn process_entries(entries: Entries) -> Result<(String, Option<String>), VelociError> {
+    let config = match entries.fields.get("config") {
+        Some(config_field) => {
+            let mut config = String::new();
+            config_field[0].data.readable()?.read_to_string(&mut config)?;
+            Some(config)
+        }
+        None => None,
+    };
+
+    let data_reader = entries
+        .fields
+        .get("data")
+        .ok_or_else(|| VelociError::StringError(format!("expecting data field, but got {:?}", entries.fields.keys().collect::<Vec<_>>())))?[0]
+        .data
+        .readable()?;
+    let mut data: Vec<u8> = vec![];
+    veloci::create::convert_any_json_data_to_line_delimited(data_reader, &mut data)?;
+    let data = String::from_utf8(data).map_err(|err| VelociError::StringError(format!("data is not valid utf-8: {}", err)))?;
+    Ok((data, config))
+}
+
+#[get("/_jobs/<job_id>")]
+fn get_job(_auth: auth::Authorized<auth::Admin>, job_id: String) -> Result<Json<IndexJob>, Custom<String>> {
+    INDEX_JOBS
+        .get(&job_id)
+        .map(|job| Json(job.clone()))
+        .ok_or_else(|| Custom(Status::NotFound, format!("Job {:?} not found", job_id)))
+}
+
+/// Lists the loaded databases, sorted by name
+#[get("/_databases")]
+fn list_dbs(_auth: auth::Authorized<auth::Admin>) -> Json<Vec<DatabaseInfo>> {
+    let databases = std::cell::RefCell::new(vec![]);
+    PERSISTENCES.retain(|name, persistence| {
+        databases.borrow_mut().push(DatabaseInfo {
+            name: name.to_string(),
+            num_docs: persistence.get_number_of_documents(),
+        });
+        true
+    });
+    let mut databases = databases.into_inner();
+    databases.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(databases)
+}
+
+/// Unloads the database and deletes it from disk
+#[delete("/<database>")]
+fn delete_db(_auth: auth::Authorized<auth::Admin>, database: String) -> Result<String, Custom<String>> {
+    check_database_name(&database)?;
+    let _reservation = DatabaseReservation::acquire(&database)?;
+    let was_loaded = PERSISTENCES.remove(&database).is_some();
+    let db_path = CONFIG.database_path(&database);
+    let exists_on_disk = db_path.exists();
+    if exists_on_disk {
+        std::fs::remove_dir_all(&db_path).map_err(|err| search_error_to_rocket_error(err.into()))?;
+    }
+    if !was_loaded && !exists_on_disk {
+        return Err(Custom(Status::NotFound, format!("Database {:?} not found", database)));
+    }
+
+    Ok(format!("deleted {:?}", database))
+}
+
+/// Loads the database from disk, replacing the loaded one
+#[post("/<database>/_reload")]
+fn reload_db(_auth: auth::Authorized<auth::Admin>, database: String) -> Result<String, Custom<String>> {
+    check_database_name(&database)?;
+    let _reservation = DatabaseReservation::acquire(&database)?;
+    let db_path = CONFIG.database_path(&database);
+    if !db_path.exists() {
+        return Err(Custom(Status::NotFound, format!("Database {:?} not found", database)));
+    }
+    let persistence = Persistence::load_with_loading_type(&db_path, CONFIG.loading_type(&database)).map_err(search_error_to_rocket_error)?;
+    PERSISTENCES.insert(database.to_string(), persistence);
+
+    Ok(format!("reloaded {:?}", database))
+}
 
 #[get("/<database>/inspect/<path>/<id>")]
-fn inspect_data(database: String, path: String, id: u64) -> Result<String, VelociError> {
+fn inspect_data(_auth: auth::Authorized<auth::Admin>, database: String, path: String, id: u64) -> Result<String, VelociError> {
     ensure_database(&database)?;
     let persistence = PERSISTENCES.get(&database).unwrap();
     // persistence.get(path)
@@ -574,22 +961,23 @@ fn inspect_data(database: String, path: String, id: u64) -> Result<String, Veloc
 // ******************************************** PERMISSION CRITICAL END ********************************************
 
 #[post("/<database>/suggest", format = "application/json", data = "<request>")]
-fn suggest_post(database: String, request: Json<search::Request>) -> Json<serde_json::Value> {
-    ensure_database(&database).unwrap();
+fn suggest_post(auth: auth::Authorized<auth::Suggest>, database: String, request: Json<search::Request>) -> Result<SuggestResult, VelociError> {
+    ensure_database(&database)?;
     let persistence = PERSISTENCES.get(&database).unwrap();
-    let hits = search_field::suggest_multi(&persistence, request.0).unwrap();
-    Json(serde_json::from_str(&serde_json::to_string(&hits).unwrap()).unwrap())
+    let mut request = request.0;
+    apply_mandatory_filters(&persistence, &mut request, auth.filters())?;
+    excute_suggest(&persistence, &database, "/<database>/suggest", request)
 }
 
 #[get("/<database>/suggest?<params..>", format = "application/json")]
-fn suggest_get(database: String, params: LenientForm<QueryParams>) -> Result<SuggestResult, VelociError> {
+fn suggest_get(auth: auth::Authorized<auth::Suggest>, database: String, params: LenientForm<QueryParams>) -> Result<SuggestResult, VelociError> {
     let params: QueryParams = params.into_inner();
     ensure_database(&database)?;
     let persistence = PERSISTENCES.get(&database).unwrap();
 
     let fields: Option<Vec<String>> = query_param_to_vec(params.fields);
 
-    let request = query_generator::suggest_query(
+    let mut request = query_generator::suggest_query(
         &params.query,
         &persistence,
         params.top,
@@ -599,41 +987,73 @@ fn suggest_get(database: String, params: LenientForm<QueryParams>) -> Result<Sug
         params.levenshtein_auto_limit,
     )
     .unwrap();
+    apply_mandatory_filters(&persistence, &mut request, auth.filters())?;
 
     debug!("{}", serde_json::to_string(&request).unwrap());
-    excute_suggest(&persistence, request, false)
+    excute_suggest(&persistence, &database, "/<database>/suggest", request)
 }
 
 #[post("/<database>/highlight", format = "application/json", data = "<request>")]
-fn highlight_post(database: String, mut request: Json<search::RequestSearchPart>) -> String {
+fn highlight_post(auth: auth::Authorized<auth::Search>, database: String, mut request: Json<search::RequestSearchPart>) -> Result<String, Custom<String>> {
+    check_no_mandatory_filter(auth.filters())?;
     ensure_database(&database).unwrap();
     let persistence = PERSISTENCES.get(&database).unwrap();
     let hits = search_field::highlight(&persistence, &mut request).unwrap();
-    serde_json::to_string(&hits).unwrap()
+    Ok(serde_json::to_string(&hits).unwrap())
+}
+
+fn load_auth_config() -> auth::AuthConfig {
+    auth::AuthConfig::load(CONFIG.auth.api_keys_file.as_ref().map(|path| path.as_path()), CONFIG.auth.filter_header.clone())
+        .unwrap_or_else(|err| panic!("Could not load api keys: {}", err))
 }
 
 fn rocket() -> rocket::Rocket {
+    rocket_with_auth(load_auth_config())
+}
+
+fn rocket_with_auth(auth_config: auth::AuthConfig) -> rocket::Rocket {
+    mount(rocket::ignite(), auth_config)
+}
+
+fn get_allowed_origins(origins: &[String]) -> AllowedOrigins {
+    if origins.iter().any(|origin| origin == "*") {
+        return AllowedOrigins::all();
+    }
+    let origins: Vec<&str> = origins.iter().map(|origin| origin.as_str()).collect();
+    let (allowed_origins, failed_origins) = AllowedOrigins::some(&origins);
+    if !failed_origins.is_empty() {
+        panic!("Invalid cors origins {:?}", failed_origins);
+    }
+    allowed_origins
+}
+
+fn mount(rocket: rocket::Rocket, auth_config: auth::AuthConfig) -> rocket::Rocket {
     let cors_options = rocket_cors::Cors {
-        allowed_origins: AllowedOrigins::all(),
-        allowed_methods: vec![Method::Get, Method::Post].into_iter().map(From::from).collect(),
+        allowed_origins: get_allowed_origins(&CONFIG.cors.allowed_origins),
+        allowed_methods: vec![Method::Get, Method::Post, Method::Delete].into_iter().map(From::from).collect(),
         allowed_headers: AllowedHeaders::all(),
         allow_credentials: true,
         ..Default::default()
     };
-    rocket::ignite()
+    rocket
         .mount(
             "/",
             routes![
                 version,
-                // delete_db,
-                // create_db,
-                // multipart_upload,
+                metrics_get,
+                create_db,
+                get_job,
+                list_dbs,
+                delete_db,
+                reload_db,
                 get_doc_for_id_direct,
                 get_doc_for_id_tree,
                 search_get,
                 search_post,
+                msearch,
                 search_post_query_params,
                 search_post_query_params_explain,
+                explain_hit_post,
                 suggest_get,
                 // search_get_shard,
                 suggest_post,
@@ -641,14 +1061,42 @@ fn rocket() -> rocket::Rocket {
                 inspect_data
             ],
         )
-        .attach(Gzip)
+        .manage(auth_config)
+        .attach(RequestMetrics)
+        .attach(Gzip { min_size: CONFIG.gzip_min_size })
         .attach(cors_options)
 }
 
+/// Rocket config with the address and port of the server config
+fn get_rocket_config() -> Result<rocket::Config, rocket::config::ConfigError> {
+    let environment = rocket::config::Environment::active()?;
+    let mut rocket_config = rocket::Config::build(environment);
+    if let Some(address) = &CONFIG.address {
+        rocket_config = rocket_config.address(address.as_str());
+    }
+    if let Some(port) = CONFIG.port {
+        rocket_config = rocket_config.port(port);
+    }
+    rocket_config.finalize()
+}
+
 fn main() {
     veloci::trace::enable_log();
 
-    for preload_db in std::env::args().skip(1) {
+    // the config is loaded lazily, validate it before anything else
+    if let Err(err) = config::ServerConfig::load() {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+    let auth_config = load_auth_config();
+    lazy_static::initialize(&QUERY_LOG);
+    let rocket_config = get_rocket_config().unwrap_or_else(|err| {
+        eprintln!("Invalid server config: {}", err);
+        std::process::exit(1);
+    });
+
+    // databases of the config and the command line arguments
+    for preload_db in CONFIG.databases.keys().cloned().chain(std::env::args().skip(1)) {
         ensure_database(&preload_db).unwrap();
     }
     // for preload_db in std::env::args().skip(1) {
@@ -656,16 +1104,42 @@ fn main() {
     // }
 
     println!("Starting Server...");
-    rocket().launch();
+    mount(rocket::custom(rocket_config), auth_config).launch();
+}
+
+/// Counts the requests by database, endpoint and status
+pub struct RequestMetrics;
+impl fairing::Fairing for RequestMetrics {
+    fn on_response(&self, request: &Request, response: &mut Response) {
+        let endpoint = request.route().map(|route| route.uri.path()).unwrap_or("unmatched");
+        // only loaded databases are used as label, to not create a label for every requested name
+        let database = auth::database_param(request).filter(|database| PERSISTENCES.contains_key(database)).unwrap_or_default();
+        metrics::record_request(&database, endpoint, response.status().code);
+    }
+
+    fn info(&self) -> fairing::Info {
+        fairing::Info {
+            name: "Request metrics",
+            kind: fairing::Kind::Response,
+        }
+    }
 }
 
-pub struct Gzip;
+/// Compresses responses of at least `min_size` bytes
+pub struct Gzip {
+    min_size: usize,
+}
 impl fairing::Fairing for Gzip {
     fn on_response(&self, request: &Request, response: &mut Response) {
         use flate2::Compression;
         let headers = request.headers();
         if headers.get("Accept-Encoding").any(|e| e.to_lowercase().contains("gzip")) {
+            let min_size = self.min_size;
             response.body_bytes().and_then(|body| {
+                if body.len() < min_size {
+                    response.set_sized_body(Cursor::new(body));
+                    return None;
+                }
                 let mut gz = GzEncoder::new(&body[..], Compression::default());
                 let mut buf = Vec::with_capacity(body.len());
                 gz.read_to_end(&mut buf)
@@ -1,9 +1,9 @@
 #[cfg(test)]
 mod test {
-    use super::super::rocket;
+    use super::super::{auth, config, query_log, rocket, rocket_with_auth, DatabaseReservation, MSEARCH_BODY_LIMIT};
     use rocket::local::Client;
 
-    use rocket::http::{ContentType, Status};
+    use rocket::http::{ContentType, Header, Status};
     use veloci::*;
 
     #[macro_export]
@@ -52,6 +52,83 @@ mod test {
         assert_contains!(response.body_string().unwrap(), "name");
     }
 
+    #[test]
+    fn get_request_invalid_query() {
+        create_db();
+
+        let client = Client::new(rocket()).expect("valid rocket instance");
+        let response = client.get("/test_rocket/search?query=%28fred%20AND").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let mut response = client.get("/test_rocket/search?query=%28fred%20AND&lenient=true").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_contains!(response.body_string().unwrap(), "name");
+    }
+
+    #[test]
+    fn get_metrics() {
+        create_db();
+        let client = Client::new(rocket()).expect("valid rocket instance");
+        let response = client.get("/test_rocket/search?query=fred").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let mut response = client.get("/metrics").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let metrics = response.body_string().unwrap();
+        assert_contains!(metrics, r#"veloci_http_requests_total{database="test_rocket",endpoint="/<database>/search",status="200"}"#);
+        assert_contains!(metrics, r#"veloci_search_duration_seconds_count{database="test_rocket",endpoint="/<database>/search"}"#);
+        assert_contains!(metrics, r#"veloci_documents{database="test_rocket"} 1"#);
+        assert_contains!(metrics, r#"veloci_index_size_bytes{database="test_rocket",type="FST",path="name.textindex"}"#);
+        assert_contains!(metrics, r#"veloci_index_size_bytes{database="test_rocket",type="AnchorScore",path="name.textindex.to_anchor_id_score"}"#);
+        assert_contains!(metrics, r#"veloci_index_size_bytes{database="test_rocket",type="DocStore",path="data"}"#);
+        assert_contains!(metrics, r#"veloci_cache_hits_total{database="test_rocket",cache="term_boost"}"#);
+    }
+
+    #[test]
+    fn post_msearch() {
+        create_db();
+        let client = Client::new(rocket()).expect("valid rocket instance");
+        let body = r#"[
+            {"database": "test_rocket", "request": {"search_req": {"search": {"terms": ["fred"], "path": "name"}}}},
+            {"database": "test_rocket_missing", "request": {"search_req": {"search": {"terms": ["fred"], "path": "name"}}}},
+            {"database": "test_rocket"},
+            {"database": "test_rocket", "request": {"search_req": {"search": {"terms": ["there"], "path": "text"}}}}
+        ]"#;
+        let mut response = client.post("/_msearch").body(body).header(ContentType::JSON).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let result: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        let responses = result["responses"].as_array().unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["num_hits"], 1);
+        assert!(responses[1]["error"].is_string());
+        assert_eq!(responses[2]["status"], 400);
+        assert_eq!(responses[3]["num_hits"], 1);
+
+        // ndjson
+        let body = "{\"database\": \"test_rocket\", \"request\": {\"search_req\": {\"search\": {\"terms\": [\"fred\"], \"path\": \"name\"}}}}\n\
+                    {\"database\": \"test_rocket\", \"request\": {\"search_req\": {\"search\": {\"terms\": [\"nope\"], \"path\": \"name\"}}}}\n";
+        let mut response = client.post("/_msearch").body(body).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let result: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(result["responses"][0]["num_hits"], 1);
+        assert_eq!(result["responses"][1]["num_hits"], 0);
+
+        // a malformed line only fails its search
+        let body = "{\"database\": \"test_rocket\", \"request\": {\"search_req\": {\"search\": {\"terms\": [\"fred\"], \"path\": \"name\"}}}}\n\
+                    {\"database\": \"test_rocket\", \"request\": \n\
+                    {\"database\": \"test_rocket\", \"request\": {\"search_req\": {\"search\": {\"terms\": [\"there\"], \"path\": \"text\"}}}}\n";
+        let mut response = client.post("/_msearch").body(body).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let result: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(result["responses"][0]["num_hits"], 1);
+        assert_eq!(result["responses"][1]["status"], 400);
+        assert_eq!(result["responses"][2]["num_hits"], 1);
+
+        let body = " ".repeat(MSEARCH_BODY_LIMIT as usize + 1);
+        let response = client.post("/_msearch").body(body).dispatch();
+        assert_eq!(response.status(), Status::PayloadTooLarge);
+    }
+
     #[test]
     fn get_suggest() {
         create_db();
@@ -131,6 +208,23 @@ mod test {
         assert_contains!(resp, "search");
     }
 
+    #[test]
+    fn post_explain_hit() {
+        create_db();
+        let client = Client::new(rocket()).expect("valid rocket instance");
+        let request = r#"{
+            "search_req": {"search": {"terms": ["fred"], "path": "name"}}
+        }"#;
+        let mut response = client.post("/test_rocket/explain/0").body(request).header(ContentType::JSON).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let resp = response.body_string().unwrap();
+        assert_contains!(resp, "term_to_anchor");
+        assert_contains!(resp, "fred");
+
+        let response = client.post("/test_rocket/explain/10").body(request).header(ContentType::JSON).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
     #[test]
     fn post_suggest() {
         create_db();
@@ -183,4 +277,355 @@ mod test {
         let ret = response.body_string().unwrap();
         assert_contains!(ret, r#"[0]"#);
     }
+
+    fn wait_for_job(client: &Client, job_id: &str) -> String {
+        for _ in 0..100 {
+            let mut response = client.get(format!("/_jobs/{}", job_id)).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let job = response.body_string().unwrap();
+            if !job.contains(r#""status":"running""#) {
+                return job;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        panic!("job {} did not finish", job_id);
+    }
+
+    #[test]
+    fn create_list_reload_delete_db() {
+        let client = Client::new(rocket()).expect("valid rocket instance");
+        let body = "--BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"data\"\r\n\r\n\
+                    [{\"name\": \"barney\"}, {\"name\": \"wilma\"}]\r\n\
+                    --BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"config\"\r\n\r\n\
+                    [\"*GLOBAL*\"]\r\n    features = [\"All\"]\r\n\
+                    --BOUNDARY--\r\n";
+        let mut response = client
+            .post("/test_rocket_upload")
+            .body(body)
+            .header(ContentType::new("multipart", "form-data").with_params(("boundary", "BOUNDARY")))
+            .dispatch();
+        assert_eq!(response.status(), Status::Accepted);
+        let job: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        let job = wait_for_job(&client, job["id"].as_str().unwrap());
+        assert_contains!(job, r#""status":"done""#);
+
+        let mut response = client.get("/_databases").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_contains!(response.body_string().unwrap(), r#"{"name":"test_rocket_upload","num_docs":2}"#);
+
+        let mut response = client.get("/test_rocket_upload/search?query=wilma").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_contains!(response.body_string().unwrap(), "wilma");
+
+        let response = client.post("/test_rocket_upload/_reload").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.delete("/test_rocket_upload").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let mut response = client.get("/_databases").dispatch();
+        assert!(!response.body_string().unwrap().contains("test_rocket_upload"));
+
+        let response = client.delete("/test_rocket_upload").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        let response = client.post("/test_rocket_upload/_reload").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn database_reservation_is_exclusive() {
+        let reservation = DatabaseReservation::acquire("test_rocket_reserved").unwrap();
+        assert_eq!(DatabaseReservation::acquire("test_rocket_reserved").err().unwrap().0, Status::Conflict);
+        assert!(DatabaseReservation::acquire("test_rocket_reserved_other").is_ok());
+        drop(reservation);
+        assert!(DatabaseReservation::acquire("test_rocket_reserved").is_ok());
+
+        let client = Client::new(rocket()).expect("valid rocket instance");
+        let _reservation = DatabaseReservation::acquire("test_rocket_reserved").unwrap();
+        let response = client.delete("/test_rocket_reserved").dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        let response = client.post("/test_rocket_reserved/_reload").dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+    }
+
+    #[test]
+    fn create_db_invalid_data() {
+        let client = Client::new(rocket()).expect("valid rocket instance");
+        let body = "--BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"data\"\r\n\r\n\
+                    {\"name\": \"barney\"}\r\n\
+                    --BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"config\"\r\n\r\n\
+                    [name\r\n\
+                    --BOUNDARY--\r\n";
+        let mut response = client
+            .post("/test_rocket_invalid_upload")
+            .body(body)
+            .header(ContentType::new("multipart", "form-data").with_params(("boundary", "BOUNDARY")))
+            .dispatch();
+        assert_eq!(response.status(), Status::Accepted);
+        let job: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        let job = wait_for_job(&client, job["id"].as_str().unwrap());
+        assert_contains!(job, r#""status":"failed""#);
+        assert!(!std::path::Path::new("test_rocket_invalid_upload").exists());
+    }
+
+    #[test]
+    fn create_db_invalid_name() {
+        let client = Client::new(rocket()).expect("valid rocket instance");
+        let response = client
+            .post("/_databases")
+            .body("")
+            .header(ContentType::new("multipart", "form-data").with_params(("boundary", "BOUNDARY")))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        let response = client.delete("/..").dispatch();
+        assert_ne!(response.status(), Status::Ok);
+        let response = client.get("/_jobs/unknown").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    fn rocket_with_api_keys() -> rocket::Rocket {
+        let api_keys = auth::ApiKeys::parse(
+            r#"
+            [[keys]]
+            key = "search_key"
+            databases = ["test_rocket"]
+            operations = ["search"]
+
+            [[keys]]
+            key = "other_key"
+            databases = ["other"]
+            operations = ["search", "suggest", "admin"]
+
+            [[keys]]
+            key = "admin_key"
+            databases = ["*"]
+            operations = ["admin"]
+
+            [[keys]]
+            key = "fred_key"
+            databases = ["test_rocket"]
+            operations = ["search", "suggest"]
+            filter = "name:fred"
+
+            [[keys]]
+            key = "barney_key"
+            databases = ["test_rocket"]
+            operations = ["search", "suggest"]
+            filter = "name:barney"
+        "#,
+        )
+        .unwrap();
+        rocket_with_auth(auth::AuthConfig {
+            api_keys: Some(api_keys),
+            filter_header: Some("X-Veloci-Filter".to_string()),
+        })
+    }
+
+    #[test]
+    fn api_key_authentication() {
+        create_db();
+        let client = Client::new(rocket_with_api_keys()).expect("valid rocket instance");
+        let response = client.get("/test_rocket/search?query=fred").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client.get("/test_rocket/search?query=fred").header(Header::new("X-Api-Key", "unknown")).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let mut response = client.get("/test_rocket/search?query=fred").header(Header::new("X-Api-Key", "search_key")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_contains!(response.body_string().unwrap(), "fred");
+        let response = client
+            .get("/test_rocket/search?query=fred")
+            .header(Header::new("Authorization", "Bearer search_key"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.get("/version").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn api_key_authorization() {
+        create_db();
+        let client = Client::new(rocket_with_api_keys()).expect("valid rocket instance");
+        let search_key = Header::new("X-Api-Key", "search_key");
+        let response = client.get("/test_rocket/suggest?query=fr").header(search_key.clone()).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client
+            .get("/test_rocket/inspect/boost.textindex.parent_to_value_id/0")
+            .header(search_key.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client.get("/_databases").header(search_key.clone()).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        // other database
+        let response = client.get("/test_rocket/search?query=fred").header(Header::new("X-Api-Key", "other_key")).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let admin_key = Header::new("X-Api-Key", "admin_key");
+        let response = client.get("/test_rocket/inspect/boost.textindex.parent_to_value_id/0").header(admin_key.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/_databases").header(admin_key.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/test_rocket/search?query=fred").header(admin_key).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let body = r#"[
+            {"database": "test_rocket", "request": {"search_req": {"search": {"terms": ["fred"], "path": "name"}}}},
+            {"database": "other", "request": {"search_req": {"search": {"terms": ["fred"], "path": "name"}}}}
+        ]"#;
+        let mut response = client.post("/_msearch").body(body).header(search_key).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let result: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(result["responses"][0]["num_hits"], 1);
+        assert_eq!(result["responses"][1]["status"], 403);
+    }
+
+    #[test]
+    fn api_key_mandatory_filter() {
+        create_db();
+        let client = Client::new(rocket_with_api_keys()).expect("valid rocket instance");
+        let num_hits = |response: &mut rocket::local::LocalResponse<'_>| {
+            let result: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+            result["num_hits"].clone()
+        };
+        let fred_key = Header::new("X-Api-Key", "fred_key");
+        let barney_key = Header::new("X-Api-Key", "barney_key");
+
+        let mut response = client.get("/test_rocket/search?query=fred").header(fred_key.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(num_hits(&mut response), 1);
+        let mut response = client.get("/test_rocket/search?query=fred").header(barney_key.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(num_hits(&mut response), 0);
+
+        // the filter of the request is intersected, it can't remove the mandatory filter
+        let body = r#"{"search_req": {"search": {"terms": ["fred"], "path": "name"}}, "filter": {"search": {"terms": ["fred"], "path": "name"}}}"#;
+        let mut response = client
+            .post("/test_rocket/search")
+            .body(body)
+            .header(ContentType::JSON)
+            .header(barney_key.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(num_hits(&mut response), 0);
+
+        let mut response = client.get("/test_rocket/suggest?query=fr").header(fred_key.clone()).dispatch();
+        assert_contains!(response.body_string().unwrap(), "fred");
+        let mut response = client.get("/test_rocket/suggest?query=fr").header(barney_key.clone()).dispatch();
+        assert_eq!(response.body_string().unwrap(), "[]");
+
+        let body = r#"[{"database": "test_rocket", "request": {"search_req": {"search": {"terms": ["fred"], "path": "name"}}}}]"#;
+        let mut response = client.post("/_msearch").body(body).header(barney_key.clone()).dispatch();
+        let result: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(result["responses"][0]["num_hits"], 0);
+
+        // documents can't be loaded without a search
+        let response = client.get("/test_rocket/_id/0").header(barney_key.clone()).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        // the filter header is applied additionally
+        let mut response = client
+            .get("/test_rocket/search?query=fred")
+            .header(fred_key.clone())
+            .header(Header::new("X-Veloci-Filter", "boost:other"))
+            .dispatch();
+        assert_eq!(num_hits(&mut response), 0);
+
+        // a client can't override or remove the filter of the proxy with additional or empty values
+        let response = client
+            .get("/test_rocket/search?query=fred")
+            .header(fred_key.clone())
+            .header(Header::new("X-Veloci-Filter", ""))
+            .header(Header::new("X-Veloci-Filter", "boost:other"))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        let response = client
+            .get("/test_rocket/search?query=fred")
+            .header(fred_key)
+            .header(Header::new("X-Veloci-Filter", " "))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn server_config() {
+        use std::io::Write;
+        let parse = |config: &str| {
+            let mut file = tempfile::NamedTempFile::new().unwrap();
+            file.write_all(config.as_bytes()).unwrap();
+            config::ServerConfig::from_file(file.path().to_path_buf())
+        };
+
+        let server_config = parse(include_str!("veloci-server.example.toml")).unwrap();
+        assert_eq!(server_config.port, Some(8000));
+        assert_eq!(server_config.max_top, Some(1000));
+
+        assert_contains!(parse("prot = 8000").unwrap_err().to_string(), "unknown field `prot`");
+        assert_contains!(
+            parse("default_top = 50\nmax_top = 10").unwrap_err().to_string(),
+            "default_top 50 is greater than max_top 10"
+        );
+        assert_contains!(parse("[databases.missing_db]").unwrap_err().to_string(), r#"database "missing_db" not found"#);
+        assert_contains!(parse("[cors]\nallowed_origins = [\"example.com\"]").unwrap_err().to_string(), "invalid cors origin");
+        assert_contains!(parse("[auth]\nfilter_header = \"X Filter\"").unwrap_err().to_string(), "invalid auth.filter_header");
+        assert_contains!(parse("[query_log]\npath = \"missing_folder/queries.jsonl\"").unwrap_err().to_string(), "query_log.path");
+    }
+
+    #[test]
+    fn query_log() {
+        create_db();
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("queries.jsonl");
+        let pers = persistence::Persistence::load("test_rocket").unwrap();
+        let request: search::Request = serde_json::from_str(r#"{"search_req": {"search": {"terms": ["fred"], "path": "name"}}, "profile": true}"#).unwrap();
+        let result = search::search(request.clone(), &pers);
+        let elapsed = std::time::Duration::from_millis(1);
+
+        let query_log = query_log::QueryLog::open(&config::QueryLogConfig {
+            path: path.clone(),
+            slow_threshold_ms: None,
+            plan_steps: true,
+        })
+        .unwrap();
+        query_log.log_search("test_rocket", "/<database>/search", request.clone(), &result, elapsed);
+        let suggest: search::Request = serde_json::from_str(r#"{"suggest": [{"terms": ["fr"], "path": "name", "starts_with": true}]}"#).unwrap();
+        query_log.log_suggest("test_rocket", "/<database>/suggest", suggest.clone(), &search_field::suggest_multi(&pers, suggest), elapsed);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<query_log::QueryLogEntry> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].database, "test_rocket");
+        assert_eq!(lines[0].num_hits, 1);
+        assert!(lines[0].plan_steps.is_some());
+        assert!(lines[0].request.search_req.is_some());
+        assert_eq!(lines[1].endpoint, "/<database>/suggest");
+        assert_eq!(lines[1].num_hits, 1);
+
+        // only slow requests
+        let slow_query_log = query_log::QueryLog::open(&config::QueryLogConfig {
+            path: path.clone(),
+            slow_threshold_ms: Some(60_000),
+            plan_steps: false,
+        })
+        .unwrap();
+        slow_query_log.log_search("test_rocket", "/<database>/search", request.clone(), &result, elapsed);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        // the plan steps are only logged if configured
+        let query_log = query_log::QueryLog::open(&config::QueryLogConfig {
+            path: path.clone(),
+            slow_threshold_ms: None,
+            plan_steps: false,
+        })
+        .unwrap();
+        assert!(!query_log.logs_plan_steps());
+        query_log.log_search("test_rocket", "/<database>/search", request, &result, elapsed);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let last: query_log::QueryLogEntry = serde_json::from_str(content.lines().last().unwrap()).unwrap();
+        assert!(last.plan_steps.is_none());
+    }
 }
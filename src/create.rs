@@ -18,8 +18,10 @@ use crate::{
         write_docs::write_docs,
     },
     error::*,
+    geo,
     indices::{persistence_score::token_to_anchor_score_vint::*, *},
-    metadata::FulltextIndexOptions,
+    metadata::{FieldType, FulltextIndexOptions},
+    number,
     persistence::{self, Persistence, *},
     util::{self, StringAdd, *},
 };
@@ -52,6 +54,57 @@ type TermMap = inohashmap::StringHashMap<TermInfo>;
 
 const NUM_TERM_LIMIT_MSG: &str = "number of terms per field is currently limited to u32";
 
+fn to_invalid_data(err: VelociError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Converts the indexed term of a value to the value in the boost index, which stores unsigned integers.
+/// Values which can't be stored without loss, e.g. negative, fractional or pre-1970 dates, are rejected.
+fn boost_value_to_u32(field_type: FieldType, term: &str) -> Result<u32, VelociError> {
+    let invalid_value = |value: &dyn std::fmt::Debug| {
+        VelociError::StringError(format!(
+            "Boost values need to be integers from 0 to {}, but got {:?} in a {:?} field",
+            u32::MAX,
+            value,
+            field_type
+        ))
+    };
+    let my_int = match field_type {
+        // boost values of dates are the seconds since the unix epoch
+        FieldType::Date | FieldType::I64 => {
+            let value = number::term_to_i64(term).ok_or_else(|| invalid_value(&term))?;
+            value.to_u32().ok_or_else(|| invalid_value(&value))?
+        }
+        FieldType::F64 => {
+            let value = number::term_to_f64(term).ok_or_else(|| invalid_value(&term))?;
+            Some(value)
+                .filter(|value| value.fract() == 0.0)
+                .and_then(|value| value.to_u32())
+                .ok_or_else(|| invalid_value(&value))?
+        }
+        FieldType::Bool => (term == number::bool_to_term(true)) as u32,
+        // geo points are boosted by the distance at query time, see `geo_boost` in the request
+        FieldType::GeoPoint => 0,
+        FieldType::Text => term.parse::<u32>().map_err(|_| invalid_value(&term))?,
+    };
+    Ok(my_int)
+}
+
+#[test]
+fn test_boost_value_to_u32() {
+    assert_eq!(boost_value_to_u32(FieldType::Text, "10").unwrap(), 10);
+    assert!(boost_value_to_u32(FieldType::Text, "ten").is_err());
+    assert_eq!(boost_value_to_u32(FieldType::I64, &number::i64_to_term(10)).unwrap(), 10);
+    assert!(boost_value_to_u32(FieldType::I64, &number::i64_to_term(-1)).is_err());
+    assert!(boost_value_to_u32(FieldType::I64, &number::i64_to_term(i64::from(u32::MAX) + 1)).is_err());
+    assert_eq!(boost_value_to_u32(FieldType::F64, &number::f64_to_term(3.0)).unwrap(), 3);
+    assert!(boost_value_to_u32(FieldType::F64, &number::f64_to_term(3.5)).is_err());
+    assert!(boost_value_to_u32(FieldType::F64, &number::f64_to_term(-3.0)).is_err());
+    assert_eq!(boost_value_to_u32(FieldType::Date, &number::i64_to_term(1_546_300_800)).unwrap(), 1_546_300_800);
+    assert!(boost_value_to_u32(FieldType::Date, &number::i64_to_term(-86_400)).is_err());
+    assert_eq!(boost_value_to_u32(FieldType::Bool, number::bool_to_term(true)).unwrap(), 1);
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FacetIndex {
     facet: String,
@@ -187,6 +240,9 @@ where
         let mut tokens_to_anchor_id = Vec::with_capacity(10);
 
         let mut cb_text = |anchor_id: u32, value: &str, path: &str, parent_val_id: u32| -> Result<(), io::Error> {
+            let field_config = fields_config.get(path);
+            let term = field_config.value_to_term(value).map_err(to_invalid_data)?;
+            let value: &str = &term;
             let data: &mut PathData = get_or_insert_prefer_get(&mut path_data, path, || {
                 let term_data = term_data.terms_in_path.remove(path).unwrap_or_else(|| panic!("Couldn't find path in term_data {:?}", path));
                 prepare_path_data(&persistence.temp_dir(), &persistence, &fields_config, path, term_data)
@@ -208,7 +264,7 @@ where
             add!(data.anchor_to_text_id, anchor_id, text_info.id);
             if let Some(el) = data.boost.as_mut() {
                 if value.trim() != "" {
-                    let my_int = value.parse::<u32>().unwrap_or_else(|_| panic!("Expected an int value but got {:?}", value));
+                    let my_int = boost_value_to_u32(field_config.field_type, value).map_err(to_invalid_data)?;
                     el.add(parent_val_id, my_int)?;
                 }
             }
@@ -777,6 +833,19 @@ where
 {
     let mut term_data = AllTermsAndDocumentBuilder::default();
 
+    // geo point objects {"lat", "lon"} are indexed as one value
+    let geo_point_paths = indices_json.get_geo_point_paths();
+    let normalize_geo_points = |doc: Result<serde_json::Value, serde_json::Error>| {
+        doc.map(|mut doc| {
+            for path in &geo_point_paths {
+                geo::normalize_geo_points(&mut doc, path);
+            }
+            doc
+        })
+    };
+    let stream1 = stream1.map(normalize_geo_points);
+    let stream2 = stream2.map(normalize_geo_points);
+
     let doc_write_res = write_docs(&mut persistence, stream3)?;
     get_allterms_per_path(stream1, &indices_json, &mut term_data)?;
 
@@ -788,13 +857,16 @@ where
             .par_iter_mut()
             .map(|(path, mut terms_data)| {
                 let mut textindex_metadata = TextIndexValuesMetadata::default();
-                let options: &FulltextIndexOptions = indices_json.get(&path).fulltext.as_ref().unwrap_or_else(|| &default_fulltext_options);
+                let field_config = indices_json.get(&path);
+                let options: &FulltextIndexOptions = field_config.fulltext.as_ref().unwrap_or_else(|| &default_fulltext_options);
                 let path_text_index = path.to_string() + TEXTINDEX;
                 textindex_metadata.options = options.clone();
                 let mut col_info = FieldInfo {
                     name: path.to_string(),
                     has_fst: true,
                     textindex_metadata,
+                    field_type: field_config.field_type,
+                    date_formats: field_config.date_formats.clone(),
                     ..Default::default()
                 };
 
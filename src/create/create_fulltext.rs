@@ -124,14 +124,16 @@ pub(crate) fn get_allterms_per_path<I: Iterator<Item = Result<serde_json::Value,
     let mut id_holder = json_converter::IDHolder::new();
     {
         let mut cb_text = |_anchor_id: u32, value: &str, path: &str, _parent_val_id: u32| -> Result<(), io::Error> {
-            let options: &FulltextIndexOptions = fulltext_info_for_path.get(path).fulltext.as_ref().unwrap_or(&default_fulltext_options);
+            let field_config = fulltext_info_for_path.get(path);
+            let options: &FulltextIndexOptions = field_config.fulltext.as_ref().unwrap_or(&default_fulltext_options);
+            let value = field_config.value_to_term(value).map_err(to_invalid_data)?;
 
             let mut terms_data = get_or_insert_prefer_get(&mut data.terms_in_path, path, || TermDataInPath {
                 do_not_store_text_longer_than: options.do_not_store_text_longer_than,
                 ..Default::default()
             });
 
-            add_text(value, &mut terms_data, &options, options.tokenizer.as_ref().unwrap_or_else(|| &default_tokenizer));
+            add_text(&value, &mut terms_data, &options, options.tokenizer.as_ref().unwrap_or_else(|| &default_tokenizer));
             Ok(())
         };
         let mut callback_ids = |_anchor_id: u32, _path: &str, _value_id: u32, _parent_val_id: u32| -> Result<(), io::Error> { Ok(()) };
@@ -1,7 +1,11 @@
 use super::features::{Features, IndexCreationType};
-use crate::{error::VelociError, metadata::FulltextIndexOptions};
+use crate::{
+    error::VelociError,
+    metadata::{FieldType, FulltextIndexOptions},
+};
 
 use fnv::{FnvHashMap, FnvHashSet};
+use std::borrow::Cow;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateIndexConfig {
@@ -29,6 +33,15 @@ impl FieldsConfig {
         }
     }
 
+    /// Paths of the fields configured as `geo_point`
+    pub(crate) fn get_geo_point_paths(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|(_, config)| config.field_type == FieldType::GeoPoint)
+            .map(|(path, _)| path.to_string())
+            .collect()
+    }
+
     pub fn features_to_indices(&mut self) -> Result<(), VelociError> {
         if self.0.get(ALL_FIELD_CONFIG).is_none() {
             let default_field_config = FieldConfig::default();
@@ -66,7 +79,12 @@ pub struct FieldConfig {
     pub disabled_indices: Option<FnvHashSet<IndexCreationType>>,
     pub features: Option<FnvHashSet<Features>>,
     pub disabled_features: Option<FnvHashSet<Features>>,
+    /// Boost values are stored as integers from 0 to `u32::MAX`, dates as seconds since the unix epoch. Other values fail the indexing.
     pub boost: Option<BoostIndexOptions>,
+    #[serde(default)]
+    pub field_type: FieldType,
+    /// Input formats of a date field, e.g. `["rfc3339", "%Y-%m-%d", "epoch_seconds"]`. See `date::parse_date`
+    pub date_formats: Option<Vec<String>>,
 }
 
 impl Default for FieldConfig {
@@ -78,6 +96,8 @@ impl Default for FieldConfig {
             fulltext: Some(FulltextIndexOptions::new_with_tokenize()),
             disabled_indices: None,
             boost: None,
+            field_type: FieldType::Text,
+            date_formats: None,
         }
     }
 }
@@ -86,6 +106,11 @@ impl FieldConfig {
     pub fn is_index_enabled(&self, index: IndexCreationType) -> bool {
         self.disabled_indices.as_ref().map(|el| !el.contains(&index)).unwrap_or(true)
     }
+
+    /// Converts the value to the term, which is indexed. Typed values are converted to sortable terms, text values are unchanged.
+    pub(crate) fn value_to_term<'a>(&self, value: &'a str) -> Result<Cow<'a, str>, VelociError> {
+        self.field_type.value_to_term(value, self.date_formats.as_deref().unwrap_or(&[]))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -101,6 +126,10 @@ pub fn config_from_string(indices: &str) -> Result<FieldsConfig, VelociError> {
         FieldsConfig(map)
     };
     for value in &mut configs.0.values_mut() {
+        if value.field_type != FieldType::Text {
+            // typed values are indexed as one term
+            value.fulltext.get_or_insert_with(FulltextIndexOptions::default).tokenize = false;
+        }
         if let Some(fulltext) = &mut value.fulltext {
             fulltext.create_tokenizer();
             // if let Some(fulltext) = &fulltext.tokenize_on_chars {
@@ -140,6 +169,7 @@ fn test_field_config_from_json() {
 
 #[test]
 fn test_field_config_from_toml() {
+    use crate::{date, number};
     let indices = r#"
         ["*GLOBAL*"]
             features = ["All"]
@@ -169,7 +199,20 @@ fn test_field_config_from_toml() {
             boost_type = "int"
         ["kana[].commonness".boost]
             boost_type = "int"
+        ["created"]
+            field_type = "date"
+            date_formats = ["%d.%m.%Y", "rfc3339"]
+        ["price"]
+            field_type = "i64"
     "#;
 
-    config_from_string(indices).unwrap();
+    let config = config_from_string(indices).unwrap();
+    let date_config = config.get("created");
+    assert_eq!(date_config.field_type, FieldType::Date);
+    assert_eq!(date_config.fulltext.as_ref().unwrap().tokenize, false);
+    assert_eq!(date_config.value_to_term("31.01.2019").unwrap(), date::date_to_term(1_548_892_800));
+    assert!(date_config.value_to_term("2019-01-31").is_err());
+    assert_eq!(config.get("commonness").value_to_term("2019-01-31").unwrap(), "2019-01-31");
+    assert_eq!(config.get("price").value_to_term("10").unwrap(), number::i64_to_term(10));
+    assert!(config.get("price").value_to_term("ten").is_err());
 }
@@ -43,7 +43,7 @@ pub fn add_token_values_to_tokens(persistence: &mut Persistence, data_str: &str,
                 ..Default::default()
             };
 
-            let hits = search_field::get_term_ids_in_field(persistence, &mut options)?;
+            let hits = search_field::get_term_ids_in_field(persistence, &mut options, &search::CancellationToken::default())?;
             if !hits.hits_scores.is_empty() {
                 buffered_index_data.add(hits.hits_scores[0].id, value)?;
             }
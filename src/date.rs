@@ -0,0 +1,266 @@
+use crate::{error::VelociError, number};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::convert::TryFrom;
+
+/// Formats used, when no `date_formats` are configured for a date field
+pub const DEFAULT_DATE_FORMATS: &[&str] = &["rfc3339", "%Y-%m-%d", "epoch_seconds"];
+
+/// Unit for date math (`now-7d`), rounding (`now/d`) and date histograms
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum DateUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl DateUnit {
+    fn from_char(unit: char) -> Option<DateUnit> {
+        match unit {
+            's' => Some(DateUnit::Second),
+            'm' => Some(DateUnit::Minute),
+            'h' | 'H' => Some(DateUnit::Hour),
+            'd' => Some(DateUnit::Day),
+            'w' => Some(DateUnit::Week),
+            'M' => Some(DateUnit::Month),
+            'y' => Some(DateUnit::Year),
+            _ => None,
+        }
+    }
+
+    fn fixed_seconds(self) -> Option<i64> {
+        match self {
+            DateUnit::Second => Some(1),
+            DateUnit::Minute => Some(60),
+            DateUnit::Hour => Some(3600),
+            DateUnit::Day => Some(86400),
+            DateUnit::Week => Some(7 * 86400),
+            DateUnit::Month | DateUnit::Year => None,
+        }
+    }
+}
+
+fn invalid_date(message: String) -> VelociError {
+    VelociError::InvalidRequest { message }
+}
+
+fn to_naive(timestamp: i64) -> Result<NaiveDateTime, VelociError> {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|date| date.naive_utc())
+        .ok_or_else(|| invalid_date(format!("timestamp {} out of range", timestamp)))
+}
+
+fn to_timestamp(date: NaiveDateTime) -> i64 {
+    Utc.from_utc_datetime(&date).timestamp()
+}
+
+fn start_of_day(date: NaiveDate) -> NaiveDateTime {
+    date.and_time(chrono::NaiveTime::from_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+}
+
+fn parse_with_format(value: &str, format: &str) -> Option<i64> {
+    match format {
+        "rfc3339" => DateTime::parse_from_rfc3339(value).ok().map(|date| date.timestamp()),
+        "epoch_seconds" => value.parse::<i64>().ok(),
+        "epoch_millis" => value.parse::<i64>().ok().map(|millis| millis.div_euclid(1000)),
+        _ => NaiveDateTime::parse_from_str(value, format)
+            .ok()
+            .or_else(|| NaiveDate::parse_from_str(value, format).ok().map(start_of_day))
+            .map(to_timestamp),
+    }
+}
+
+/// Parses the date with the first matching format to seconds since the unix epoch.
+///
+/// Formats are `rfc3339`, `epoch_seconds`, `epoch_millis` or a chrono format string like `%Y-%m-%d`, dates without timezone are treated as UTC.
+/// The `DEFAULT_DATE_FORMATS` are used if `formats` is empty.
+pub fn parse_date<S: AsRef<str>>(value: &str, formats: &[S]) -> Result<i64, VelociError> {
+    let value = value.trim();
+    let parsed = if formats.is_empty() {
+        DEFAULT_DATE_FORMATS.iter().find_map(|format| parse_with_format(value, format))
+    } else {
+        formats.iter().find_map(|format| parse_with_format(value, format.as_ref()))
+    };
+    parsed.ok_or_else(|| invalid_date(format!("Could not parse date {:?}, expected one of the formats {:?}", value, get_format_names(formats))))
+}
+
+fn get_format_names<S: AsRef<str>>(formats: &[S]) -> Vec<String> {
+    if formats.is_empty() {
+        DEFAULT_DATE_FORMATS.iter().map(|el| el.to_string()).collect()
+    } else {
+        formats.iter().map(|el| el.as_ref().to_string()).collect()
+    }
+}
+
+/// Converts the timestamp to a term, which sorts in the same order as the timestamp in the fst. See `number::i64_to_term`
+pub fn date_to_term(timestamp: i64) -> String {
+    number::i64_to_term(timestamp)
+}
+
+/// Inverse of `date_to_term`
+pub fn term_to_date(term: &str) -> Option<i64> {
+    number::term_to_i64(term)
+}
+
+/// Formats the timestamp as RFC3339 in UTC, e.g. `2019-01-31T12:00:00+00:00`
+pub fn format_date(timestamp: i64) -> String {
+    match Utc.timestamp_opt(timestamp, 0).single() {
+        Some(date) => date.to_rfc3339(),
+        None => timestamp.to_string(),
+    }
+}
+
+pub fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+fn add_months(timestamp: i64, months: i64) -> Result<i64, VelociError> {
+    let date = to_naive(timestamp)?;
+    let total_months = (i64::from(date.year()) * 12 + i64::from(date.month0()))
+        .checked_add(months)
+        .ok_or_else(|| out_of_range(timestamp))?;
+    let year = i32::try_from(total_months.div_euclid(12)).map_err(|_| out_of_range(timestamp))?;
+    let month0 = total_months.rem_euclid(12) as u32;
+    // clamp the day to the last day of the target month, e.g. 31. jan + 1M => 28. feb
+    let new_date = (0..4)
+        .find_map(|days_back| NaiveDate::from_ymd_opt(year, month0 + 1, date.day() - days_back))
+        .ok_or_else(|| out_of_range(timestamp))?;
+    Ok(to_timestamp(new_date.and_time(date.time())))
+}
+
+fn out_of_range(timestamp: i64) -> VelociError {
+    invalid_date(format!("date math out of range for {}", timestamp))
+}
+
+/// Adds `amount` units to the timestamp. Months and years are calendar aware.
+pub fn add_to_date(timestamp: i64, amount: i64, unit: DateUnit) -> Result<i64, VelociError> {
+    match unit.fixed_seconds() {
+        Some(seconds) => amount
+            .checked_mul(seconds)
+            .and_then(|seconds| timestamp.checked_add(seconds))
+            .ok_or_else(|| out_of_range(timestamp)),
+        None if unit == DateUnit::Month => add_months(timestamp, amount),
+        None => add_months(timestamp, amount.checked_mul(12).ok_or_else(|| out_of_range(timestamp))?),
+    }
+}
+
+/// Rounds the timestamp down to the start of the unit, weeks start on monday.
+pub fn round_date(timestamp: i64, unit: DateUnit) -> Result<i64, VelociError> {
+    let date = to_naive(timestamp)?.date();
+    let rounded = match unit {
+        DateUnit::Second | DateUnit::Minute | DateUnit::Hour => {
+            let seconds = unit.fixed_seconds().unwrap_or(1);
+            return Ok(timestamp.div_euclid(seconds) * seconds);
+        }
+        DateUnit::Day => date,
+        DateUnit::Week => date - chrono::Duration::days(i64::from(date.weekday().num_days_from_monday())),
+        DateUnit::Month => date.with_day(1).unwrap_or(date),
+        DateUnit::Year => date.with_ordinal(1).unwrap_or(date),
+    };
+    Ok(to_timestamp(start_of_day(rounded)))
+}
+
+/// Parses date math expressions to seconds since the unix epoch.
+///
+/// An expression starts with `now` or a date followed by `||`, then any number of operations `+1d`, `-7d` and an optional rounding `/d`.
+/// Units are `s`, `m`, `h`, `d`, `w`, `M` (month) and `y`. e.g. `now-7d/d`, `2019-01-01||+1M`, `2019-01-01`
+pub fn parse_date_math<S: AsRef<str>>(expression: &str, formats: &[S], now: i64) -> Result<i64, VelociError> {
+    let expression = expression.trim();
+    let (mut timestamp, operations) = if let Some(operations) = expression.strip_prefix("now") {
+        (now, operations)
+    } else if let Some(pos) = expression.find("||") {
+        (parse_date(&expression[..pos], formats)?, &expression[pos + 2..])
+    } else {
+        return parse_date(expression, formats);
+    };
+
+    let invalid = |message: &str| invalid_date(format!("Invalid date math {:?}: {}", expression, message));
+    let chars: Vec<char> = operations.chars().collect();
+    let mut pos = 0;
+    while pos < chars.len() {
+        match chars[pos] {
+            op @ '+' | op @ '-' => {
+                let start = pos + 1;
+                pos = start;
+                while pos < chars.len() && chars[pos].is_ascii_digit() {
+                    pos += 1;
+                }
+                let amount: i64 = if start == pos {
+                    1
+                } else {
+                    chars[start..pos].iter().collect::<String>().parse().map_err(|_| invalid("invalid number"))?
+                };
+                let unit = chars
+                    .get(pos)
+                    .and_then(|unit| DateUnit::from_char(*unit))
+                    .ok_or_else(|| invalid("expected unit after number"))?;
+                timestamp = add_to_date(timestamp, if op == '-' { -amount } else { amount }, unit)?;
+                pos += 1;
+            }
+            '/' => {
+                let unit = chars
+                    .get(pos + 1)
+                    .and_then(|unit| DateUnit::from_char(*unit))
+                    .ok_or_else(|| invalid("expected unit after /"))?;
+                timestamp = round_date(timestamp, unit)?;
+                pos += 2;
+            }
+            other => return Err(invalid(&format!("unexpected character {:?}", other))),
+        }
+    }
+    Ok(timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_FORMATS: &[&str] = &[];
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2019-01-31", NO_FORMATS).unwrap(), 1_548_892_800);
+        assert_eq!(parse_date("2019-01-31T01:00:00+01:00", NO_FORMATS).unwrap(), 1_548_892_800);
+        assert_eq!(parse_date("1548892800", NO_FORMATS).unwrap(), 1_548_892_800);
+        assert_eq!(parse_date("31.01.2019", &["%d.%m.%Y"]).unwrap(), 1_548_892_800);
+        assert_eq!(parse_date("1548892800000", &["epoch_millis"]).unwrap(), 1_548_892_800);
+        assert!(parse_date("31.01.2019", NO_FORMATS).is_err());
+    }
+
+    #[test]
+    fn test_date_term_sort_order() {
+        let timestamps = vec![-1_000_000, -1, 0, 1, 1_548_892_800, i64::max_value(), i64::min_value()];
+        for timestamp in &timestamps {
+            assert_eq!(term_to_date(&date_to_term(*timestamp)), Some(*timestamp));
+        }
+        let mut by_term = timestamps.clone();
+        by_term.sort_by_key(|el| date_to_term(*el));
+        let mut by_value = timestamps;
+        by_value.sort();
+        assert_eq!(by_term, by_value);
+    }
+
+    #[test]
+    fn test_date_math() {
+        let now = parse_date("2019-01-31T15:30:10Z", NO_FORMATS).unwrap();
+        let parse = |expression: &str| format_date(parse_date_math(expression, NO_FORMATS, now).unwrap());
+        assert_eq!(parse("now"), "2019-01-31T15:30:10+00:00");
+        assert_eq!(parse("now-7d"), "2019-01-24T15:30:10+00:00");
+        assert_eq!(parse("now-1d/d"), "2019-01-30T00:00:00+00:00");
+        assert_eq!(parse("now+1M"), "2019-02-28T15:30:10+00:00");
+        assert_eq!(parse("now-1y+2h/h"), "2018-01-31T17:00:00+00:00");
+        assert_eq!(parse("now/w"), "2019-01-28T00:00:00+00:00");
+        assert_eq!(parse("2019-01-01||+1M/M"), "2019-02-01T00:00:00+00:00");
+        assert_eq!(parse("2019-03-01"), "2019-03-01T00:00:00+00:00");
+        assert!(parse_date_math("now-7x", NO_FORMATS, now).is_err());
+        assert!(parse_date_math("now*7d", NO_FORMATS, now).is_err());
+        assert!(parse_date_math("now+200000000000000d", NO_FORMATS, now).is_err());
+        assert!(parse_date_math("now+2000000000000000000y", NO_FORMATS, now).is_err());
+        assert!(parse_date_math("now+2000000000000M", NO_FORMATS, now).is_err());
+    }
+}
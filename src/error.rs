@@ -9,6 +9,9 @@ pub enum VelociError {
     #[fail(display = "InvalidRequest: {} ", _0)]
     InvalidRequest { message: String },
 
+    #[fail(display = "Could not parse query: {}", _0)]
+    QueryParserError(query_parser::error::ParseError),
+
     #[fail(display = "Field {} not found in {:?}", _0, _1)]
     FieldNotFound { field: String, all_fields: Vec<String> },
 
@@ -42,6 +45,44 @@ pub enum VelociError {
     PlanExecutionSendFailed,
     #[fail(display = "Plan Execution Failed, filter channel was closed or empty ")]
     PlanExecutionRecvFailedFilter,
+    #[fail(display = "Query timed out after {} ms", timeout_ms)]
+    Timeout { timeout_ms: u64 },
+    #[fail(display = "Query was cancelled")]
+    Cancelled,
+    #[fail(
+        display = "Query exceeded the memory limit of {} bytes with {} bytes in hits, restrict the query or increase max_memory",
+        max_memory, memory_used
+    )]
+    MemoryLimitExceeded { memory_used: usize, max_memory: usize },
+}
+
+impl VelociError {
+    /// The name of the variant, e.g. for metrics
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            VelociError::StringError(_) => "StringError",
+            VelociError::InvalidRequest { .. } => "InvalidRequest",
+            VelociError::QueryParserError(_) => "QueryParserError",
+            VelociError::FieldNotFound { .. } => "FieldNotFound",
+            VelociError::AllFieldsFiltered { .. } => "AllFieldsFiltered",
+            VelociError::RonSerError(_) => "RonSerError",
+            VelociError::JsonError(_) => "JsonError",
+            VelociError::TomlError(_) => "TomlError",
+            VelociError::Utf8Error(_) => "Utf8Error",
+            VelociError::FromUtf8Error(_) => "FromUtf8Error",
+            VelociError::FstError(_) => "FstError",
+            VelociError::Io(_) => "Io",
+            VelociError::InvalidConfig(_) => "InvalidConfig",
+            VelociError::MissingTextId { .. } => "MissingTextId",
+            VelociError::FstNotFound(_) => "FstNotFound",
+            VelociError::PlanExecutionRecvFailed => "PlanExecutionRecvFailed",
+            VelociError::PlanExecutionSendFailed => "PlanExecutionSendFailed",
+            VelociError::PlanExecutionRecvFailedFilter => "PlanExecutionRecvFailedFilter",
+            VelociError::Timeout { .. } => "Timeout",
+            VelociError::Cancelled => "Cancelled",
+            VelociError::MemoryLimitExceeded { .. } => "MemoryLimitExceeded",
+        }
+    }
 }
 
 impl From<io::Error> for VelociError {
@@ -49,6 +90,11 @@ impl From<io::Error> for VelociError {
         VelociError::Io(error)
     }
 }
+impl From<query_parser::error::ParseError> for VelociError {
+    fn from(error: query_parser::error::ParseError) -> VelociError {
+        VelociError::QueryParserError(error)
+    }
+}
 impl From<fst::Error> for VelociError {
     fn from(error: fst::Error) -> VelociError {
         VelociError::FstError(error)
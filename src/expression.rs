@@ -1,101 +1,328 @@
-#[derive(Debug)]
+use crate::error::VelociError;
+
+/// Arithmetic expression to calculate a boost score.
+///
+/// Supported are numbers, `$SCORE` (the boost value of the hit), `doc['field']` (the value of another boost field),
+/// the operators `+ - * /` with the usual precedence, parentheses and the functions `log` (base 10), `ln`, `sqrt`, `abs`, `min`, `max` and `pow`.
+///
+/// e.g. `log(doc['commonness'] + 1) * sqrt($SCORE)`
+#[derive(Debug, Clone, PartialEq)]
 pub struct ScoreExpression {
     expression: String,
-    ops: Vec<OperatorType>,
+    root: ExpressionNode,
+    /// fields referenced via `doc['field']`, the index in this vec is the index in the `doc_values` passed to `get_score`
+    doc_fields: Vec<String>,
 }
 
-#[derive(Debug)]
-enum OperatorType {
-    Division,
-    Mul,
+#[derive(Debug, Clone, PartialEq)]
+enum ExpressionNode {
+    Float(f32),
+    Score,
+    DocValue(usize),
+    Neg(Box<ExpressionNode>),
+    BinaryOp(BinaryOperator, Box<ExpressionNode>, Box<ExpressionNode>),
+    Function(Function, Vec<ExpressionNode>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOperator {
     Add,
     Sub,
-    _Score_,
-    Float(f32),
+    Mul,
+    Division,
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
-enum OperationStep {
-    OperatorType,
-    Value,
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Log,
+    Ln,
+    Sqrt,
+    Abs,
+    Min,
+    Max,
+    Pow,
 }
 
-impl ScoreExpression {
-    pub fn get_score(&self, rank: f32) -> f32 {
-        let left = match self.ops[0] {
-            OperatorType::_Score_ => rank,
-            OperatorType::Float(val) => val,
-            _ => panic!("Need to start with float oder $SCORE"),
-        };
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "log" => Some(Function::Log),
+            "ln" => Some(Function::Ln),
+            "sqrt" => Some(Function::Sqrt),
+            "abs" => Some(Function::Abs),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            "pow" => Some(Function::Pow),
+            _ => None,
+        }
+    }
 
-        let right = match self.ops[2] {
-            OperatorType::_Score_ => rank,
-            OperatorType::Float(val) => val,
-            _ => panic!("Need to end with float oder $SCORE"),
-        };
+    /// min and max accept one or more arguments
+    fn check_num_args(self, num_args: usize) -> bool {
+        match self {
+            Function::Log | Function::Ln | Function::Sqrt | Function::Abs => num_args == 1,
+            Function::Pow => num_args == 2,
+            Function::Min | Function::Max => num_args >= 1,
+        }
+    }
 
-        match self.ops[1] {
-            OperatorType::Division => left / right,
-            OperatorType::Mul => left * right,
-            OperatorType::Add => left + right,
-            OperatorType::Sub => left - right,
-            _ => panic!("Need to asdf"),
+    fn apply(self, args: &[f32]) -> f32 {
+        match self {
+            Function::Log => args[0].log10(),
+            Function::Ln => args[0].ln(),
+            Function::Sqrt => args[0].sqrt(),
+            Function::Abs => args[0].abs(),
+            Function::Min => args.iter().cloned().fold(std::f32::INFINITY, f32::min),
+            Function::Max => args.iter().cloned().fold(std::f32::NEG_INFINITY, f32::max),
+            Function::Pow => args[0].powf(args[1]),
         }
     }
+}
 
-    fn parse(expression: &str) -> Vec<OperatorType> {
-        let mut operations: Vec<OperatorType> = vec![];
-        let mut current = "".to_string();
-        // let currVal = None;
-        for next_char in expression.chars() {
-            if let ' ' = next_char {
-                let val = current.parse::<f32>();
-                // trace!("{:?}", val);
-                if let Ok(val) = val {
-                    operations.push(OperatorType::Float(val));
+impl ExpressionNode {
+    fn eval(&self, score: f32, doc_values: &[f32]) -> f32 {
+        match self {
+            ExpressionNode::Float(val) => *val,
+            ExpressionNode::Score => score,
+            ExpressionNode::DocValue(index) => doc_values.get(*index).cloned().unwrap_or(0.0),
+            ExpressionNode::Neg(node) => -node.eval(score, doc_values),
+            ExpressionNode::BinaryOp(op, left, right) => {
+                let left = left.eval(score, doc_values);
+                let right = right.eval(score, doc_values);
+                match op {
+                    BinaryOperator::Add => left + right,
+                    BinaryOperator::Sub => left - right,
+                    BinaryOperator::Mul => left * right,
+                    BinaryOperator::Division => left / right,
                 }
-                current.clear();
             }
+            ExpressionNode::Function(fun, args) => {
+                let args: Vec<f32> = args.iter().map(|arg| arg.eval(score, doc_values)).collect();
+                fun.apply(&args)
+            }
+        }
+    }
+}
 
-            if next_char != ' ' {
-                current += &next_char.to_string();
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Float(f32),
+    Score,
+    Ident(String),
+    Str(String),
+    Op(char),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = vec![];
+    let mut pos = 0;
+    while pos < chars.len() {
+        let start = pos;
+        let next_char = chars[pos];
+        match next_char {
+            ' ' | '\t' | '\n' => pos += 1,
+            '+' | '-' | '*' | '/' | '(' | ')' | '[' | ']' | ',' => {
+                tokens.push((start, Token::Op(next_char)));
+                pos += 1;
+            }
+            '0'..='9' | '.' => {
+                while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '.') {
+                    pos += 1;
+                }
+                let number: String = chars[start..pos].iter().collect();
+                let val = number.parse::<f32>().map_err(|_| format!("invalid number '{}' at position {}", number, start))?;
+                tokens.push((start, Token::Float(val)));
             }
-            match current.as_ref() {
-                "+" => {
-                    operations.push(OperatorType::Add);
-                    current.clear();
+            '\'' | '"' => {
+                pos += 1;
+                while pos < chars.len() && chars[pos] != next_char {
+                    pos += 1;
                 }
-                "-" => {
-                    operations.push(OperatorType::Sub);
-                    current.clear();
+                if pos == chars.len() {
+                    return Err(format!("unterminated string at position {}", start));
                 }
-                "/" => {
-                    operations.push(OperatorType::Division);
-                    current.clear();
+                tokens.push((start, Token::Str(chars[start + 1..pos].iter().collect())));
+                pos += 1;
+            }
+            '$' => {
+                pos += 1;
+                while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+                    pos += 1;
                 }
-                "*" => {
-                    operations.push(OperatorType::Mul);
-                    current.clear();
+                let variable: String = chars[start..pos].iter().collect();
+                if variable != "$SCORE" {
+                    return Err(format!("unknown variable '{}' at position {}, only $SCORE is supported", variable, start));
                 }
-                "$SCORE" => {
-                    operations.push(OperatorType::_Score_);
-                    current.clear();
+                tokens.push((start, Token::Score));
+            }
+            _ if next_char.is_alphabetic() || next_char == '_' => {
+                while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+                    pos += 1;
                 }
-                _ => {}
+                tokens.push((start, Token::Ident(chars[start..pos].iter().collect())));
             }
+            _ => return Err(format!("unexpected character '{}' at position {}", next_char, start)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser
+///
+/// expression := term (('+' | '-') term)*
+/// term       := unary (('*' | '/') unary)*
+/// unary      := '-' unary | primary
+/// primary    := number | $SCORE | doc[string] | function(expression, ...) | (expression)
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    doc_fields: Vec<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, token)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn describe_position(&self) -> String {
+        match self.tokens.get(self.pos) {
+            Some((position, token)) => format!("{:?} at position {}", token, position),
+            None => "end of expression".to_string(),
+        }
+    }
+
+    fn expect_op(&mut self, op: char) -> Result<(), String> {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}', found {}", op, self.describe_position()))
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<ExpressionNode, String> {
+        let mut node = self.parse_term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op('+')) => BinaryOperator::Add,
+                Some(Token::Op('-')) => BinaryOperator::Sub,
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = ExpressionNode::BinaryOp(op, Box::new(node), Box::new(self.parse_term()?));
         }
+    }
 
-        if let Ok(val) = current.parse::<f32>() {
-            operations.push(OperatorType::Float(val));
+    fn parse_term(&mut self) -> Result<ExpressionNode, String> {
+        let mut node = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op('*')) => BinaryOperator::Mul,
+                Some(Token::Op('/')) => BinaryOperator::Division,
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = ExpressionNode::BinaryOp(op, Box::new(node), Box::new(self.parse_unary()?));
         }
-        // trace!("{:?}", operations);
-        operations
     }
 
-    pub fn new(expression: String) -> Self {
-        let ops = ScoreExpression::parse(&expression);
-        ScoreExpression { expression, ops }
+    fn parse_unary(&mut self) -> Result<ExpressionNode, String> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.pos += 1;
+            return Ok(ExpressionNode::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<ExpressionNode, String> {
+        let position = self.describe_position();
+        match self.next() {
+            Some(Token::Float(val)) => Ok(ExpressionNode::Float(val)),
+            Some(Token::Score) => Ok(ExpressionNode::Score),
+            Some(Token::Op('(')) => {
+                let node = self.parse_expression()?;
+                self.expect_op(')')?;
+                Ok(node)
+            }
+            Some(Token::Ident(ref name)) if name == "doc" => {
+                self.expect_op('[')?;
+                let field = match self.next() {
+                    Some(Token::Str(field)) => field,
+                    _ => {
+                        self.pos -= 1;
+                        return Err(format!("expected field name in quotes after doc[, found {}", self.describe_position()));
+                    }
+                };
+                self.expect_op(']')?;
+                let index = match self.doc_fields.iter().position(|el| *el == field) {
+                    Some(index) => index,
+                    None => {
+                        self.doc_fields.push(field);
+                        self.doc_fields.len() - 1
+                    }
+                };
+                Ok(ExpressionNode::DocValue(index))
+            }
+            Some(Token::Ident(name)) => {
+                let fun = Function::from_name(&name).ok_or_else(|| format!("unknown function '{}'", name))?;
+                self.expect_op('(')?;
+                let mut args = vec![self.parse_expression()?];
+                while self.peek() == Some(&Token::Op(',')) {
+                    self.pos += 1;
+                    args.push(self.parse_expression()?);
+                }
+                self.expect_op(')')?;
+                if !fun.check_num_args(args.len()) {
+                    return Err(format!("wrong number of arguments ({}) for function '{}'", args.len(), name));
+                }
+                Ok(ExpressionNode::Function(fun, args))
+            }
+            _ => Err(format!("expected number, $SCORE, doc['field'], function or '(', found {}", position)),
+        }
+    }
+}
+
+impl ScoreExpression {
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The fields referenced via `doc['field']`, in the order expected by `get_score`
+    pub fn doc_fields(&self) -> &[String] {
+        &self.doc_fields
+    }
+
+    /// Evaluates the expression. `doc_values` contains the values of the `doc_fields`, missing values are treated as 0.
+    pub fn get_score(&self, score: f32, doc_values: &[f32]) -> f32 {
+        self.root.eval(score, doc_values)
+    }
+
+    pub fn new(expression: String) -> Result<Self, VelociError> {
+        let invalid = |message: String| VelociError::InvalidRequest {
+            message: format!("Invalid score expression '{}': {}", expression, message),
+        };
+        let tokens = tokenize(&expression).map_err(invalid)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            doc_fields: vec![],
+        };
+        let root = parser.parse_expression().map_err(invalid)?;
+        if parser.pos < parser.tokens.len() {
+            return Err(invalid(format!("unexpected {}", parser.describe_position())));
+        }
+        Ok(ScoreExpression {
+            doc_fields: parser.doc_fields,
+            expression,
+            root,
+        })
     }
 }
 
@@ -109,22 +336,72 @@ mod tests {
     use super::*;
     use crate::test::Bencher;
 
+    fn eval(expression: &str, score: f32) -> f32 {
+        ScoreExpression::new(expression.to_string()).unwrap().get_score(score, &[])
+    }
+
     #[test]
     fn test_parser() {
-        let expre = ScoreExpression::new("$SCORE + 2.0".to_string());
-        assert_eq!(expre.get_score(10.0), 12.0);
+        assert_eq!(eval("$SCORE + 2.0", 10.0), 12.0);
+        assert_eq!(eval("10.0 / $SCORE", 10.0), 1.0);
+        assert_eq!(eval("$SCORE * $SCORE", 10.0), 100.0);
+        assert_eq!(eval("10 / $SCORE", 5.0), 2.0);
+    }
+
+    #[test]
+    fn test_parser_precedence_and_parentheses() {
+        assert_eq!(eval("1 + 2 * 3", 0.0), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", 0.0), 9.0);
+        assert_eq!(eval("10 - 4 - 3", 0.0), 3.0);
+        assert_eq!(eval("-$SCORE + 1", 3.0), -2.0);
+        assert_eq!(eval("2 * -(1 + $SCORE)", 3.0), -8.0);
+    }
+
+    #[test]
+    fn test_parser_functions() {
+        assert_eq!(eval("log(100)", 0.0), 2.0);
+        assert_eq!(eval("ln(1)", 0.0), 0.0);
+        assert_eq!(eval("sqrt($SCORE)", 16.0), 4.0);
+        assert_eq!(eval("abs(1 - $SCORE)", 16.0), 15.0);
+        assert_eq!(eval("min($SCORE, 5, 7)", 16.0), 5.0);
+        assert_eq!(eval("max($SCORE, 5)", 16.0), 16.0);
+        assert_eq!(eval("pow(2, 3) + 1", 0.0), 9.0);
+    }
 
-        let expre = ScoreExpression::new("10.0 / $SCORE".to_string());
-        assert_eq!(expre.get_score(10.0), 1.0);
+    #[test]
+    fn test_parser_doc_values() {
+        let expre = ScoreExpression::new("log(doc['commonness'] + 1) * doc[\"rank\"] + doc['commonness']".to_string()).unwrap();
+        assert_eq!(expre.doc_fields(), &["commonness".to_string(), "rank".to_string()]);
+        assert_eq!(expre.get_score(0.0, &[99.0, 3.0]), 105.0);
+        assert_eq!(expre.get_score(0.0, &[]), 0.0);
+    }
 
-        let expre = ScoreExpression::new("$SCORE * $SCORE".to_string());
-        assert_eq!(expre.get_score(10.0), 100.0);
+    #[test]
+    fn test_parser_invalid_expressions() {
+        for expression in &[
+            "",
+            "$SCORE +",
+            "(1 + 2",
+            "1 2",
+            "foo(1)",
+            "log(1, 2)",
+            "pow(1)",
+            "doc[rank]",
+            "$RANK",
+            "1 # 2",
+            "doc['rank'",
+        ] {
+            match ScoreExpression::new(expression.to_string()) {
+                Err(VelociError::InvalidRequest { .. }) => {}
+                other => panic!("expected InvalidRequest for {:?}, got {:?}", expression, other),
+            }
+        }
     }
 
     #[bench]
     fn bench_expr_mult(b: &mut Bencher) {
-        let expre = ScoreExpression::new("$SCORE * $SCORE".to_string());
-        b.iter(|| expre.get_score(10.0));
+        let expre = ScoreExpression::new("$SCORE * $SCORE".to_string()).unwrap();
+        b.iter(|| expre.get_score(10.0, &[]));
     }
 
     #[bench]
@@ -1,7 +1,9 @@
 #![cfg_attr(feature = "cargo-clippy", allow(clippy::boxed_local))]
 
 use crate::{
+    date,
     error::VelociError,
+    metadata::FieldType,
     persistence::*,
     search::{sort::check_apply_top_n_sort, *},
     util::{self, StringAdd},
@@ -9,7 +11,7 @@ use crate::{
 use fnv::FnvHashMap;
 use itertools::Itertools;
 use num::{self, ToPrimitive};
-use std::{self, cmp::Ordering};
+use std::{self, cmp::Ordering, collections::BTreeMap};
 
 fn get_top_facet_group<T: IndexIdToParentData>(hits: &FnvHashMap<T, usize>, top: Option<usize>) -> Vec<(T, u32)> {
     let groups: Vec<(T, u32)> = hits.iter().map(|ref tupl| (*tupl.0, tupl.1.to_u32().unwrap())).collect();
@@ -26,9 +28,48 @@ fn get_groups_with_text(persistence: &Persistence, groups: &[(u32, u32)], field:
     groups.iter().map(|el| (get_text_for_id(persistence, field, el.0), el.1 as usize)).collect()
 }
 
+pub fn get_facet(persistence: &Persistence, req: &FacetRequest, ids: &[u32]) -> Result<Vec<(String, usize)>, VelociError> {
+    let field_type = persistence.metadata.columns.get(&req.field).map(|field_info| field_info.field_type).unwrap_or_default();
+    match field_type {
+        FieldType::Date => get_date_facet(persistence, req, ids),
+        _ if req.date_histogram.is_some() => Err(VelociError::InvalidRequest {
+            message: format!("date_histogram is only supported on date fields, but {:?} is not a date field", req.field),
+        }),
+        FieldType::Text => get_text_facet(persistence, req, ids),
+        // typed values are returned as text, e.g. `10` instead of the sortable term
+        _ => Ok(get_text_facet(persistence, req, ids)?
+            .into_iter()
+            .map(|(term, count)| (field_type.term_to_text(term), count))
+            .collect()),
+    }
+}
+
+/// The dates are returned as RFC3339 strings, with `date_histogram` they are counted per interval
+fn get_date_facet(persistence: &Persistence, req: &FacetRequest, ids: &[u32]) -> Result<Vec<(String, usize)>, VelociError> {
+    let term_to_date_text = |term: String| date::term_to_date(&term).map(date::format_date).unwrap_or(term);
+    let interval = match req.date_histogram {
+        Some(interval) => interval,
+        None => {
+            return Ok(get_text_facet(persistence, req, ids)?
+                .into_iter()
+                .map(|(term, count)| (term_to_date_text(term), count))
+                .collect())
+        }
+    };
+
+    let all_dates_req = FacetRequest { top: None, ..req.clone() };
+    let mut buckets: BTreeMap<i64, usize> = BTreeMap::new();
+    for (term, count) in get_text_facet(persistence, &all_dates_req, ids)? {
+        if let Some(timestamp) = date::term_to_date(&term) {
+            *buckets.entry(date::round_date(timestamp, interval)?).or_insert(0) += count;
+        }
+    }
+    Ok(buckets.into_iter().map(|(timestamp, count)| (date::format_date(timestamp), count)).collect())
+}
+
 // TODO Check ignorecase, check duplicates in facet data
 // For ignorecase, we probably need a term_ids -> lower case term id mapping index - read all texts annd aggregate may be too slow
-pub fn get_facet(persistence: &Persistence, req: &FacetRequest, ids: &[u32]) -> Result<Vec<(String, usize)>, VelociError> {
+fn get_text_facet(persistence: &Persistence, req: &FacetRequest, ids: &[u32]) -> Result<Vec<(String, usize)>, VelociError> {
     info_time!("facets in field {:?}", req.field);
     trace!("get_facet for ids {:?}", ids);
     let steps = util::get_steps_to_anchor(&req.field);
@@ -0,0 +1,286 @@
+use crate::error::VelociError;
+
+/// Geo points are indexed as geohashes with this length, which is a precision of a few centimeters
+pub const GEOHASH_PRECISION: usize = 12;
+/// Maximum number of geohash cells, which are searched to cover a bounding box
+const MAX_COVERING_CELLS: usize = 32;
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+const BASE32: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lon: f64) -> Result<GeoPoint, VelociError> {
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(invalid_geo(format!(
+                "Invalid geo point lat: {} lon: {}, lat needs to be between -90 and 90 and lon between -180 and 180",
+                lat, lon
+            )));
+        }
+        Ok(GeoPoint { lat, lon })
+    }
+
+    /// Distance in meters on the earth surface (haversine formula)
+    pub fn distance_to(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let delta_lat = lat2 - lat1;
+        let delta_lon = (other.lon - self.lon).to_radians();
+        let a = (delta_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (delta_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+    }
+}
+
+fn invalid_geo(message: String) -> VelociError {
+    VelociError::InvalidRequest { message }
+}
+
+/// Parses a geo point in the format `"lat,lon"`, e.g. `"52.52,13.405"`
+pub fn parse_geo_point(value: &str) -> Result<GeoPoint, VelociError> {
+    let invalid = || invalid_geo(format!("Could not parse geo point {:?}, expected \"lat,lon\"", value));
+    let mut parts = value.split(',').map(|part| part.trim().parse::<f64>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(lat)), Some(Ok(lon)), None) => GeoPoint::new(lat, lon),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a distance with the unit `m`, `km` or `mi` to meters, e.g. `"10km"`. Distances without unit are meters.
+pub fn parse_distance(value: &str) -> Result<f64, VelociError> {
+    let value = value.trim();
+    let (number, factor) = [("km", 1000.0), ("mi", 1609.344), ("m", 1.0)]
+        .iter()
+        .find(|(unit, _)| value.ends_with(unit))
+        .map(|(unit, factor)| (&value[..value.len() - unit.len()], *factor))
+        .unwrap_or((value, 1.0));
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|distance| distance.is_finite() && *distance >= 0.0)
+        .map(|distance| distance * factor)
+        .ok_or_else(|| invalid_geo(format!("Could not parse distance {:?}, expected e.g. \"10km\", \"500m\" or \"2mi\"", value)))
+}
+
+pub fn encode_geohash(point: &GeoPoint, precision: usize) -> String {
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut hash = String::with_capacity(precision);
+    let (mut bits, mut num_bits, mut is_lon_bit) = (0, 0, true);
+    while hash.len() < precision {
+        let (range, value) = if is_lon_bit { (&mut lon_range, point.lon) } else { (&mut lat_range, point.lat) };
+        let mid = (range.0 + range.1) / 2.0;
+        bits <<= 1;
+        if value >= mid {
+            bits |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+        is_lon_bit = !is_lon_bit;
+        num_bits += 1;
+        if num_bits == 5 {
+            hash.push(BASE32[bits] as char);
+            bits = 0;
+            num_bits = 0;
+        }
+    }
+    hash
+}
+
+/// Returns the center of the geohash cell
+pub fn decode_geohash(hash: &str) -> Option<GeoPoint> {
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut is_lon_bit = true;
+    for byte in hash.bytes() {
+        let bits = BASE32.iter().position(|el| *el == byte)?;
+        for shift in (0..5).rev() {
+            let range = if is_lon_bit { &mut lon_range } else { &mut lat_range };
+            let mid = (range.0 + range.1) / 2.0;
+            if bits >> shift & 1 == 1 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            is_lon_bit = !is_lon_bit;
+        }
+    }
+    Some(GeoPoint {
+        lat: (lat_range.0 + lat_range.1) / 2.0,
+        lon: (lon_range.0 + lon_range.1) / 2.0,
+    })
+}
+
+/// Converts `{"lat": 52.52, "lon": 13.405}` objects to the text `"52.52,13.405"`, other values are unchanged
+pub fn geo_point_object_to_text(value: &mut serde_json::Value) {
+    let text = match (value.get("lat").and_then(|lat| lat.as_f64()), value.get("lon").and_then(|lon| lon.as_f64())) {
+        (Some(lat), Some(lon)) if value.as_object().map(|obj| obj.len() == 2).unwrap_or(false) => format!("{},{}", lat, lon),
+        _ => return,
+    };
+    *value = serde_json::Value::String(text);
+}
+
+/// Converts the geo point objects at `path` (e.g. `stores[].location`) in the document with `geo_point_object_to_text`
+pub fn normalize_geo_points(doc: &mut serde_json::Value, path: &str) {
+    let segments: Vec<&str> = path.split('.').collect();
+    normalize_geo_points_in(doc, &segments);
+}
+
+fn normalize_geo_points_in(value: &mut serde_json::Value, segments: &[&str]) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return geo_point_object_to_text(value),
+    };
+    if let Some(child) = value.get_mut(segment.trim_end_matches("[]")) {
+        match child {
+            serde_json::Value::Array(elements) if segment.ends_with("[]") => elements.iter_mut().for_each(|el| normalize_geo_points_in(el, rest)),
+            _ => normalize_geo_points_in(child, rest),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        point.lat >= self.min_lat && point.lat <= self.max_lat && point.lon >= self.min_lon && point.lon <= self.max_lon
+    }
+
+    /// Bounding boxes crossing the date line (`min_lon` > `max_lon`) are split in two boxes
+    pub fn split_at_date_line(min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Vec<BoundingBox> {
+        let new_box = |min_lon, max_lon| BoundingBox {
+            min_lat,
+            max_lat,
+            min_lon,
+            max_lon,
+        };
+        if min_lon > max_lon {
+            vec![new_box(min_lon, 180.0), new_box(-180.0, max_lon)]
+        } else {
+            vec![new_box(min_lon, max_lon)]
+        }
+    }
+
+    /// Bounding boxes, which contain all points within the distance to the origin
+    pub fn around(origin: &GeoPoint, meters: f64) -> Vec<BoundingBox> {
+        let angular_distance = meters / EARTH_RADIUS_METERS;
+        let min_lat = origin.lat - angular_distance.to_degrees();
+        let max_lat = origin.lat + angular_distance.to_degrees();
+        let lon_delta = (angular_distance.sin() / origin.lat.to_radians().cos()).asin().to_degrees();
+        if min_lat <= -90.0 || max_lat >= 90.0 || lon_delta.is_nan() || lon_delta >= 180.0 {
+            // the circle contains a pole, all longitudes are possible
+            return vec![BoundingBox {
+                min_lat: min_lat.max(-90.0),
+                max_lat: max_lat.min(90.0),
+                min_lon: -180.0,
+                max_lon: 180.0,
+            }];
+        }
+        let wrap = |lon: f64| {
+            if lon < -180.0 {
+                lon + 360.0
+            } else if lon > 180.0 {
+                lon - 360.0
+            } else {
+                lon
+            }
+        };
+        BoundingBox::split_at_date_line(min_lat, max_lat, wrap(origin.lon - lon_delta), wrap(origin.lon + lon_delta))
+    }
+
+    /// Geohash prefixes, whose cells cover the bounding box. The longest prefixes with at most `MAX_COVERING_CELLS` cells are used.
+    pub fn covering_geohashes(&self) -> Vec<String> {
+        for precision in (1..=GEOHASH_PRECISION).rev() {
+            let lon_cell = 360.0 / f64::from(1u32 << ((5 * precision + 1) / 2).min(31));
+            let lat_cell = 180.0 / f64::from(1u32 << (5 * precision / 2).min(31));
+            let cell_range = |min: f64, max: f64, offset: f64, cell: f64| {
+                let num_cells = ((2.0 * offset) / cell).round() as i64;
+                let to_cell = |value: f64| (((value + offset) / cell).floor() as i64).max(0).min(num_cells - 1);
+                (to_cell(min), to_cell(max))
+            };
+            let (lat_start, lat_end) = cell_range(self.min_lat, self.max_lat, 90.0, lat_cell);
+            let (lon_start, lon_end) = cell_range(self.min_lon, self.max_lon, 180.0, lon_cell);
+            let num_cells = (lat_end - lat_start + 1) * (lon_end - lon_start + 1);
+            if num_cells as usize > MAX_COVERING_CELLS && precision > 1 {
+                continue;
+            }
+            let mut hashes = vec![];
+            for lat_cell_index in lat_start..=lat_end {
+                for lon_cell_index in lon_start..=lon_end {
+                    let cell_center = GeoPoint {
+                        lat: -90.0 + (lat_cell_index as f64 + 0.5) * lat_cell,
+                        lon: -180.0 + (lon_cell_index as f64 + 0.5) * lon_cell,
+                    };
+                    hashes.push(encode_geohash(&cell_center, precision));
+                }
+            }
+            return hashes;
+        }
+        unreachable!("precision 1 covers the world with 32 cells")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geohash() {
+        let berlin = GeoPoint { lat: 52.52, lon: 13.405 };
+        assert_eq!(encode_geohash(&berlin, 6), "u33dc0");
+        let decoded = decode_geohash(&encode_geohash(&berlin, GEOHASH_PRECISION)).unwrap();
+        assert!(decoded.distance_to(&berlin) < 0.1);
+        assert_eq!(decode_geohash("u33a!"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_geo_point("52.52, 13.405").unwrap(), GeoPoint { lat: 52.52, lon: 13.405 });
+        assert!(parse_geo_point("152.52,13.405").is_err());
+        assert!(parse_geo_point("52.52").is_err());
+        assert_eq!(parse_distance("10km").unwrap(), 10_000.0);
+        assert_eq!(parse_distance("500m").unwrap(), 500.0);
+        assert_eq!(parse_distance("2mi").unwrap(), 3218.688);
+        assert_eq!(parse_distance("25").unwrap(), 25.0);
+        assert!(parse_distance("-1km").is_err());
+    }
+
+    #[test]
+    fn test_distance() {
+        let berlin = GeoPoint { lat: 52.52, lon: 13.405 };
+        let munich = GeoPoint { lat: 48.1351, lon: 11.582 };
+        assert!((berlin.distance_to(&munich) / 1000.0 - 504.0).abs() < 2.0);
+        assert_eq!(berlin.distance_to(&berlin), 0.0);
+    }
+
+    #[test]
+    fn test_normalize_geo_points() {
+        let mut doc = json!({"location": {"lat": 52.52, "lon": 13.405}, "stores": [{"location": {"lat": 1, "lon": 2}}]});
+        normalize_geo_points(&mut doc, "location");
+        normalize_geo_points(&mut doc, "stores[].location");
+        assert_eq!(doc, json!({"location": "52.52,13.405", "stores": [{"location": "1,2"}]}));
+    }
+
+    #[test]
+    fn test_covering_geohashes() {
+        let berlin = GeoPoint { lat: 52.52, lon: 13.405 };
+        for bounding_box in BoundingBox::around(&berlin, 5000.0) {
+            let hashes = bounding_box.covering_geohashes();
+            assert!(hashes.len() <= MAX_COVERING_CELLS);
+            assert!(hashes.iter().any(|hash| encode_geohash(&berlin, GEOHASH_PRECISION).starts_with(hash.as_str())));
+        }
+        // crosses the date line
+        let fiji = GeoPoint { lat: -17.7, lon: 179.9 };
+        let boxes = BoundingBox::around(&fiji, 50_000.0);
+        assert_eq!(boxes.len(), 2);
+        assert!(boxes.iter().any(|bounding_box| bounding_box.contains(&GeoPoint { lat: -17.7, lon: -179.9 })));
+    }
+}
@@ -36,17 +36,21 @@ pub mod type_info;
 #[cfg(feature = "create")]
 pub mod create;
 
+pub mod date;
 pub mod error;
 pub mod expression;
 pub mod facet;
+pub mod geo;
 pub mod highlight_field;
 pub mod indices;
 pub mod metadata;
+pub mod number;
 pub mod persistence;
 pub mod plan_creator;
 pub mod query_generator;
 pub mod search;
 pub mod shards;
+pub mod stats;
 pub mod steps;
 pub mod tokenizer;
 pub mod trace;
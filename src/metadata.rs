@@ -1,11 +1,14 @@
 use crate::{
+    date,
     error::VelociError,
+    geo,
     indices::metadata::*,
+    number,
     tokenizer::{Tokenizer, *},
     util,
 };
 use fnv::{FnvHashMap, FnvHashSet};
-use std::sync::Arc;
+use std::{borrow::Cow, sync::Arc};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct PeristenceMetaData {
@@ -40,6 +43,84 @@ pub struct FieldInfo {
     /// special case when text_id equals document id
     pub is_anchor_identity_column: bool,
     pub has_fst: bool,
+    #[serde(default)]
+    pub field_type: FieldType,
+    /// Formats to parse the values of a date field, see `date::parse_date`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub date_formats: Option<Vec<String>>,
+}
+
+/// Type of the values in a field
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Text,
+    /// Dates are stored as seconds since the unix epoch, in terms which sort like the timestamps (`date::date_to_term`)
+    Date,
+    /// Integers are stored in terms, which sort like the values (`number::i64_to_term`)
+    I64,
+    /// Floats are stored in terms, which sort like the values (`number::f64_to_term`)
+    F64,
+    /// Booleans are stored as the terms `true` and `false`
+    Bool,
+    /// Geo points are parsed from `{"lat": 52.52, "lon": 13.405}` objects or `"52.52,13.405"` texts and stored as geohash terms (`geo::encode_geohash`)
+    #[serde(rename = "geo_point")]
+    GeoPoint,
+}
+
+impl Default for FieldType {
+    fn default() -> FieldType {
+        FieldType::Text
+    }
+}
+
+impl FieldType {
+    /// Converts the value to the term, which is indexed in the fst. Text values are unchanged.
+    pub fn value_to_term<'a>(self, value: &'a str, date_formats: &[String]) -> Result<Cow<'a, str>, VelociError> {
+        Ok(match self {
+            FieldType::Text => Cow::Borrowed(value),
+            FieldType::Date => Cow::Owned(date::date_to_term(date::parse_date(value, date_formats)?)),
+            FieldType::I64 => Cow::Owned(number::i64_to_term(number::parse_i64(value)?)),
+            FieldType::F64 => Cow::Owned(number::f64_to_term(number::parse_f64(value)?)),
+            FieldType::Bool => Cow::Borrowed(number::bool_to_term(number::parse_bool(value)?)),
+            FieldType::GeoPoint => Cow::Owned(geo::encode_geohash(&geo::parse_geo_point(value)?, geo::GEOHASH_PRECISION)),
+        })
+    }
+
+    /// Like `value_to_term`, but date fields also accept date math relative to `now`, see `date::parse_date_math`
+    pub fn query_to_term(self, value: &str, date_formats: &[String], now: i64) -> Result<String, VelociError> {
+        match self {
+            FieldType::Date => Ok(date::date_to_term(date::parse_date_math(value, date_formats, now)?)),
+            _ => Ok(self.value_to_term(value, date_formats)?.into_owned()),
+        }
+    }
+
+    /// Converts the indexed term back to a typed json value, dates are returned as RFC3339 strings and geo points as `{"lat", "lon"}` objects
+    pub fn term_to_json(self, term: String) -> serde_json::Value {
+        let value = match self {
+            FieldType::Text => None,
+            FieldType::Date => date::term_to_date(&term).map(|timestamp| json!(date::format_date(timestamp))),
+            FieldType::I64 => number::term_to_i64(&term).map(|value| json!(value)),
+            FieldType::F64 => number::term_to_f64(&term).map(|value| json!(value)),
+            FieldType::Bool => number::parse_bool(&term).ok().map(|value| json!(value)),
+            FieldType::GeoPoint => geo::decode_geohash(&term).map(|point| json!({"lat": round_coordinate(point.lat), "lon": round_coordinate(point.lon)})),
+        };
+        value.unwrap_or_else(|| json!(term))
+    }
+
+    /// Converts the indexed term back to the text, which is displayed e.g. in facets
+    pub fn term_to_text(self, term: String) -> String {
+        match self.term_to_json(term) {
+            serde_json::Value::String(text) => text,
+            other => other.to_string(),
+        }
+    }
+}
+
+/// The geohash cell center is rounded to 6 decimals (~10cm), so that e.g. 52.52 is returned instead of 52.520000003
+fn round_coordinate(coordinate: f64) -> f64 {
+    (coordinate * 1_000_000.0).round() / 1_000_000.0
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -0,0 +1,110 @@
+use crate::error::VelociError;
+
+fn invalid_value(value: &str, expected: &str) -> VelociError {
+    VelociError::InvalidRequest {
+        message: format!("Could not parse {:?} as {}", value, expected),
+    }
+}
+
+/// Converts the integer to a term, which sorts in the same order as the integer in the fst.
+///
+/// The sign bit is flipped, so that negative values sort before positive ones, and the value is zero padded to a fixed width.
+pub fn i64_to_term(value: i64) -> String {
+    format!("{:020}", (value as u64) ^ (1 << 63))
+}
+
+/// Inverse of `i64_to_term`
+pub fn term_to_i64(term: &str) -> Option<i64> {
+    if term.len() != 20 {
+        return None;
+    }
+    term.parse::<u64>().ok().map(|val| (val ^ (1 << 63)) as i64)
+}
+
+/// Converts the float to a term, which sorts in the same order as the float in the fst.
+///
+/// Positive floats only get the sign bit flipped, the bits of negative floats are all inverted, so that larger negative values sort first.
+pub fn f64_to_term(value: f64) -> String {
+    // -0.0 and 0.0 should be the same term
+    let bits = (value + 0.0).to_bits();
+    let sortable = if bits & (1 << 63) == 0 { bits ^ (1 << 63) } else { !bits };
+    format!("{:020}", sortable)
+}
+
+/// Inverse of `f64_to_term`
+pub fn term_to_f64(term: &str) -> Option<f64> {
+    if term.len() != 20 {
+        return None;
+    }
+    let sortable = term.parse::<u64>().ok()?;
+    let bits = if sortable & (1 << 63) != 0 { sortable ^ (1 << 63) } else { !sortable };
+    Some(f64::from_bits(bits))
+}
+
+pub fn parse_i64(value: &str) -> Result<i64, VelociError> {
+    value.trim().parse::<i64>().map_err(|_| invalid_value(value, "an integer"))
+}
+
+/// Parses the float, `NaN` is not allowed, since it has no order.
+pub fn parse_f64(value: &str) -> Result<f64, VelociError> {
+    value.trim().parse::<f64>().ok().filter(|val| !val.is_nan()).ok_or_else(|| invalid_value(value, "a float"))
+}
+
+/// Parses `true` or `false`
+pub fn parse_bool(value: &str) -> Result<bool, VelociError> {
+    match value.trim() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(invalid_value(value, "a bool")),
+    }
+}
+
+pub fn bool_to_term(value: bool) -> &'static str {
+    if value {
+        "true"
+    } else {
+        "false"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_i64_term_sort_order() {
+        let values = vec![-1_000_000, -1, 0, 1, 9, 10, 100, i64::max_value(), i64::min_value()];
+        for value in &values {
+            assert_eq!(term_to_i64(&i64_to_term(*value)), Some(*value));
+        }
+        let mut by_term = values.clone();
+        by_term.sort_by_key(|el| i64_to_term(*el));
+        let mut by_value = values;
+        by_value.sort();
+        assert_eq!(by_term, by_value);
+    }
+
+    #[test]
+    fn test_f64_term_sort_order() {
+        let values = vec![-1_000_000.5, -1.0, -0.25, 0.0, 0.25, 1.0, 9.0, 10.0, 100.5, std::f64::INFINITY, std::f64::NEG_INFINITY];
+        for value in &values {
+            assert_eq!(term_to_f64(&f64_to_term(*value)), Some(*value));
+        }
+        let mut by_term = values.clone();
+        by_term.sort_by_key(|el| f64_to_term(*el));
+        let mut by_value = values;
+        by_value.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(by_term, by_value);
+        assert_eq!(f64_to_term(-0.0), f64_to_term(0.0));
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_i64(" 10").unwrap(), 10);
+        assert!(parse_i64("10.5").is_err());
+        assert_eq!(parse_f64("10").unwrap(), 10.0);
+        assert!(parse_f64("NaN").is_err());
+        assert_eq!(parse_bool("true").unwrap(), true);
+        assert!(parse_bool("yes").is_err());
+    }
+}
@@ -28,6 +28,7 @@ use std::{
     path::{Path},
     str,
     str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
     time::Duration,
     u32,
 };
@@ -86,6 +87,21 @@ pub struct Persistence {
     pub indices: PersistenceIndices,
     pub lru_cache: HashMap<String, LruCache<RequestSearchPart, SearchResult>>,
     pub term_boost_cache: RwLock<LruCache<Vec<RequestSearchPart>, Vec<SearchFieldResult>>>,
+    pub term_boost_cache_stats: CacheStats,
+}
+
+/// Hit and miss counters of a cache
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+}
+
+impl CacheStats {
+    pub(crate) fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 impl fmt::Debug for Persistence {
@@ -254,7 +270,7 @@ impl Persistence {
         Ok(Box::new(store))
     }
 
-    fn load_indices(&mut self) -> Result<(), VelociError> {
+    fn load_indices(&mut self, loading_type_override: Option<LoadingType>) -> Result<(), VelociError> {
         info_time!("loaded persistence {:?}", &self.db);
 
         let doc_offsets_file = self.get_file_handle("data.offsets")?;
@@ -265,7 +281,7 @@ impl Persistence {
         for el in self.metadata.columns.iter().flat_map(|col| col.1.indices.iter()) {
             let indirect_path = get_file_path(&self.db, &el.path).set_ext(Ext::Indirect);
             let indirect_data_path = get_file_path(&self.db, &el.path).set_ext(Ext::Data);
-            let loading_type = get_loading_type(el.loading_type)?;
+            let loading_type = get_loading_type(el.loading_type, loading_type_override)?;
             match el.index_category {
                 IndexCategory::Phrase => {
                     //Insert dummy index, to seperate between emtpy indexes and nonexisting indexes
@@ -506,11 +522,17 @@ impl Persistence {
             db,
             lru_cache: HashMap::default(),
             term_boost_cache: RwLock::new(LruCache::with_expiry_duration_and_capacity(Duration::new(3600, 0), 10)),
+            term_boost_cache_stats: CacheStats::default(),
             indices: PersistenceIndices::default(),
         })
     }
 
     pub fn load<P: AsRef<Path>>(db: P) -> Result<Self, VelociError> {
+        Self::load_with_loading_type(db, None)
+    }
+
+    /// Loads the persistence, `loading_type` overrules the loading type of the indices and the `LoadingType` environment variable
+    pub fn load_with_loading_type<P: AsRef<Path>>(db: P, loading_type: Option<LoadingType>) -> Result<Self, VelociError> {
         let metadata = PeristenceMetaData::new(db.as_ref().to_str().unwrap())?;
         let mut pers = Persistence {
             persistence_type: PersistenceType::Persistent,
@@ -518,9 +540,10 @@ impl Persistence {
             db: db.as_ref().to_str().unwrap().to_string(),
             lru_cache: HashMap::default(),
             term_boost_cache: RwLock::new(LruCache::with_expiry_duration_and_capacity(Duration::new(3600, 0), 10)),
+            term_boost_cache_stats: CacheStats::default(),
             indices: PersistenceIndices::default(),
         };
-        pers.load_indices()?;
+        pers.load_indices(loading_type)?;
         pers.print_heap_sizes();
         Ok(pers)
     }
@@ -541,18 +564,7 @@ impl Persistence {
 
         // info!("totale size {}", get_readable_size(total_size));
 
-        let mut print_and_size = vec![];
-        // for (k, v) in &self.indices.key_value_stores {
-        //     print_and_size.push((v.heap_size_of_children(), v.type_name(), k));
-        // }
-        // for (k, v) in &self.indices.token_to_anchor_score {
-        //     print_and_size.push((v.heap_size_of_children(), v.type_name(), k));
-        // }
-        for (k, v) in &self.indices.fst {
-            print_and_size.push((v.as_fst().size(), "FST".to_string(), k));
-        }
-        // Sort by size
-        print_and_size.sort_by_key(|row| row.0);
+        let print_and_size = self.get_index_sizes();
 
         // Create the table
         let mut table = Table::new();
@@ -565,6 +577,26 @@ impl Persistence {
         info!("{}", table);
     }
 
+    /// Size in bytes, type and path of the loaded indices and the document store, sorted by size.
+    ///
+    /// The type of the other indices is their `IndexCategory`, their size is the size on disk, so it's 0 for transient persistences.
+    pub fn get_index_sizes(&self) -> Vec<(usize, String, String)> {
+        let mut index_sizes = vec![];
+        for (k, v) in &self.indices.fst {
+            index_sizes.push((v.as_fst().size(), "FST".to_string(), k.to_string()));
+        }
+        for index in self.metadata.columns.values().flat_map(|field_info| field_info.indices.iter()) {
+            index_sizes.push((self.get_index_file_size(&index.path) as usize, format!("{:?}", index.index_category), index.path.to_string()));
+        }
+        if self.indices.doc_offsets.is_some() {
+            let size = self.get_file_size("data") + self.get_file_size("data.offsets");
+            index_sizes.push((size as usize, "DocStore".to_string(), "data".to_string()));
+        }
+        // Sort by size
+        index_sizes.sort_by_key(|row| row.0);
+        index_sizes
+    }
+
     pub fn temp_dir(&self) -> String {
         self.db.to_string() + "/temp"
     }
@@ -594,7 +626,10 @@ fn load_type_from_env() -> Result<Option<LoadingType>, VelociError> {
     }
 }
 
-fn get_loading_type(loading_type: LoadingType) -> Result<LoadingType, VelociError> {
+fn get_loading_type(loading_type: LoadingType, loading_type_override: Option<LoadingType>) -> Result<LoadingType, VelociError> {
+    if let Some(val) = loading_type_override {
+        return Ok(val);
+    }
     let mut loading_type = loading_type;
     if let Some(val) = load_type_from_env()? {
         // Overrule Loadingtype from env
@@ -1,6 +1,6 @@
 use crate::search::{result::SearchFieldResult, FilterResult};
 use crossbeam_channel::{self, unbounded};
-use std::sync::Arc;
+use std::sync::{atomic::AtomicUsize, Arc};
 
 pub(crate) type PlanDataSender = crossbeam_channel::Sender<SearchFieldResult>;
 pub(crate) type PlanDataReceiver = crossbeam_channel::Receiver<SearchFieldResult>;
@@ -15,6 +15,8 @@ pub struct PlanStepDataChannels {
     pub num_receivers: u32,
     pub receiver_for_next_step: PlanDataReceiver, // used in plan_creation
     pub filter_channel: Option<FilterChannel>,    // Sending result as filter output to receivers
+    /// Number of hits sent to the next steps, used for profiling
+    pub num_output_hits: Arc<AtomicUsize>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +26,8 @@ pub struct FilterChannel {
     pub filter_sender: PlanDataFilterSender,
     pub filter_receiver: PlanDataFilterReceiver,
     pub num_receivers: u32,
+    /// Send the ids of the scored hits instead of the ids only hits, used to prefilter other branches of an intersection
+    pub from_score_hits: bool,
 }
 
 impl Default for FilterChannel {
@@ -33,6 +37,7 @@ impl Default for FilterChannel {
             num_receivers: 0,
             filter_sender: tx,
             filter_receiver: rx,
+            from_score_hits: false,
         }
     }
 }
@@ -47,6 +52,7 @@ impl Default for PlanStepDataChannels {
             receiver_for_next_step: rx,
             filter_receiver: None,
             filter_channel: None,
+            num_output_hits: Arc::new(AtomicUsize::new(0)),
         }
     }
 }
@@ -62,6 +68,7 @@ impl PlanStepDataChannels {
             // output_sending_to_next_steps_as_filter: None,
             filter_receiver: None,
             filter_channel: None,
+            num_output_hits: Arc::new(AtomicUsize::new(0)),
         }
     }
 }
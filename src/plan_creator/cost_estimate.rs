@@ -0,0 +1,153 @@
+use crate::{
+    persistence::{Persistence, *},
+    search::*,
+    util::{self, extract_field_name, StringAdd},
+};
+use std::fmt;
+
+/// When the smallest input of an intersection is estimated to be this factor smaller than the next one,
+/// the other inputs are only resolved to anchors which are hit by the smallest input.
+pub(crate) const PREFILTER_SELECTIVITY_FACTOR: f32 = 10.0;
+
+/// Estimated fraction of terms in a field which are hit by a regex.
+const REGEX_TERM_RATIO: f32 = 0.1;
+/// Estimated fraction of terms in a field which are hit by a starts_with search.
+const STARTS_WITH_TERM_RATIO: f32 = 0.05;
+/// Estimated number of additional terms per levenshtein distance.
+const TERMS_PER_LEVENSHTEIN_DISTANCE: f32 = 5.0;
+
+/// Estimated cardinality of a part of the search request tree, calculated from the index statistics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CardinalityEstimate {
+    /// Estimated number of terms hit in the fst
+    pub num_terms: f32,
+    /// Estimated number of anchor hits
+    pub num_hits: f32,
+}
+
+impl fmt::Display for CardinalityEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "est. terms {:.0}, est. hits {:.0}", self.num_terms, self.num_hits)
+    }
+}
+
+/// Estimates the cardinality of search requests, using `IndexValuesMetadata` and the fst sizes
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CostEstimator<'a> {
+    persistence: &'a Persistence,
+}
+
+impl<'a> CostEstimator<'a> {
+    pub(crate) fn new(persistence: &'a Persistence) -> Self {
+        CostEstimator { persistence }
+    }
+
+    fn cap_to_num_docs(&self, num_hits: f32) -> f32 {
+        let num_docs = self.persistence.get_number_of_documents() as f32;
+        if num_docs > 0.0 {
+            num_hits.min(num_docs)
+        } else {
+            num_hits
+        }
+    }
+
+    pub(crate) fn estimate_request(&self, request: &SearchRequest) -> CardinalityEstimate {
+        match request {
+            SearchRequest::Or(SearchTree { queries, .. }) => {
+                let estimates: Vec<_> = queries.iter().map(|query| self.estimate_request(query)).collect();
+                CardinalityEstimate {
+                    num_terms: estimates.iter().map(|el| el.num_terms).sum(),
+                    num_hits: self.cap_to_num_docs(estimates.iter().map(|el| el.num_hits).sum()),
+                }
+            }
+            SearchRequest::And(SearchTree { queries, .. }) => {
+                let estimates: Vec<_> = queries.iter().map(|query| self.estimate_request(query)).collect();
+                let min_hits = estimates.iter().map(|el| el.num_hits).fold(f32::MAX, f32::min);
+                let num_docs = self.persistence.get_number_of_documents() as f32;
+                // assume independent subqueries, the result can't be larger than the smallest input
+                let num_hits = if num_docs > 0.0 {
+                    let selectivity: f32 = estimates.iter().map(|el| el.num_hits / num_docs).product();
+                    (selectivity * num_docs).min(min_hits)
+                } else {
+                    min_hits
+                };
+                CardinalityEstimate {
+                    num_terms: estimates.iter().map(|el| el.num_terms).sum(),
+                    num_hits: if estimates.is_empty() { 0.0 } else { num_hits },
+                }
+            }
+            SearchRequest::Search(part) => self.estimate_search_part(part),
+            // the conditions match on the elements, at most as many documents as the unscoped subquery
+            SearchRequest::Nested(nested) => self.estimate_request(&nested.query),
+            SearchRequest::Exists(exists) => CardinalityEstimate {
+                num_terms: 0.0,
+                num_hits: self.estimate_anchors_with_value(&exists.path),
+            },
+            SearchRequest::Missing(missing) => CardinalityEstimate {
+                num_terms: 0.0,
+                num_hits: (self.persistence.get_number_of_documents() as f32 - self.estimate_anchors_with_value(&missing.path)).max(0.0),
+            },
+        }
+    }
+
+    /// Number of anchors with a value in the field, estimated from the number of keys with values in the index of the first step.
+    /// For multi-step paths without `ANCHOR_TO_TEXT_ID` this is an upper bound, assume all documents match without statistics.
+    fn estimate_anchors_with_value(&self, path: &str) -> f32 {
+        let num_docs = self.persistence.get_number_of_documents() as f32;
+        let steps = util::get_steps_to_anchor(path);
+        let index_path = if steps.len() > 1 && self.persistence.has_index(&steps.last().unwrap().add(ANCHOR_TO_TEXT_ID)) {
+            steps.last().unwrap().add(ANCHOR_TO_TEXT_ID)
+        } else {
+            steps.first().unwrap().add(PARENT_TO_VALUE_ID)
+        };
+        match self.persistence.get_valueid_to_parent(index_path) {
+            Ok(index) => self.cap_to_num_docs(index.get_index_meta_data().num_values as f32),
+            Err(_) => num_docs,
+        }
+    }
+
+    pub(crate) fn estimate_search_part(&self, part: &RequestSearchPart) -> CardinalityEstimate {
+        let path = if part.path.ends_with(TEXTINDEX) {
+            part.path.to_string()
+        } else {
+            part.path.add(TEXTINDEX)
+        };
+        let num_terms_in_field = match self.persistence.indices.fst.get(&path) {
+            Some(map) => map.len() as f32,
+            None => return CardinalityEstimate::default(),
+        };
+
+        let mut num_terms = if part.is_regex {
+            num_terms_in_field * REGEX_TERM_RATIO
+        } else if part.starts_with {
+            num_terms_in_field * STARTS_WITH_TERM_RATIO
+        } else {
+            1.0 + part.levenshtein_distance.unwrap_or(0) as f32 * TERMS_PER_LEVENSHTEIN_DISTANCE
+        };
+        num_terms = num_terms.max(1.0).min(num_terms_in_field);
+        if let Some(top) = part.top {
+            num_terms = num_terms.min((top + part.skip.unwrap_or(0)) as f32);
+        }
+
+        CardinalityEstimate {
+            num_terms,
+            num_hits: self.cap_to_num_docs(num_terms * self.get_avg_hits_per_term(&path, num_terms_in_field)),
+        }
+    }
+
+    /// The average number of anchors per term is stored in the metadata of the token to anchor index.
+    /// If no metadata is available (e.g. in a transient persistence), the average is derived from the number of documents.
+    fn get_avg_hits_per_term(&self, path: &str, num_terms_in_field: f32) -> f32 {
+        let anchor_score_path = path.add(TO_ANCHOR_ID_SCORE);
+        let avg_join_size = self
+            .persistence
+            .metadata
+            .columns
+            .get(&extract_field_name(path))
+            .and_then(|field_info| field_info.indices.iter().find(|index| index.path == anchor_score_path))
+            .map(|index| index.metadata.avg_join_size)
+            .filter(|avg_join_size| *avg_join_size > 0.0);
+
+        avg_join_size.unwrap_or_else(|| (self.persistence.get_number_of_documents() as f32 / num_terms_in_field.max(1.0)).max(1.0))
+    }
+}
@@ -2,7 +2,7 @@
 use crate::{
     error::*,
     persistence::{Persistence, *},
-    plan_creator::{channel::*, plan::*, plan_steps::*, PlanStepTrait},
+    plan_creator::{channel::*, cost_estimate::*, plan::*, plan_steps::*, PlanStepTrait},
     search::*,
     util::{self, StringAdd},
 };
@@ -74,6 +74,10 @@ fn get_all_field_request_parts_and_propagate_settings_to_search_req<'a>(
             search.options.explain |= header_request.explain;
             map.insert(search);
         }
+        SearchRequest::Nested(nested) => {
+            get_all_field_request_parts_and_propagate_settings_to_search_req(header_request, &mut nested.query, map);
+        }
+        SearchRequest::Exists(_) | SearchRequest::Missing(_) => {}
     }
 }
 
@@ -81,24 +85,30 @@ fn get_all_field_request_parts_and_propagate_settings_to_search_req<'a>(
 ///
 /// The function also propagates settings before collecting requests, because this changes the equality. This should be probably done seperately.
 ///
-fn collect_all_field_request_into_cache(header_request: &Request, request: &mut Request, plan: &mut Plan) -> FieldRequestCache {
+fn collect_all_field_request_into_cache(header_request: &Request, request: &mut Request, plan: &mut Plan, estimator: CostEstimator<'_>) -> FieldRequestCache {
     let mut field_search_cache = FnvHashMap::default();
     let mut field_requests = FnvHashSet::default();
     get_all_field_request_parts_and_propagate_settings(header_request, request, &mut field_requests);
-    add_request_to_search_field_cache(field_requests, plan, &mut field_search_cache, false);
+    add_request_to_search_field_cache(field_requests, plan, &mut field_search_cache, false, estimator);
 
     // collect filter requests seperately and set to fetch ids
     // This way we can potentially reuse the same request to emit both, score and ids
     if let Some(filter) = request.filter.as_mut() {
         let mut field_requests = FnvHashSet::default();
         get_all_field_request_parts_and_propagate_settings_to_search_req(header_request, filter, &mut field_requests);
-        add_request_to_search_field_cache(field_requests, plan, &mut field_search_cache, true);
+        add_request_to_search_field_cache(field_requests, plan, &mut field_search_cache, true, estimator);
     };
 
     field_search_cache
 }
 
-fn add_request_to_search_field_cache<'a>(field_requests: FnvHashSet<&'a mut RequestSearchPart>, plan: &mut Plan, field_search_cache: &mut FieldRequestCache, ids_only: bool) {
+fn add_request_to_search_field_cache<'a>(
+    field_requests: FnvHashSet<&'a mut RequestSearchPart>,
+    plan: &mut Plan,
+    field_search_cache: &mut FieldRequestCache,
+    ids_only: bool,
+    estimator: CostEstimator<'_>,
+) {
     for request_part in field_requests {
         // There could be the same query for filter and normal search, then we load scores and ids
         if let Some((_, field_search)) = field_search_cache.get_mut(&request_part) {
@@ -115,6 +125,7 @@ fn add_request_to_search_field_cache<'a>(field_requests: FnvHashSet<&'a mut Requ
         let field_search = PlanStepFieldSearchToTokenIds {
             req: plan_request_part,
             channel: PlanStepDataChannels::open_channel(0, vec![]),
+            estimate: estimator.estimate_search_part(request_part),
         };
         let step_id = plan.add_step(Box::new(field_search.clone())); // this is actually only a placeholder in the plan, will be replaced with the data from the field_search_cache after plan creation
 
@@ -122,15 +133,19 @@ fn add_request_to_search_field_cache<'a>(field_requests: FnvHashSet<&'a mut Requ
     }
 }
 
-pub fn plan_creator(mut request: Request, plan: &mut Plan) {
+/// Creates the execution plan for the request.
+///
+/// The index statistics in `persistence` are used to estimate the cardinality of each field search, which decides the order of intersections.
+pub fn plan_creator(mut request: Request, plan: &mut Plan, persistence: &Persistence) {
     let request_header = request.clone();
+    let estimator = CostEstimator::new(persistence);
 
-    let mut field_search_cache = collect_all_field_request_into_cache(&request_header, &mut request, plan);
+    let mut field_search_cache = collect_all_field_request_into_cache(&request_header, &mut request, plan, estimator);
 
     let filter_final_step_id: Option<PlanStepId> = if let Some(filter) = request.filter.as_mut() {
         // get_all_field_request_parts_and_propagate_settings_to_search_req(header_request, filter, map);
         // collect_all_field_request_into_cache(&request_header, filter, &mut field_search_cache, plan, true);
-        let final_output_filter = plan_creator_2(true, true, None, &request_header, &*filter, vec![], plan, None, None, &mut field_search_cache);
+        let final_output_filter = plan_creator_2(true, true, None, &request_header, &*filter, vec![], plan, None, None, &mut field_search_cache, estimator);
         Some(final_output_filter)
     } else {
         None
@@ -150,6 +165,7 @@ pub fn plan_creator(mut request: Request, plan: &mut Plan) {
             None,
             filter_final_step_id,
             &mut field_search_cache,
+            estimator,
         )
     };
     // Add intersect step the search result with the filter
@@ -273,6 +289,7 @@ fn plan_creator_2(
     parent_step_dependecy: Option<usize>,
     depends_on_step: Option<usize>,
     field_search_cache: &mut FieldRequestCache,
+    estimator: CostEstimator<'_>,
 ) -> PlanStepId {
     // request.explain |= request_header.explain;
 
@@ -286,7 +303,11 @@ fn plan_creator_2(
             if is_filter_channel {
                 channel.filter_channel = Some(FilterChannel::default());
             }
-            let step = Union { ids_only: is_filter, channel };
+            let step = Union {
+                ids_only: is_filter,
+                channel,
+                estimate: estimator.estimate_request(request),
+            };
             let step_id = plan.add_step(Box::new(step));
             let result_channels_from_prev_steps = queries
                 .iter()
@@ -304,6 +325,7 @@ fn plan_creator_2(
                         Some(step_id),
                         depends_on_step,
                         field_search_cache,
+                        estimator,
                     );
                     plan.get_step_channel(step_id).receiver_for_next_step.clone()
                 })
@@ -328,28 +350,49 @@ fn plan_creator_2(
             if is_filter_channel {
                 channel.filter_channel = Some(FilterChannel::default());
             }
-            let step = Intersect { ids_only: is_filter, channel };
+            let step = Intersect {
+                ids_only: is_filter,
+                channel,
+                estimate: estimator.estimate_request(request),
+            };
             let step_id = plan.add_step(Box::new(step));
-            let result_channels_from_prev_steps = queries
-                .iter()
-                .map(|x| {
-                    // x.explain = request_header.explain;
-                    let boost = merge_vec(&boost, &x.get_boost());
-                    let step_id = plan_creator_2(
-                        is_filter,
-                        false,
-                        filter_channel_step,
-                        request_header,
-                        x,
-                        boost,
-                        plan,
-                        Some(step_id),
-                        depends_on_step,
-                        field_search_cache,
-                    );
-                    plan.get_step_channel(step_id).receiver_for_next_step.clone()
-                })
-                .collect();
+
+            // Start with the most selective subquery, so the intersection works on the smallest inputs first
+            let mut queries: Vec<(CardinalityEstimate, &SearchRequest)> = queries.iter().map(|query| (estimator.estimate_request(query), query)).collect();
+            queries.sort_by(|a, b| a.0.num_hits.partial_cmp(&b.0.num_hits).unwrap_or(std::cmp::Ordering::Equal));
+
+            // If the smallest subquery is much more selective than the rest, the other subqueries are resolved only to the anchors hit by it.
+            // A step can only receive from one filter channel, so this is not possible if there is already a filter.
+            let prefilter_with_smallest =
+                !is_filter && filter_channel_step.is_none() && queries.len() > 1 && queries[0].0.num_hits * PREFILTER_SELECTIVITY_FACTOR <= queries[1].0.num_hits;
+
+            let mut prefilter_step = None;
+            let mut result_channels_from_prev_steps = vec![];
+            for (_estimate, x) in queries {
+                // x.explain = request_header.explain;
+                let boost = merge_vec(&boost, &x.get_boost());
+                let child_step_id = plan_creator_2(
+                    is_filter,
+                    false,
+                    prefilter_step.or(filter_channel_step),
+                    request_header,
+                    x,
+                    boost,
+                    plan,
+                    Some(step_id),
+                    prefilter_step.or(depends_on_step),
+                    field_search_cache,
+                    estimator,
+                );
+                if prefilter_with_smallest && prefilter_step.is_none() {
+                    plan.get_step_channel(child_step_id).filter_channel = Some(FilterChannel {
+                        from_score_hits: true,
+                        ..Default::default()
+                    });
+                    prefilter_step = Some(child_step_id);
+                }
+                result_channels_from_prev_steps.push(plan.get_step_channel(child_step_id).receiver_for_next_step.clone());
+            }
             plan.get_step_channel(step_id).input_prev_steps = result_channels_from_prev_steps;
 
             if let Some(parent_step_dependecy) = parent_step_dependecy {
@@ -374,11 +417,106 @@ fn plan_creator_2(
                 parent_step_dependecy,
                 depends_on_step,
                 field_search_cache,
+                estimator,
             )
         }
+        SearchRequest::Nested(nested) => plan_creator_nested(
+            is_filter,
+            is_filter_channel,
+            filter_channel_step,
+            nested,
+            request_header,
+            plan,
+            parent_step_dependecy,
+            depends_on_step,
+            field_search_cache,
+            estimator,
+        ),
+        SearchRequest::Exists(exists) | SearchRequest::Missing(exists) => {
+            let mut channel = PlanStepDataChannels::open_channel(1, vec![]);
+            if let Some(step_id) = filter_channel_step {
+                plan.get_step_channel(step_id).filter_channel.as_mut().unwrap().num_receivers += 1;
+                channel.filter_receiver = Some(plan.get_step_channel(step_id).filter_channel.as_mut().unwrap().filter_receiver.clone());
+            }
+            if is_filter_channel {
+                channel.filter_channel = Some(FilterChannel::default());
+            }
+            let step = ExistsToAnchor {
+                request: exists.clone(),
+                missing: matches!(request, SearchRequest::Missing(_)),
+                ids_only: is_filter,
+                channel,
+                estimate: estimator.estimate_request(request),
+            };
+            let step_id = plan.add_step(Box::new(step));
+            if let Some(parent_step_dependecy) = parent_step_dependecy {
+                plan.add_dependency(parent_step_dependecy, step_id);
+            }
+            if let Some(depends_on_step) = depends_on_step {
+                plan.add_dependency(step_id, depends_on_step);
+            }
+            step_id
+        }
     }
 }
 
+/// The field searches of the nested query are resolved in one step to the nested elements, intersected there and then joined to the anchor
+fn plan_creator_nested(
+    is_filter: bool,
+    is_filter_channel: bool,
+    filter_channel_step: Option<usize>,
+    nested: &NestedSearch,
+    request: &Request,
+    plan: &mut Plan,
+    parent_step_dependecy: Option<usize>,
+    depends_on_step: Option<usize>,
+    field_search_cache: &mut FieldRequestCache,
+    estimator: CostEstimator<'_>,
+) -> PlanStepId {
+    let store_term_id_hits = request.why_found || request.text_locality;
+    let mut field_search_step_ids = vec![];
+    let mut field_rxs = vec![];
+    for request_part in nested.query.get_search_parts() {
+        let (field_search_step_id, field_search_step) = field_search_cache
+            .get_mut(request_part)
+            .unwrap_or_else(|| panic!("PlanCreator: Could not find nested request in field_search_cache {:?}", request_part));
+        // the elements are resolved with scores, also in filters
+        field_search_step.req.get_scores = true;
+        field_search_step.req.store_term_texts |= request.why_found;
+        field_search_step.req.store_term_id_hits |= store_term_id_hits;
+        field_search_step.channel.num_receivers += 1;
+        field_rxs.push(field_search_step.channel.receiver_for_next_step.clone());
+        field_search_step_ids.push(*field_search_step_id);
+    }
+
+    let mut channel = PlanStepDataChannels::open_channel(1, field_rxs);
+    if let Some(step_id) = filter_channel_step {
+        plan.get_step_channel(step_id).filter_channel.as_mut().unwrap().num_receivers += 1;
+        channel.filter_receiver = Some(plan.get_step_channel(step_id).filter_channel.as_mut().unwrap().filter_receiver.clone());
+    }
+    if is_filter_channel {
+        channel.filter_channel = Some(FilterChannel::default());
+    }
+    let step = NestedToAnchor {
+        nested: nested.clone(),
+        ids_only: is_filter,
+        channel,
+        estimate: estimator.estimate_request(&nested.query),
+    };
+    let step_id = plan.add_step(Box::new(step));
+    for field_search_step_id in field_search_step_ids {
+        plan.add_dependency(step_id, field_search_step_id);
+    }
+
+    if let Some(parent_step_dependecy) = parent_step_dependecy {
+        plan.add_dependency(parent_step_dependecy, step_id);
+    }
+    if let Some(depends_on_step) = depends_on_step {
+        plan.add_dependency(step_id, depends_on_step);
+    }
+    step_id
+}
+
 fn plan_creator_search_part(
     is_filter_channel: bool,
     filter_channel_step: Option<usize>,
@@ -389,6 +527,7 @@ fn plan_creator_search_part(
     parent_step_dependecy: Option<usize>,
     depends_on_step: Option<usize>,
     field_search_cache: &mut FieldRequestCache,
+    estimator: CostEstimator<'_>,
 ) -> PlanStepId {
     let paths = util::get_steps_to_anchor(&request_part.path);
     let store_term_id_hits = request.why_found || request.text_locality;
@@ -449,6 +588,7 @@ fn plan_creator_search_part(
             let token_to_anchor_step = ResolveTokenIdToAnchor {
                 request: request_part.clone(),
                 channel: channel.clone(),
+                estimate: estimator.estimate_search_part(request_part),
             };
             let token_to_anchor_step_id = plan.add_step(Box::new(token_to_anchor_step));
 
@@ -513,6 +653,7 @@ fn plan_creator_search_part(
     let token_to_anchor_step = ResolveTokenIdToAnchor {
         request: request_part.clone(),
         channel,
+        estimate: estimator.estimate_search_part(request_part),
     };
     let id1 = plan.add_step(Box::new(token_to_anchor_step));
     plan.add_dependency(id1, *field_search_step_id);
@@ -528,8 +669,11 @@ fn plan_creator_search_part(
 
 use rayon::prelude::*;
 
-pub fn execute_steps(steps: Vec<Box<dyn PlanStepTrait>>, persistence: &Persistence) -> Result<(), VelociError> {
-    let r: Result<Vec<_>, VelociError> = steps.into_par_iter().map(|step: Box<dyn PlanStepTrait>| step.execute_step(persistence)).collect();
+pub fn execute_steps(steps: Vec<Box<dyn PlanStepTrait>>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+    let r: Result<Vec<_>, VelociError> = steps
+        .into_par_iter()
+        .map(|step: Box<dyn PlanStepTrait>| step.execute_step(persistence, cancellation_token))
+        .collect();
 
     if let Err(err) = r {
         Err(err)
@@ -537,3 +681,19 @@ pub fn execute_steps(steps: Vec<Box<dyn PlanStepTrait>>, persistence: &Persisten
         Ok(())
     }
 }
+
+/// Like `execute_steps`, but returns the wall-clock time of each step together with its position in the plan
+pub(crate) fn execute_steps_profiled(
+    steps: Vec<(usize, Box<dyn PlanStepTrait>)>,
+    persistence: &Persistence,
+    cancellation_token: &CancellationToken,
+) -> Result<Vec<(usize, std::time::Duration)>, VelociError> {
+    steps
+        .into_par_iter()
+        .map(|(step_id, step): (usize, Box<dyn PlanStepTrait>)| {
+            let start = std::time::Instant::now();
+            step.execute_step(persistence, cancellation_token)?;
+            Ok((step_id, start.elapsed()))
+        })
+        .collect()
+}
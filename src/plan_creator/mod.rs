@@ -1,15 +1,23 @@
-use crate::{error::VelociError, persistence::Persistence};
+use crate::{error::VelociError, persistence::Persistence, search::CancellationToken};
 use channel::PlanStepDataChannels;
 use std::fmt::Display;
 
 use core::fmt::Debug;
 
 pub mod channel;
+pub mod cost_estimate;
 pub mod execution_plan;
 pub mod plan;
 pub mod plan_steps;
+pub mod profile;
 
 pub trait PlanStepTrait: Debug + Display + Sync + Send {
     fn get_channel(&mut self) -> &mut PlanStepDataChannels;
-    fn execute_step(self: Box<Self>, persistence: &Persistence) -> Result<(), VelociError>;
+    fn execute_step(self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError>;
+    /// Name of the step type, used for profiling
+    fn get_step_name(&self) -> &'static str;
+    /// The field the step operates on, used for profiling
+    fn get_field(&self) -> Option<&str> {
+        None
+    }
 }
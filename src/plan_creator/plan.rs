@@ -46,6 +46,14 @@ impl Plan {
     // }
 
     pub fn get_ordered_steps(self) -> Vec<Vec<Box<dyn PlanStepTrait>>> {
+        self.get_ordered_steps_with_ids()
+            .into_iter()
+            .map(|steps| steps.into_iter().map(|step_with_index| step_with_index.1).collect())
+            .collect()
+    }
+
+    /// Returns the steps grouped by execution order, together with their position in the plan
+    pub(crate) fn get_ordered_steps_with_ids(self) -> Vec<Vec<(usize, Box<dyn PlanStepTrait>)>> {
         let mut ordered_steps = vec![];
         let mut remaining_steps: Vec<_> = self.steps.into_iter().enumerate().collect();
         let dep = self.dependencies;
@@ -69,9 +77,7 @@ impl Plan {
             if steps_with_fullfilled_dependencies.is_empty() {
                 panic!("invalid plan created");
             }
-            // ordered_steps.push(steps_with_fullfilled_dependencies.iter().map(|step_with_index|*step_with_index.1.clone()).collect());
-            let vecco: Vec<_> = steps_with_fullfilled_dependencies.into_iter().map(|step_with_index| step_with_index.1).collect();
-            ordered_steps.push(vecco);
+            ordered_steps.push(steps_with_fullfilled_dependencies);
         }
         ordered_steps
     }
@@ -2,7 +2,7 @@
 use crate::{
     error::*,
     persistence::{Persistence, *},
-    plan_creator::{channel::*, execution_plan::*},
+    plan_creator::{channel::*, cost_estimate::CardinalityEstimate, execution_plan::*},
     search::{boost::*, *},
     util::StringAdd,
 };
@@ -19,11 +19,13 @@ use ordered_float::OrderedFloat;
 pub(crate) struct PlanStepFieldSearchToTokenIds {
     pub(crate) req: PlanRequestSearchPart,
     pub(crate) channel: PlanStepDataChannels,
+    pub(crate) estimate: CardinalityEstimate,
 }
 #[derive(Clone, Debug)]
 pub(crate) struct ResolveTokenIdToAnchor {
     pub(crate) request: RequestSearchPart,
     pub(crate) channel: PlanStepDataChannels,
+    pub(crate) estimate: CardinalityEstimate,
 }
 #[derive(Clone, Debug)]
 pub(crate) struct BoostToAnchor {
@@ -58,26 +60,51 @@ pub(crate) struct PlanStepPhrasePairToAnchorId {
 pub(crate) struct Union {
     pub(crate) ids_only: bool,
     pub(crate) channel: PlanStepDataChannels,
+    pub(crate) estimate: CardinalityEstimate,
 }
 #[derive(Clone, Debug)]
 pub(crate) struct Intersect {
     pub(crate) ids_only: bool,
     pub(crate) channel: PlanStepDataChannels,
+    pub(crate) estimate: CardinalityEstimate,
 }
 #[derive(Clone, Debug)]
 pub(crate) struct IntersectScoresWithIds {
     pub(crate) channel: PlanStepDataChannels,
 }
+#[derive(Clone, Debug)]
+pub(crate) struct ExistsToAnchor {
+    pub(crate) request: RequestExists,
+    pub(crate) missing: bool,
+    pub(crate) ids_only: bool,
+    pub(crate) channel: PlanStepDataChannels,
+    pub(crate) estimate: CardinalityEstimate,
+}
+/// Receives the field searches of a nested query, in the order of `SearchRequest::get_search_parts`
+#[derive(Clone, Debug)]
+pub(crate) struct NestedToAnchor {
+    pub(crate) nested: NestedSearch,
+    pub(crate) ids_only: bool,
+    pub(crate) channel: PlanStepDataChannels,
+    pub(crate) estimate: CardinalityEstimate,
+}
 
 impl std::fmt::Display for PlanStepFieldSearchToTokenIds {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "search {} {}", self.req.request.path, self.req.request.terms[0])?;
+        writeln!(
+            f,
+            "search {} {}",
+            self.req.request.path,
+            self.req.request.terms.first().map(String::as_str).unwrap_or_default()
+        )?;
+        writeln!(f, "{}", self.estimate)?;
         Ok(())
     }
 }
 impl std::fmt::Display for ResolveTokenIdToAnchor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "token to anchor")?;
+        writeln!(f, "token to anchor{}", if self.channel.filter_receiver.is_some() { " (filtered)" } else { "" })?;
+        writeln!(f, "est. hits {:.0}", self.estimate.num_hits)?;
         Ok(())
     }
 }
@@ -114,12 +141,14 @@ impl std::fmt::Display for PlanStepPhrasePairToAnchorId {
 impl std::fmt::Display for Union {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Union")?;
+        writeln!(f, "est. hits {:.0}", self.estimate.num_hits)?;
         Ok(())
     }
 }
 impl std::fmt::Display for Intersect {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Intersect")?;
+        writeln!(f, "est. hits {:.0}", self.estimate.num_hits)?;
         Ok(())
     }
 }
@@ -129,15 +158,39 @@ impl std::fmt::Display for IntersectScoresWithIds {
         Ok(())
     }
 }
+impl std::fmt::Display for ExistsToAnchor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {} to anchor", if self.missing { "missing" } else { "exists" }, self.request.path)?;
+        writeln!(f, "est. hits {:.0}", self.estimate.num_hits)?;
+        Ok(())
+    }
+}
+impl std::fmt::Display for NestedToAnchor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let filtered = if self.channel.filter_receiver.is_some() { " (filtered)" } else { "" };
+        writeln!(f, "nested {} to anchor{}", self.nested.path, filtered)?;
+        writeln!(f, "est. hits {:.0}", self.estimate.num_hits)?;
+        Ok(())
+    }
+}
 
 impl PlanStepTrait for PlanStepFieldSearchToTokenIds {
     fn get_channel(&mut self) -> &mut PlanStepDataChannels {
         &mut self.channel
     }
 
-    fn execute_step(mut self: Box<Self>, persistence: &Persistence) -> Result<(), VelociError> {
-        let field_result = search_field::get_term_ids_in_field(persistence, &mut self.req)?;
-        send_result_to_channel(field_result, &self.channel)?;
+    fn get_step_name(&self) -> &'static str {
+        "PlanStepFieldSearchToTokenIds"
+    }
+
+    fn get_field(&self) -> Option<&str> {
+        Some(&self.req.request.path)
+    }
+
+    fn execute_step(mut self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let field_result = search_field::get_term_ids_in_field(persistence, &mut self.req, cancellation_token)?;
+        cancellation_token.check()?;
+        send_result_to_channel(field_result, &self.channel, cancellation_token)?;
         drop_channel(self.channel);
         Ok(())
     }
@@ -148,16 +201,25 @@ impl PlanStepTrait for ResolveTokenIdToAnchor {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, persistence: &Persistence) -> Result<(), VelociError> {
-        let res = self.channel.input_prev_steps[0].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
+    fn get_step_name(&self) -> &'static str {
+        "ResolveTokenIdToAnchor"
+    }
+
+    fn get_field(&self) -> Option<&str> {
+        Some(&self.request.path)
+    }
+
+    fn execute_step(self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let res = recv_data(&self.channel.input_prev_steps[0], cancellation_token)?;
         let filter_res = if let Some(ref filter_receiver) = self.channel.filter_receiver {
             let search_field_result = filter_receiver.recv().map_err(|_| VelociError::PlanExecutionRecvFailedFilter)?;
             Some(search_field_result)
         } else {
             None
         };
-        let field_result = resolve_token_to_anchor(persistence, &self.request, &filter_res, &res)?;
-        send_result_to_channel(field_result, &self.channel)?;
+        let field_result = resolve_token_to_anchor(persistence, &self.request, &filter_res, &res, cancellation_token)?;
+        cancellation_token.check()?;
+        send_result_to_channel(field_result, &self.channel, cancellation_token)?;
         drop_channel(self.channel);
         Ok(())
     }
@@ -172,9 +234,17 @@ impl PlanStepTrait for BoostToAnchor {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, persistence: &Persistence) -> Result<(), VelociError> {
+    fn get_step_name(&self) -> &'static str {
+        "BoostToAnchor"
+    }
+
+    fn get_field(&self) -> Option<&str> {
+        Some(&self.request.path)
+    }
+
+    fn execute_step(self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
         debug_time!("BoostToAnchor {} {}", self.request.path, self.boost.path);
-        let mut field_result = self.channel.input_prev_steps[0].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
+        let mut field_result = recv_data(&self.channel.input_prev_steps[0], cancellation_token)?;
 
         //TODO EXPLAIN INFO NOT RESPECTED IN THIS METHOD
         resolve_token_hits_to_text_id_ids_only(persistence, &self.request, &mut field_result)?;
@@ -186,7 +256,7 @@ impl PlanStepTrait for BoostToAnchor {
         let mut boost_field_path = (&self.boost.path).to_field_path();
         boost::get_boost_ids_and_resolve_to_anchor(persistence, &mut boost_field_path, &mut field_result)?;
 
-        send_result_to_channel(field_result, &self.channel)?;
+        send_result_to_channel(field_result, &self.channel, cancellation_token)?;
         drop_channel(self.channel);
         Ok(())
     }
@@ -201,14 +271,23 @@ impl PlanStepTrait for ApplyAnchorBoost {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, _persistence: &Persistence) -> Result<(), VelociError> {
-        let mut field_result = self.channel.input_prev_steps[0].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
+    fn get_step_name(&self) -> &'static str {
+        "ApplyAnchorBoost"
+    }
 
-        let boost_values = self.channel.input_prev_steps[1].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
+    fn get_field(&self) -> Option<&str> {
+        Some(&self.request.path)
+    }
+
+    fn execute_step(self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let mut field_result = recv_data(&self.channel.input_prev_steps[0], cancellation_token)?;
+
+        let boost_values = recv_data(&self.channel.input_prev_steps[1], cancellation_token)?;
 
-        apply_boost_values_anchor(&mut field_result, &self.boost, &mut boost_values.boost_ids.into_iter())?;
+        let expre = BoostExpression::from_boost_request(persistence, &self.boost)?;
+        apply_boost_values_anchor(&mut field_result, &self.boost, &expre, &mut boost_values.boost_ids.into_iter())?;
 
-        send_result_to_channel(field_result, &self.channel)?;
+        send_result_to_channel(field_result, &self.channel, cancellation_token)?;
         drop_channel(self.channel);
         Ok(())
     }
@@ -219,10 +298,18 @@ impl PlanStepTrait for BoostPlanStepFromBoostRequest {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, persistence: &Persistence) -> Result<(), VelociError> {
-        let mut input = self.channel.input_prev_steps[0].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
+    fn get_step_name(&self) -> &'static str {
+        "BoostPlanStepFromBoostRequest"
+    }
+
+    fn get_field(&self) -> Option<&str> {
+        Some(&self.req.path)
+    }
+
+    fn execute_step(self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let mut input = recv_data(&self.channel.input_prev_steps[0], cancellation_token)?;
         add_boost(persistence, &self.req, &mut input)?;
-        send_result_to_channel(input, &self.channel)?;
+        send_result_to_channel(input, &self.channel, cancellation_token)?;
         drop_channel(self.channel);
         Ok(())
     }
@@ -258,16 +345,24 @@ impl PlanStepTrait for BoostAnchorFromPhraseResults {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, _persistence: &Persistence) -> Result<(), VelociError> {
-        let input = self.channel.input_prev_steps[0].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
-        let boosts = get_data(&self.channel.input_prev_steps[1..])?;
+    fn get_step_name(&self) -> &'static str {
+        "BoostAnchorFromPhraseResults"
+    }
+
+    fn execute_step(self: Box<Self>, _persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let input = recv_data(&self.channel.input_prev_steps[0], cancellation_token)?;
+        let boosts = get_data(&self.channel.input_prev_steps[1..], cancellation_token)?;
         let mut boosts = sort_and_group_boosts_by_phrase_terms(boosts);
         //Set boost for phrases for the next step
         for boost_res in &mut boosts {
             boost_res.request.boost = Some(OrderedFloat(5.0));
         }
 
-        send_result_to_channel(boost_hits_ids_vec_multi(input, &mut boosts), &self.channel)?;
+        send_result_to_channel(
+            boost_hits_ids_vec_multi(input, &mut boosts, ExplainOperation::PhraseBoost),
+            &self.channel,
+            cancellation_token,
+        )?;
         drop_channel(self.channel);
         Ok(())
     }
@@ -277,13 +372,21 @@ impl PlanStepTrait for PlanStepPhrasePairToAnchorId {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, persistence: &Persistence) -> Result<(), VelociError> {
-        let res1 = self.channel.input_prev_steps[0].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
-        let res2 = self.channel.input_prev_steps[1].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
+    fn get_step_name(&self) -> &'static str {
+        "PlanStepPhrasePairToAnchorId"
+    }
+
+    fn get_field(&self) -> Option<&str> {
+        Some(&self.req.search1.path)
+    }
+
+    fn execute_step(self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let res1 = recv_data(&self.channel.input_prev_steps[0], cancellation_token)?;
+        let res2 = recv_data(&self.channel.input_prev_steps[1], cancellation_token)?;
         assert!(self.req.search1.path == self.req.search2.path);
         let mut res = get_anchor_for_phrases_in_search_results(persistence, &self.req.search1.path, &res1, &res2)?;
         res.phrase_boost = Some(self.req.clone());
-        send_result_to_channel(res, &self.channel)?;
+        send_result_to_channel(res, &self.channel, cancellation_token)?;
         drop_channel(self.channel);
         Ok(())
     }
@@ -294,13 +397,17 @@ impl PlanStepTrait for Union {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, _persistence: &Persistence) -> Result<(), VelociError> {
-        let res = if self.ids_only {
-            union_hits_ids(get_data(&self.channel.input_prev_steps.clone())?)
-        } else {
-            union_hits_score(get_data(&self.channel.input_prev_steps.clone())?)
-        };
-        send_result_to_channel(res, &self.channel)?;
+    fn get_step_name(&self) -> &'static str {
+        "Union"
+    }
+
+    fn execute_step(self: Box<Self>, _persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let data = get_data(&self.channel.input_prev_steps, cancellation_token)?;
+        cancellation_token.check()?;
+        // the union result can be as large as all inputs together
+        cancellation_token.check_additional_memory(data.iter().map(|res| res.get_memory_size()).sum())?;
+        let res = if self.ids_only { union_hits_ids(data) } else { union_hits_score(data) };
+        send_result_to_channel(res, &self.channel, cancellation_token)?;
         // send_result_to_channel(union_hits_score(get_data(&self.channel.clone().input_prev_steps)?), &self.channel)?;
         drop(self.channel.sender_to_next_steps);
         Ok(())
@@ -312,13 +419,15 @@ impl PlanStepTrait for Intersect {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, _persistence: &Persistence) -> Result<(), VelociError> {
-        let res = if self.ids_only {
-            intersect_hits_ids(get_data(&self.channel.input_prev_steps.clone())?)
-        } else {
-            intersect_hits_score(get_data(&self.channel.input_prev_steps.clone())?)
-        };
-        send_result_to_channel(res, &self.channel)?;
+    fn get_step_name(&self) -> &'static str {
+        "Intersect"
+    }
+
+    fn execute_step(self: Box<Self>, _persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let data = get_data(&self.channel.input_prev_steps, cancellation_token)?;
+        cancellation_token.check()?;
+        let res = if self.ids_only { intersect_hits_ids(data) } else { intersect_hits_score(data) };
+        send_result_to_channel(res, &self.channel, cancellation_token)?;
         drop(self.channel.sender_to_next_steps);
         Ok(())
     }
@@ -328,18 +437,78 @@ impl PlanStepTrait for IntersectScoresWithIds {
         &mut self.channel
     }
 
-    fn execute_step(self: Box<Self>, _persistence: &Persistence) -> Result<(), VelociError> {
+    fn get_step_name(&self) -> &'static str {
+        "IntersectScoresWithIds"
+    }
+
+    fn execute_step(self: Box<Self>, _persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
         info_time!("IntersectScoresWithIds");
-        let scores_res = self.channel.input_prev_steps[0].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
-        let ids_res = self.channel.input_prev_steps[1].recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
+        let scores_res = recv_data(&self.channel.input_prev_steps[0], cancellation_token)?;
+        let ids_res = recv_data(&self.channel.input_prev_steps[1], cancellation_token)?;
+        cancellation_token.check()?;
         trace!("IntersectScoresWithIds scores_res {} ids_res {}", scores_res, ids_res);
         let res = intersect_score_hits_with_ids(scores_res, ids_res);
-        send_result_to_channel(res, &self.channel)?;
+        send_result_to_channel(res, &self.channel, cancellation_token)?;
         drop(self.channel.sender_to_next_steps);
         Ok(())
     }
 }
 
+impl PlanStepTrait for ExistsToAnchor {
+    fn get_channel(&mut self) -> &mut PlanStepDataChannels {
+        &mut self.channel
+    }
+
+    fn get_step_name(&self) -> &'static str {
+        "ExistsToAnchor"
+    }
+
+    fn get_field(&self) -> Option<&str> {
+        Some(&self.request.path)
+    }
+
+    fn execute_step(self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let filter_res = if let Some(ref filter_receiver) = self.channel.filter_receiver {
+            Some(filter_receiver.recv().map_err(|_| VelociError::PlanExecutionRecvFailedFilter)?)
+        } else {
+            None
+        };
+        let res = exists::resolve_exists_to_anchor(persistence, &self.request, self.missing, &filter_res, self.ids_only, cancellation_token)?;
+        cancellation_token.check()?;
+        send_result_to_channel(res, &self.channel, cancellation_token)?;
+        drop_channel(self.channel);
+        Ok(())
+    }
+}
+
+impl PlanStepTrait for NestedToAnchor {
+    fn get_channel(&mut self) -> &mut PlanStepDataChannels {
+        &mut self.channel
+    }
+
+    fn get_step_name(&self) -> &'static str {
+        "NestedToAnchor"
+    }
+
+    fn get_field(&self) -> Option<&str> {
+        Some(&self.nested.path)
+    }
+
+    fn execute_step(self: Box<Self>, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+        let field_results = get_data(&self.channel.input_prev_steps, cancellation_token)?;
+        let filter_res = if let Some(ref filter_receiver) = self.channel.filter_receiver {
+            Some(filter_receiver.recv().map_err(|_| VelociError::PlanExecutionRecvFailedFilter)?)
+        } else {
+            None
+        };
+        cancellation_token.check()?;
+        let res = nested::resolve_nested_to_anchor(persistence, &self.nested, field_results, &filter_res, self.ids_only)?;
+        send_result_to_channel(res, &self.channel, cancellation_token)?;
+        drop_channel(self.channel);
+        Ok(())
+    }
+}
+
 fn drop_channel(channel: PlanStepDataChannels) {
     drop(channel.sender_to_next_steps);
     for el in channel.input_prev_steps {
@@ -350,11 +519,25 @@ fn drop_channel(channel: PlanStepDataChannels) {
     }
 }
 
-fn send_result_to_channel(field_result: SearchFieldResult, channel: &PlanStepDataChannels) -> Result<(), VelociError> {
+/// Sends the result to the next steps, the memory of each copy is accounted until it is received by `recv_data`
+fn send_result_to_channel(field_result: SearchFieldResult, channel: &PlanStepDataChannels, cancellation_token: &CancellationToken) -> Result<(), VelociError> {
+    cancellation_token.add_memory(field_result.get_memory_size() * channel.num_receivers.max(1) as usize)?;
+    // a field search for scores and ids sends the same hits in hits_scores and hits_ids, they are counted once
+    let num_output_hits = if field_result.hits_scores.is_empty() {
+        field_result.hits_ids.len()
+    } else {
+        field_result.hits_scores.len()
+    };
+    channel.num_output_hits.store(num_output_hits, std::sync::atomic::Ordering::Relaxed);
     //Send SearchFieldResult as Filter
     if let Some(ref filter_channel) = channel.filter_channel {
         debug_time!("convert filter");
-        let res = Arc::new(FilterResult::from_result(&field_result.hits_ids));
+        let res = if filter_channel.from_score_hits {
+            let ids: Vec<_> = field_result.hits_scores.iter().map(|hit| hit.id).collect();
+            Arc::new(FilterResult::from_result(&ids))
+        } else {
+            Arc::new(FilterResult::from_result(&field_result.hits_ids))
+        };
         // let res = Arc::new(field_result.clone());
         for _ in 0..filter_channel.num_receivers {
             filter_channel.filter_sender.send(Arc::clone(&res)).map_err(|_| VelociError::PlanExecutionSendFailed)?;
@@ -371,10 +554,17 @@ fn send_result_to_channel(field_result: SearchFieldResult, channel: &PlanStepDat
     Ok(())
 }
 
-fn get_data(input_prev_steps: &[PlanDataReceiver]) -> Result<Vec<SearchFieldResult>, VelociError> {
+/// Receives the result of a previous step and releases its memory, the receiving step accounts its own result when sending it
+fn recv_data(input_prev_step: &PlanDataReceiver, cancellation_token: &CancellationToken) -> Result<SearchFieldResult, VelociError> {
+    let res = input_prev_step.recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?;
+    cancellation_token.release_memory(res.get_memory_size());
+    Ok(res)
+}
+
+fn get_data(input_prev_steps: &[PlanDataReceiver], cancellation_token: &CancellationToken) -> Result<Vec<SearchFieldResult>, VelociError> {
     let mut dat = vec![];
     for el in input_prev_steps {
-        dat.push(el.recv().map_err(|_| VelociError::PlanExecutionRecvFailed)?);
+        dat.push(recv_data(el, cancellation_token)?);
     }
     Ok(dat)
 }
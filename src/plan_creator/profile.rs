@@ -0,0 +1,93 @@
+use crate::{
+    error::VelociError,
+    persistence::Persistence,
+    plan_creator::{execution_plan::execute_steps_profiled, plan::Plan},
+    search::CancellationToken,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// Profile of an executed plan step. The steps, which provide the input data for the step, are its children.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct PlanStepProfile {
+    pub step_id: usize,
+    pub step_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// sum of the hits received from the input steps
+    pub num_input_hits: usize,
+    pub num_output_hits: usize,
+    /// wall-clock time of the step execution
+    pub execution_time_ns: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub inputs: Vec<PlanStepProfile>,
+}
+
+#[derive(Debug)]
+struct StepInfo {
+    step_type: &'static str,
+    field: Option<String>,
+    input_step_ids: Vec<usize>,
+    num_output_hits: Arc<AtomicUsize>,
+    execution_time_ns: u64,
+}
+
+/// Executes the plan and measures hits and execution time for each step.
+///
+/// The returned profile tree starts with the step, which delivers the plan result.
+pub(crate) fn execute_plan_profiled(mut plan: Plan, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<Option<PlanStepProfile>, VelociError> {
+    let mut infos = collect_step_infos(&mut plan);
+    let final_step_id = plan
+        .plan_result
+        .clone()
+        .and_then(|plan_result| plan.steps.iter_mut().position(|step| step.get_channel().receiver_for_next_step.same_channel(&plan_result)));
+
+    for steps in plan.get_ordered_steps_with_ids() {
+        for (step_id, execution_time) in execute_steps_profiled(steps, persistence, cancellation_token)? {
+            infos[step_id].execution_time_ns = execution_time.as_nanos() as u64;
+        }
+    }
+
+    Ok(final_step_id.map(|step_id| to_profile_tree(step_id, &infos)))
+}
+
+/// The data flow between the steps is derived by comparing the input channels of a step with the output channels of all other steps
+fn collect_step_infos(plan: &mut Plan) -> Vec<StepInfo> {
+    let output_receivers: Vec<_> = plan.steps.iter_mut().map(|step| step.get_channel().receiver_for_next_step.clone()).collect();
+    plan.steps
+        .iter_mut()
+        .map(|step| {
+            let channel = step.get_channel();
+            let input_step_ids = channel
+                .input_prev_steps
+                .iter()
+                .filter_map(|input| output_receivers.iter().position(|output| output.same_channel(input)))
+                .collect();
+            let num_output_hits = Arc::clone(&channel.num_output_hits);
+            StepInfo {
+                step_type: step.get_step_name(),
+                field: step.get_field().map(|field| field.to_string()),
+                input_step_ids,
+                num_output_hits,
+                execution_time_ns: 0,
+            }
+        })
+        .collect()
+}
+
+fn to_profile_tree(step_id: usize, infos: &[StepInfo]) -> PlanStepProfile {
+    let info = &infos[step_id];
+    let inputs: Vec<_> = info.input_step_ids.iter().map(|input_step_id| to_profile_tree(*input_step_id, infos)).collect();
+    PlanStepProfile {
+        step_id,
+        step_type: info.step_type.to_string(),
+        field: info.field.clone(),
+        num_input_hits: inputs.iter().map(|input| input.num_output_hits).sum(),
+        num_output_hits: info.num_output_hits.load(Ordering::Relaxed),
+        execution_time_ns: info.execution_time_ns,
+        inputs,
+    }
+}
@@ -8,6 +8,7 @@ use std::{
 
 use crate::{
     error::VelociError,
+    metadata::FieldType,
     persistence::Persistence,
     search::{stopwords, *},
     util::*,
@@ -25,6 +26,18 @@ pub struct QueryParserOptions {
     /// This setting will disable defining a levensthtein distance after a searchterm
     /// e.g. "searchterm~2"
     pub no_levensthein: bool,
+    /// This setting will disable the wildcards `*` and `?` in a searchterm
+    /// e.g. "fo?ba*"
+    #[serde(default)]
+    pub no_wildcards: bool,
+    /// This setting will disable defining a boost after a searchterm or group
+    /// e.g. "searchterm^2"
+    #[serde(default)]
+    pub no_boost: bool,
+    /// Repairs invalid queries instead of returning an error, e.g. unbalanced quotes and parentheses are removed
+    /// e.g. "(searchterm" is parsed as "searchterm"
+    #[serde(default)]
+    pub lenient: bool,
     // pub no_quotes: bool
 }
 
@@ -34,6 +47,9 @@ impl From<QueryParserOptions> for query_parser::Options {
             no_attributes: options.no_attributes,
             no_parentheses: options.no_parentheses,
             no_levensthein: options.no_levensthein,
+            no_wildcards: options.no_wildcards,
+            no_boost: options.no_boost,
+            lenient: options.lenient,
         }
     }
 }
@@ -76,6 +92,7 @@ pub struct SearchQueryGeneratorParameters {
     pub boost_terms: Option<HashMap<String, f32>>,
     pub phrase_pairs: Option<bool>,
     pub explain: Option<bool>,
+    pub profile: Option<bool>,
     pub filter: Option<String>,
     pub filter_parser_options: Option<QueryParserOptions>,
     pub select: Option<String>,
@@ -126,9 +143,23 @@ fn get_all_search_field_names(persistence: &Persistence, fields: &Option<Vec<Str
     }
 }
 
+/// Terms without attribute are only searched in text fields, since they are usually not valid values for typed fields (e.g. a date or a number)
+fn get_default_search_field_names(persistence: &Persistence, fields: &[String]) -> Vec<String> {
+    let text_fields: Vec<String> = fields
+        .iter()
+        .filter(|field| persistence.metadata.columns.get(*field).map(|info| info.field_type == FieldType::Text).unwrap_or(true))
+        .cloned()
+        .collect();
+    if text_fields.is_empty() {
+        fields.to_vec()
+    } else {
+        text_fields
+    }
+}
+
 fn get_levenshteinn(term: &str, levenshtein: Option<usize>, levenshtein_auto_limit: Option<usize>, wildcard: bool) -> u32 {
     let levenshtein_distance = levenshtein.unwrap_or_else(|| get_default_levenshtein(term, levenshtein_auto_limit.unwrap_or(1), wildcard));
-    std::cmp::min(levenshtein_distance, term.chars().count() - 1) as u32
+    std::cmp::min(levenshtein_distance, term.chars().count().saturating_sub(1)) as u32
 }
 
 fn check_field(field: &str, all_fields: &[String]) -> Result<(), VelociError> {
@@ -155,8 +186,11 @@ pub fn handle_boost_term_query(persistence: &Persistence, boost_term: &str, boos
         None
     };
 
-    get_all_search_field_names(&persistence, &field_filter)
-        .unwrap()
+    let mut search_fields = get_all_search_field_names(&persistence, &field_filter).unwrap();
+    if field_filter.is_none() {
+        search_fields = get_default_search_field_names(persistence, &search_fields);
+    }
+    search_fields
         .iter()
         .map(|field_name| RequestSearchPart {
             path: field_name.to_string(),
@@ -180,11 +214,16 @@ pub fn search_query(persistence: &Persistence, mut opt: SearchQueryGeneratorPara
     let all_search_fields = get_all_search_field_names(&persistence, &opt.fields)?; // all fields with applied field_filter
 
     let parser_options: QueryParserOptions = opt.parser_options.unwrap_or_else(Default::default);
-    let query_ast = query_parser::parse_with_opt(&opt.search_term, parser_options.into()).unwrap();
+    let query_ast = query_parser::parse_with_opt(&opt.search_term, parser_options.into())?;
 
     let mut request = Request::default();
 
-    request.search_req = Some(ast_to_search_request(&query_ast, &all_search_fields, &opt)?);
+    let default_search_fields = if opt.fields.is_some() {
+        all_search_fields.clone()
+    } else {
+        get_default_search_field_names(persistence, &all_search_fields)
+    };
+    request.search_req = Some(ast_to_search_request(&query_ast, &all_search_fields, &default_search_fields, &opt)?);
     if let Some(el) = request.search_req.as_mut() { el.simplify() }
 
     let facetlimit = opt.facetlimit;
@@ -194,7 +233,11 @@ pub fn search_query(persistence: &Persistence, mut opt: SearchQueryGeneratorPara
             .into_iter()
             .map(|field| {
                 check_field(&field, &all_fields)?;
-                Ok(FacetRequest { field, top: facetlimit })
+                Ok(FacetRequest {
+                    field,
+                    top: facetlimit,
+                    ..Default::default()
+                })
             })
             .collect::<Result<Vec<FacetRequest>, VelociError>>()
     });
@@ -223,12 +266,7 @@ pub fn search_query(persistence: &Persistence, mut opt: SearchQueryGeneratorPara
     }
 
     if let Some(filters) = opt.filter.as_ref() {
-        let mut params = SearchQueryGeneratorParameters::default();
-        params.levenshtein = Some(0);
-        let query_ast = query_parser::parse_with_opt(&filters, opt.filter_parser_options.unwrap_or_else(Default::default).into()).unwrap();
-        let mut filter_request_ast = ast_to_search_request(&query_ast, &all_fields, &params)?;
-        filter_request_ast.simplify();
-        request.filter = Some(Box::new(filter_request_ast));
+        request.filter = Some(Box::new(filter_query(persistence, filters, opt.filter_parser_options)?));
     }
 
     request.top = opt.top;
@@ -239,10 +277,22 @@ pub fn search_query(persistence: &Persistence, mut opt: SearchQueryGeneratorPara
     request.boost_term = boost_term;
     request.boost = opt.boost_queries.clone();
     request.explain = opt.explain.unwrap_or(false);
+    request.profile = opt.profile.unwrap_or(false);
 
     Ok(request)
 }
 
+/// Generates the `SearchRequest` for a filter query like `tenant:acme`, the terms are matched exactly (levenshtein 0) on all fields
+pub fn filter_query(persistence: &Persistence, filter: &str, parser_options: Option<QueryParserOptions>) -> Result<SearchRequest, VelociError> {
+    let all_fields = persistence.metadata.get_all_fields();
+    let mut params = SearchQueryGeneratorParameters::default();
+    params.levenshtein = Some(0);
+    let query_ast = query_parser::parse_with_opt(filter, parser_options.unwrap_or_else(Default::default).into())?;
+    let mut filter_request = ast_to_search_request(&query_ast, &all_fields, &get_default_search_field_names(persistence, &all_fields), &params)?;
+    filter_request.simplify();
+    Ok(filter_request)
+}
+
 /// Generates Phrase Boosts for adjoined terms
 ///
 /// Generates Phrase Boosts queries
@@ -258,8 +308,9 @@ pub fn generate_phrase_queries_for_searchterm(
     boost_fields: &Option<HashMap<String, f32>>,
 ) -> Result<Vec<RequestPhraseBoost>, VelociError> {
     let mut phase_boost_requests = vec![];
+    let search_fields = get_default_search_field_names(persistence, &get_all_search_field_names(&persistence, &fields)?);
     for [term_a, term_b] in terms.iter() {
-        phase_boost_requests.extend(get_all_search_field_names(&persistence, &fields)?.iter().map(|field_name| RequestPhraseBoost {
+        phase_boost_requests.extend(search_fields.iter().map(|field_name| RequestPhraseBoost {
             search1: RequestSearchPart {
                 path: field_name.to_string(),
                 terms: vec![term_a.to_string()],
@@ -292,7 +343,11 @@ pub fn suggest_query(
     if top.is_none() {
         top = Some(10);
     }
-    let requests = get_all_search_field_names(&persistence, &fields)?
+    let mut search_fields = get_all_search_field_names(&persistence, &fields)?;
+    if fields.is_none() {
+        search_fields = get_default_search_field_names(persistence, &search_fields);
+    }
+    let requests = search_fields
         .iter()
         .map(|field_name| {
             let levenshtein_distance = levenshtein.unwrap_or_else(|| get_default_levenshtein(request, levenshtein_auto_limit.unwrap_or(1), true));
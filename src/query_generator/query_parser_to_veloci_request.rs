@@ -1,6 +1,6 @@
 use crate::{
     query_generator::*,
-    search::request::search_request::{SearchRequest, SearchTree},
+    search::request::search_request::{RequestExists, SearchRequest, SearchTree},
 };
 
 use crate::error::VelociError;
@@ -8,22 +8,32 @@ use query_parser::{
     self,
     ast::{Operator, UserAST},
 };
-pub(crate) fn ast_to_search_request(query_ast: &UserAST, all_fields: &[String], opt: &SearchQueryGeneratorParameters) -> Result<SearchRequest, VelociError> {
+/// Terms without attribute are searched in `default_fields`, attributes must be in `all_fields`
+pub(crate) fn ast_to_search_request(
+    query_ast: &UserAST,
+    all_fields: &[String],
+    default_fields: &[String],
+    opt: &SearchQueryGeneratorParameters,
+) -> Result<SearchRequest, VelociError> {
     filter_stopwords(query_ast, opt);
-    let query_ast = expand_fields_in_query_ast(query_ast, all_fields)?;
-    Ok(query_ast_to_request(&query_ast, opt, None))
+    let query_ast = expand_fields_in_query_ast(query_ast, all_fields, default_fields)?;
+    Ok(query_ast_to_request(&query_ast, opt, None, None))
 }
 
 /// Converts the SearchQueryGeneratorParameters into an SearchRequest ast
 ///
-/// * has a special meaning as a searchterm, it counts as a wildcard, e.g.
+/// * and ? have a special meaning as a searchterm, they count as wildcards, e.g.
 /// foo* will match all tokens starting with foo
 /// foo*bar will match all tokens starting with foo and ending with bar
 /// *foo* will match all tokens containing foo
-fn query_ast_to_request<'a>(ast: &UserAST, opt: &SearchQueryGeneratorParameters, field_name: Option<&'a str>) -> SearchRequest {
+/// fo?bar will match all tokens with one character between fo and bar
+/// * alone will match all documents with a value in the field
+///
+/// The boosts of the query, e.g. (harry potter)^3, are multiplied with the boost of the field
+fn query_ast_to_request<'a>(ast: &UserAST, opt: &SearchQueryGeneratorParameters, field_name: Option<&'a str>, query_boost: Option<f32>) -> SearchRequest {
     match ast {
         UserAST::BinaryClause(ast1, op, ast2) => {
-            let queries = [ast1, ast2].iter().map(|ast| query_ast_to_request(ast, opt, field_name)).collect();
+            let queries = [ast1, ast2].iter().map(|ast| query_ast_to_request(ast, opt, field_name, query_boost)).collect();
             match op {
                 Operator::And => SearchRequest::And(SearchTree {
                     queries,
@@ -35,25 +45,36 @@ fn query_ast_to_request<'a>(ast: &UserAST, opt: &SearchQueryGeneratorParameters,
                 }),
             }
         }
-        UserAST::Attributed(attr, ast) => query_ast_to_request(ast, opt, Some(attr)),
+        UserAST::Attributed(attr, ast) => query_ast_to_request(ast, opt, Some(attr), query_boost),
+        UserAST::Boosted(ast, boost) => query_ast_to_request(ast, opt, field_name, Some(query_boost.unwrap_or(1.0) * boost.into_inner())),
+        UserAST::Exists(field) => SearchRequest::Exists(RequestExists {
+            path: field.to_string(),
+            ..Default::default()
+        }),
         UserAST::Leaf(filter) => {
             let field_name: &str = field_name.as_ref().unwrap();
             let mut term = filter.phrase.to_string();
 
             let mut levenshtein_distance = None;
-            let starts_with = term.ends_with('*');
-            if term.ends_with('*') {
+            let starts_with = filter.wildcard && term.ends_with('*');
+            if starts_with {
                 term.pop();
                 // term = &term[..term.len() - 1];
             }
+            // a lone * matches every value of the field
+            if starts_with && term.is_empty() {
+                return SearchRequest::Exists(RequestExists {
+                    path: field_name.to_string(),
+                    ..Default::default()
+                });
+            }
 
-            // regex is currently enabled, when there is a star, expect if there is only one star at the the end, e.g. fooba*
+            // regex is currently enabled, when there is a wildcard, expect if there is only one star at the the end, e.g. fooba*
             // Then it uses a combination of fuzzy + starts_with
             // This enables fuzzy search with patterns, currently there is no fuzzy_search for regex
-            let is_regex = term.contains('*');
+            let is_regex = filter.wildcard && term.contains(|c| c == '*' || c == '?');
             if is_regex {
-                use itertools::Itertools;
-                term = term.split('*').map(|term| regex::escape(term)).join(".*");
+                term = wildcard_to_regex(&term);
             } else {
                 levenshtein_distance = if let Some(levenshtein) = filter.levenshtein {
                     Some(u32::from(levenshtein))
@@ -62,8 +83,14 @@ fn query_ast_to_request<'a>(ast: &UserAST, opt: &SearchQueryGeneratorParameters,
                 };
             }
 
+            let field_boost = opt.boost_fields.as_ref().and_then(|boost| boost.get(field_name).copied());
+            let boost = match (field_boost, query_boost) {
+                (Some(field_boost), Some(query_boost)) => Some(field_boost * query_boost),
+                (field_boost, query_boost) => field_boost.or(query_boost),
+            };
+
             let part = RequestSearchPart {
-                boost: opt.boost_fields.as_ref().and_then(|boost| boost.get(field_name).map(|el| OrderedFloat(*el))),
+                boost: boost.map(OrderedFloat),
                 levenshtein_distance,
                 path: field_name.to_string(),
                 terms: vec![term],
@@ -83,15 +110,29 @@ fn query_ast_to_request<'a>(ast: &UserAST, opt: &SearchQueryGeneratorParameters,
     }
 }
 
-fn expand_fields_in_query_ast<'a, 'b>(ast: &UserAST, all_fields: &'a [String]) -> Result<UserAST, VelociError> {
+/// Escapes the term for the regex, except the wildcards. `*` matches any number of characters, `?` exactly one.
+fn wildcard_to_regex(term: &str) -> String {
+    let mut regex = String::with_capacity(term.len() * 2);
+    let mut literal = [0; 4];
+    for c in term.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(c.encode_utf8(&mut literal))),
+        }
+    }
+    regex
+}
+
+fn expand_fields_in_query_ast<'a, 'b>(ast: &UserAST, all_fields: &'a [String], default_fields: &'a [String]) -> Result<UserAST, VelociError> {
     match ast {
         UserAST::BinaryClause(ast1, op, ast2) => Ok(UserAST::BinaryClause(
-            expand_fields_in_query_ast(ast1, all_fields)?.into(),
+            expand_fields_in_query_ast(ast1, all_fields, default_fields)?.into(),
             *op,
-            expand_fields_in_query_ast(ast2, all_fields)?.into(),
+            expand_fields_in_query_ast(ast2, all_fields, default_fields)?.into(),
         )),
         UserAST::Leaf(_) => {
-            let mut field_iter = all_fields.iter();
+            let mut field_iter = default_fields.iter();
             let mut curr_ast = field_iter.next().map(|field_name| UserAST::Attributed(field_name.to_string(), Box::new(ast.clone()))).unwrap();
 
             for field_name in field_iter {
@@ -101,7 +142,8 @@ fn expand_fields_in_query_ast<'a, 'b>(ast: &UserAST, all_fields: &'a [String]) -
 
             Ok(curr_ast)
         }
-        UserAST::Attributed(field_name, _) => {
+        UserAST::Boosted(ast, boost) => Ok(UserAST::Boosted(expand_fields_in_query_ast(ast, all_fields, default_fields)?.into(), *boost)),
+        UserAST::Attributed(field_name, _) | UserAST::Exists(field_name) => {
             // dont expand in UserAST::Attributed
             check_field(field_name, &all_fields)?;
             Ok(ast.clone())
@@ -150,7 +192,7 @@ fn bench_query_custom_parse_to_request(b: &mut test::Bencher) {
     ];
     b.iter(|| {
         let query_ast = query_parser::parse("die drei fragezeigen und das unicorn").unwrap();
-        ast_to_search_request(&query_ast, &fields, &SearchQueryGeneratorParameters::default()).unwrap()
+        ast_to_search_request(&query_ast, &fields, &fields, &SearchQueryGeneratorParameters::default()).unwrap()
     })
 }
 
@@ -175,7 +217,7 @@ fn bench_custom_parse_expand_fields_in_query_ast(b: &mut test::Bencher) {
     ];
     b.iter(|| {
         let query_ast = query_parser::parse("die drei fragezeigen und das unicorn").unwrap();
-        expand_fields_in_query_ast(&query_ast, &fields).unwrap()
+        expand_fields_in_query_ast(&query_ast, &fields, &fields).unwrap()
     })
 }
 
@@ -198,6 +240,41 @@ fn test_filter_stopwords_by_userdefined_stopword_list() {
     assert_eq!(query_ast, Some("erbin".into()));
 }
 
+#[test]
+fn test_wildcard_to_regex() {
+    assert_eq!(wildcard_to_regex("*myfun(a.b)*"), ".*myfun\\(a\\.b\\).*");
+    assert_eq!(wildcard_to_regex("fo?bar"), "fo.bar");
+}
+
+#[test]
+fn test_lone_wildcard_to_exists() {
+    let fields = vec!["title".to_string()];
+    let request = ast_to_search_request(&query_parser::parse("*").unwrap(), &fields, &fields, &SearchQueryGeneratorParameters::default()).unwrap();
+    match request {
+        SearchRequest::Exists(exists) => assert_eq!(exists.path, "title"),
+        other => panic!("expected exists query, got {:?}", other),
+    }
+    assert_eq!(get_levenshteinn("", Some(1), None, true), 0);
+}
+
+#[test]
+fn test_query_boost() {
+    let query_ast = query_parser::parse("title:(harry potter)^3 OR author:rowling").unwrap();
+    let mut opt = SearchQueryGeneratorParameters::default();
+    opt.boost_fields = Some([("title".to_string(), 2.0)].iter().cloned().collect());
+    let fields = vec!["title".to_string(), "author".to_string()];
+    let request = ast_to_search_request(&query_ast, &fields, &fields, &opt).unwrap();
+    let boosts: Vec<_> = request
+        .get_search_parts()
+        .iter()
+        .map(|part| (part.terms[0].to_string(), part.boost.map(|boost| boost.into_inner())))
+        .collect();
+    assert_eq!(
+        boosts,
+        vec![("harry".to_string(), Some(6.0)), ("potter".to_string(), Some(6.0)), ("rowling".to_string(), None)]
+    );
+}
+
 #[test]
 fn test_field_expand() {
     use query_parser::ast::UserFilter;
@@ -205,8 +282,9 @@ fn test_field_expand() {
     let ast = UserAST::Leaf(Box::new(UserFilter {
         phrase: "Fred".to_string(),
         levenshtein: None,
+        wildcard: false,
     }));
-    let expanded_ast = expand_fields_in_query_ast(&ast, &fields).unwrap();
+    let expanded_ast = expand_fields_in_query_ast(&ast, &fields, &fields).unwrap();
     assert_eq!(format!("{:?}", expanded_ast), "(Author[].name:\"Fred\" OR Title:\"Fred\")");
 
     let ast = UserAST::Attributed(
@@ -214,9 +292,10 @@ fn test_field_expand() {
         UserAST::Leaf(Box::new(UserFilter {
             phrase: "Fred".to_string(),
             levenshtein: None,
+            wildcard: false,
         }))
         .into(),
     );
-    let expanded_ast = expand_fields_in_query_ast(&ast, &fields).unwrap();
+    let expanded_ast = expand_fields_in_query_ast(&ast, &fields, &fields).unwrap();
     assert_eq!(format!("{:?}", expanded_ast), "Title:\"Fred\"");
 }
@@ -1,4 +1,8 @@
 pub(crate) mod boost;
+pub mod cancellation;
+pub(crate) mod exists;
+mod geo_distance;
+pub(crate) mod nested;
 pub mod read_document;
 pub mod request;
 pub mod result;
@@ -9,16 +13,22 @@ pub mod stopwords;
 pub mod why_found;
 
 pub(crate) use self::boost::*;
-pub use self::{result::*, search_field::*, set_op::*};
-use self::{sort::top_n_sort, why_found::get_why_found};
+pub use self::{cancellation::CancellationToken, result::*, search_field::*, set_op::*};
+use self::{
+    geo_distance::{apply_geo_boosts, sort_by_distance},
+    sort::top_n_sort,
+    why_found::get_why_found,
+};
 pub use crate::search::{read_document::read_data, request::*};
 use crate::{
+    date,
     error::VelociError,
     expression::ScoreExpression,
     facet,
     highlight_field::highlight_on_original_document,
+    metadata::{FieldInfo, FieldType},
     persistence::{Persistence, *},
-    plan_creator::{execution_plan::*, plan::*},
+    plan_creator::{execution_plan::*, plan::*, profile::*},
     util::{self, *},
 };
 use doc_store::DocLoader;
@@ -87,6 +97,7 @@ pub fn to_documents(persistence: &Persistence, hits: &[Hit], select: &Option<Vec
                     doc: read_data(persistence, hit.id, &select).unwrap(), // TODO validate fields
                     hit: hit.clone(),
                     explain: result.explain.get(&hit.id).cloned(),
+                    explain_tree: result.explain_tree.get(&hit.id).cloned(),
                     why_found: result.why_found_info.get(&hit.id).cloned().unwrap_or_default(),
                 }
             } else {
@@ -99,6 +110,7 @@ pub fn to_documents(persistence: &Persistence, hits: &[Hit], select: &Option<Vec
                     doc: serde_json::from_str(&doc_str).unwrap(),
                     hit: hit.clone(),
                     explain: result.explain.get(&hit.id).cloned(),
+                    explain_tree: result.explain_tree.get(&hit.id).cloned(),
                     why_found: ayse,
                 }
             }
@@ -112,6 +124,8 @@ pub fn to_search_result(persistence: &Persistence, hits: SearchResult, select: &
         num_hits: hits.num_hits,
         facets: hits.facets,
         execution_time_ns: hits.execution_time_ns,
+        profile: hits.profile,
+        timed_out: hits.timed_out,
     }
 }
 
@@ -134,61 +148,214 @@ pub fn sort_by_score_and_id(a: &Hit, b: &Hit) -> Ordering {
     }
 }
 
-pub fn explain_plan(mut request: Request, _persistence: &Persistence) -> Result<String, VelociError> {
+pub fn explain_plan(mut request: Request, persistence: &Persistence) -> Result<String, VelociError> {
     request.top = request.top.or(Some(10));
+    // render the plan, which would be executed
+    prepare_plan_request(&mut request, persistence)?;
 
     let mut plan = Plan::default();
-    plan_creator(request, &mut plan);
+    plan_creator(request, &mut plan, persistence);
 
     let mut dot_graph = vec![];
     render_plan_to(&plan, &mut dot_graph);
     Ok(String::from_utf8(dot_graph)?)
 }
 
-pub fn search(mut request: Request, persistence: &Persistence) -> Result<SearchResult, VelociError> {
-    let start_time = std::time::Instant::now();
-    info_time!("search");
-    request.top = request.top.or(Some(10));
+/// Executes the search plan, the profile of the plan is returned when `profile` is enabled in the request
+fn execute_plan(request: &Request, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<(SearchFieldResult, Option<PlanStepProfile>), VelociError> {
+    info_time!("search terms");
+    let mut plan = Plan::default();
+    let mut request = request.clone();
+    prepare_plan_request(&mut request, persistence)?;
+    plan_creator(request.clone(), &mut plan, persistence);
+
+    if log_enabled!(log::Level::Debug) {
+        let mut dot_graph = vec![];
+        render_plan_to(&plan, &mut dot_graph);
+        debug!("{}", String::from_utf8(dot_graph)?);
+    }
 
-    let mut res = {
-        info_time!("search terms");
-        let mut plan = Plan::default();
-        if request.search_req.is_none() {
-            return Err(VelociError::InvalidRequest {
-                message: format!("search_req is None, but is required in search, request: {:?}", request),
-            });
-        }
-        plan_creator(request.clone(), &mut plan);
+    let plan_result = plan.plan_result.as_ref().unwrap().clone();
+    let execution = if request.profile {
+        execute_plan_profiled(plan, persistence, cancellation_token)
+    } else {
+        plan.get_ordered_steps()
+            .into_iter()
+            .try_for_each(|stepso| {
+                cancellation_token.check()?;
+                execute_steps(stepso, &persistence, cancellation_token)
+            })
+            .map(|_| None)
+    };
+    // aborted steps cause receive errors in the following steps, report the timeout instead
+    let profile = execution.map_err(|err| cancellation_token.check().err().unwrap_or(err))?;
+    let res = plan_result.recv().unwrap();
+    drop(plan_result);
+    Ok((res, profile))
+}
 
-        if log_enabled!(log::Level::Debug) {
-            let mut dot_graph = vec![];
-            render_plan_to(&plan, &mut dot_graph);
-            debug!("{}", String::from_utf8(dot_graph)?);
-        }
+/// Executes `filter` and returns the matching anchor ids, used to apply the filter of requests, which are not executed as plan, e.g. suggest
+pub(crate) fn get_filter_anchor_ids(filter: &SearchRequest, persistence: &Persistence, cancellation_token: &CancellationToken) -> Result<FnvHashSet<u32>, VelociError> {
+    let request = Request {
+        search_req: Some(filter.clone()),
+        ..Default::default()
+    };
+    let (res, _) = execute_plan(&request, persistence, cancellation_token)?;
+    Ok(res.hits_scores.iter().map(|hit| hit.id).chain(res.hits_ids.iter().cloned()).collect())
+}
+
+/// Resolves and validates the search requests, before a plan is created from them
+fn prepare_plan_request(request: &mut Request, persistence: &Persistence) -> Result<(), VelociError> {
+    if request.search_req.is_none() {
+        return Err(VelociError::InvalidRequest {
+            message: format!("search_req is None, but is required in search, request: {:?}", request),
+        });
+    }
+    resolve_typed_requests(request, persistence)?;
+    validate_search_requests(request, persistence)
+}
 
-        let plan_result = plan.plan_result.as_ref().unwrap().clone();
-        for stepso in plan.get_ordered_steps() {
-            execute_steps(stepso, &persistence)?;
+/// Converts the terms and ranges of requests on typed fields to the sortable terms in the fst, date math is resolved relative to the current time
+fn resolve_typed_requests(request: &mut Request, persistence: &Persistence) -> Result<(), VelociError> {
+    let now = date::now();
+    let mut resolve = |part: &mut RequestSearchPart| resolve_typed_request_part(part, persistence, now);
+    if let Some(search_req) = request.search_req.as_mut() {
+        search_req.try_for_each_search_part(&mut resolve)?;
+    }
+    if let Some(filter) = request.filter.as_mut() {
+        filter.try_for_each_search_part(&mut resolve)?;
+    }
+    for phrase_boost in request.phrase_boosts.iter_mut().flatten() {
+        resolve(&mut phrase_boost.search1)?;
+        resolve(&mut phrase_boost.search2)?;
+    }
+    Ok(())
+}
+
+/// Checks the nested and exists queries, which are not validated by the field searches
+fn validate_search_requests(request: &Request, persistence: &Persistence) -> Result<(), VelociError> {
+    let mut validate = |search_request: &SearchRequest| match search_request {
+        SearchRequest::Nested(nested) => nested.validate(),
+        SearchRequest::Exists(exists) | SearchRequest::Missing(exists) => exists::check_field(persistence, &exists.path),
+        _ => Ok(()),
+    };
+    if let Some(search_req) = request.search_req.as_ref() {
+        search_req.try_for_each_request(&mut validate)?;
+    }
+    if let Some(filter) = request.filter.as_ref() {
+        filter.try_for_each_request(&mut validate)?;
+    }
+    Ok(())
+}
+
+fn resolve_typed_request_part(part: &mut RequestSearchPart, persistence: &Persistence, now: i64) -> Result<(), VelociError> {
+    let field_info = persistence.metadata.columns.get(part.path.trim_end_matches(TEXTINDEX));
+    if part.geo_distance.is_some() || part.geo_bounding_box.is_some() {
+        return resolve_geo_request_part(part, field_info);
+    }
+    let field_info = match field_info {
+        Some(field_info) if field_info.field_type == FieldType::GeoPoint && part.range.is_some() => {
+            return Err(VelociError::InvalidRequest {
+                message: format!("range is not supported on geo_point field {:?}, use geo_distance or geo_bounding_box", part.path),
+            })
         }
-        let res = plan_result.recv().unwrap();
-        drop(plan_result);
-        res
+        Some(field_info) if field_info.field_type != FieldType::Text => field_info,
+        _ if part.range.is_some() => {
+            return Err(VelociError::InvalidRequest {
+                message: format!("range is only supported on date, i64, f64 and bool fields, but {:?} is a text field", part.path),
+            })
+        }
+        _ => return Ok(()),
     };
+    let formats = field_info.date_formats.as_deref().unwrap_or(&[]);
+    let to_term = |value: &str| field_info.field_type.query_to_term(value, formats, now);
+
+    if let Some(range) = part.range.as_mut() {
+        let lower = match (&range.gte, &range.gt) {
+            (Some(gte), _) => TermBound::Included(to_term(gte)?),
+            (None, Some(gt)) => TermBound::Excluded(to_term(gt)?),
+            (None, None) => TermBound::Unbounded,
+        };
+        let upper = match (&range.lte, &range.lt) {
+            (Some(lte), _) => TermBound::Included(to_term(lte)?),
+            (None, Some(lt)) => TermBound::Excluded(to_term(lt)?),
+            (None, None) => TermBound::Unbounded,
+        };
+        range.resolved_terms = Some((lower, upper));
+        if part.terms.is_empty() {
+            part.terms = vec![range.to_string()];
+        }
+    } else {
+        part.terms = part.terms.iter().map(|term| to_term(term)).collect::<Result<_, _>>()?;
+        part.levenshtein_distance = Some(0);
+        part.starts_with = false;
+        part.is_regex = false;
+    }
+    Ok(())
+}
 
-    let mut search_result = SearchResult { ..Default::default() };
-    search_result.explain = res.explain.clone();
+fn resolve_geo_request_part(part: &mut RequestSearchPart, field_info: Option<&FieldInfo>) -> Result<(), VelociError> {
+    if field_info.map(|field_info| field_info.field_type) != Some(FieldType::GeoPoint) {
+        return Err(VelociError::InvalidRequest {
+            message: format!("geo_distance and geo_bounding_box are only supported on geo_point fields, but {:?} is not a geo_point field", part.path),
+        });
+    }
+    // validates the filter, it is applied on the fst in the field search
+    GeoFilter::from_request(part)?;
+    if part.terms.is_empty() {
+        let geo_distance = part.geo_distance.as_ref().map(|geo_distance| geo_distance.to_string());
+        let geo_bounding_box = part.geo_bounding_box.as_ref().map(|bounding_box| bounding_box.to_string());
+        part.terms = vec![geo_distance.into_iter().chain(geo_bounding_box).collect::<Vec<_>>().join(" AND ")];
+    }
+    Ok(())
+}
+
+fn get_cancellation_token(request: &Request) -> CancellationToken {
+    request
+        .timeout_ms
+        .map(|timeout_ms| CancellationToken::with_timeout(timeout_ms, request.partial_results_on_timeout))
+        .unwrap_or_default()
+        .with_max_memory(request.max_memory)
+}
 
-    if let Some(boost_term) = request.boost_term {
-        res = apply_boost_term(persistence, res, &boost_term)?;
+/// Applies the boosts, which are calculated on the complete result (`boost_term`, `geo_boost` and `text_locality`)
+fn apply_result_boosts(request: &Request, mut res: SearchFieldResult, persistence: &Persistence) -> Result<SearchFieldResult, VelociError> {
+    if let Some(boost_term) = request.boost_term.as_ref() {
+        res = apply_boost_term(persistence, res, boost_term)?;
+    }
+
+    if let Some(geo_boosts) = request.geo_boost.as_ref() {
+        res = apply_geo_boosts(persistence, res, geo_boosts, request.explain)?;
     }
 
     if request.text_locality {
         info_time!("boost_text_locality_all");
         let boost_anchor = boost_text_locality_all(&persistence, &mut res.term_id_hits_in_field)?;
-        res = apply_boost_from_iter(res, &mut boost_anchor.iter().cloned());
+        res = apply_boost_from_iter(res, &mut boost_anchor.iter().cloned(), ExplainOperation::TextLocality);
     }
+    Ok(res)
+}
+
+pub fn search(mut request: Request, persistence: &Persistence) -> Result<SearchResult, VelociError> {
+    let start_time = std::time::Instant::now();
+    info_time!("search");
+    request.top = request.top.or(Some(10));
+
+    let cancellation_token = get_cancellation_token(&request);
+    let (mut res, profile) = execute_plan(&request, persistence, &cancellation_token)?;
+
+    let mut search_result = SearchResult {
+        profile,
+        timed_out: cancellation_token.was_cancelled(),
+        ..Default::default()
+    };
+    search_result.explain = res.explain.clone();
+
+    res = apply_result_boosts(&request, res, persistence)?;
+
     let term_id_hits_in_field = res.term_id_hits_in_field;
     search_result.why_found_terms = res.term_text_in_field;
+    let mut explain_tree = res.explain_tree;
 
     if let Some(facets_req) = request.facets {
         info_time!("all_facets {:?}", facets_req.iter().map(|el| el.field.clone()).collect::<Vec<_>>());
@@ -205,14 +372,18 @@ pub fn search(mut request: Request, persistence: &Persistence) -> Result<SearchR
         search_result.facets = Some(
             facets_req
                 .par_iter()
-                .map(|facet_req| (facet_req.field.to_string(), facet::get_facet(persistence, facet_req, &hit_ids).unwrap()))
-                .collect(),
+                .map(|facet_req| Ok((facet_req.field.to_string(), facet::get_facet(persistence, facet_req, &hit_ids)?)))
+                .collect::<Result<_, VelociError>>()?,
         );
     }
     search_result.num_hits = res.hits_scores.len() as u64;
     {
         debug_time!("sort search by score");
-        if let Some(top) = request.top {
+        if let Some(sort) = request.sort_by_distance.as_ref() {
+            let skip = request.skip.unwrap_or(0) as u32;
+            let top_n = request.top.map(|top| top as u32 + skip);
+            search_result.data = sort_by_distance(persistence, res.hits_scores, sort, top_n)?;
+        } else if let Some(top) = request.top {
             search_result.data = top_n_sort(res.hits_scores, top as u32 + request.skip.unwrap_or(0) as u32);
         } else {
             search_result.data = res.hits_scores;
@@ -222,6 +393,8 @@ pub fn search(mut request: Request, persistence: &Persistence) -> Result<SearchR
 
     apply_top_skip(&mut search_result.data, request.skip, request.top);
 
+    search_result.explain_tree = search_result.data.iter().filter_map(|hit| explain_tree.remove_entry(&hit.id)).collect();
+
     if request.why_found && request.select.is_some() {
         let anchor_ids: Vec<u32> = search_result.data.iter().map(|el| el.id).collect();
         let why_found_info = get_why_found(&persistence, &anchor_ids, &term_id_hits_in_field)?;
@@ -232,6 +405,20 @@ pub fn search(mut request: Request, persistence: &Persistence) -> Result<SearchR
     Ok(search_result)
 }
 
+/// Explains the score of a single document for the request.
+///
+/// Returns `None`, if the document is not hit by the request.
+pub fn explain_hit(mut request: Request, doc_id: u32, persistence: &Persistence) -> Result<Option<ExplainNode>, VelociError> {
+    request.explain = true;
+    request.top = request.top.or(Some(10));
+    let (mut res, _profile) = execute_plan(&request, persistence, &get_cancellation_token(&request))?;
+    res = apply_result_boosts(&request, res, persistence)?;
+    if !res.hits_scores.iter().any(|hit| hit.id == doc_id) {
+        return Ok(None);
+    }
+    Ok(res.explain_tree.remove(&doc_id))
+}
+
 pub fn apply_top_skip<T: Clone>(hits: &mut Vec<T>, skip: Option<usize>, top: Option<usize>) {
     if let Some(mut skip) = skip {
         skip = cmp::min(skip, hits.len());
@@ -293,6 +480,7 @@ pub fn join_to_parent_ids(persistence: &Persistence, input: &SearchFieldResult,
     let should_explain = input.request.is_explain();
 
     let mut explain_hits: FnvHashMap<u32, Vec<Explain>> = FnvHashMap::default();
+    let mut explain_tree: FnvHashMap<u32, ExplainNode> = FnvHashMap::default();
 
     for id in &input.hits_ids {
         if let Some(values) = kv_store.get_values(u64::from(*id)).as_ref() {
@@ -305,6 +493,9 @@ pub fn join_to_parent_ids(persistence: &Persistence, input: &SearchFieldResult,
                 if should_explain {
                     let expains = input.explain.get(&*id).unwrap_or_else(|| panic!("could not find explain for id {:?}", *id));
                     explain_hits.entry(*parent_val_id).or_insert_with(|| expains.clone());
+                    if let Some(node) = input.explain_tree.get(&*id) {
+                        explain_tree.entry(*parent_val_id).or_insert_with(|| node.clone());
+                    }
                 }
             }
         }
@@ -316,6 +507,7 @@ pub fn join_to_parent_ids(persistence: &Persistence, input: &SearchFieldResult,
     let mut res = SearchFieldResult::new_from(&input);
     res.hits_ids = hits;
     res.explain = explain_hits;
+    res.explain_tree = explain_tree;
     Ok(res)
 }
 
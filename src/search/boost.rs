@@ -2,7 +2,10 @@ use super::*;
 use crate::{search::request::*, steps::FieldPath};
 
 pub use self::result::*;
-use std::{self, f32};
+use std::{
+    self, f32,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use fnv::FnvHashMap;
 use itertools::Itertools;
@@ -164,12 +167,13 @@ pub(crate) fn apply_boost_term(persistence: &Persistence, mut res: SearchFieldRe
             // }
 
             debug_time!("boost_hits_ids_vec_multi");
-            res = apply_boost_from_iter(res, &mut boost_iter);
+            res = apply_boost_from_iter(res, &mut boost_iter, ExplainOperation::TermBoost);
 
             from_cache = true;
         }
     }
 
+    persistence.term_boost_cache_stats.record(from_cache);
     if !from_cache {
         let r: Result<Vec<_>, VelociError> = boost_term
             .to_vec()
@@ -180,13 +184,13 @@ pub(crate) fn apply_boost_term(persistence: &Persistence, mut res: SearchFieldRe
                     get_ids: true,
                     ..Default::default()
                 };
-                let mut result = search_field::get_term_ids_in_field(persistence, &mut boost_term_req)?;
-                result = search_field::resolve_token_to_anchor(persistence, &boost_term_req.request, &None, &result)?;
+                let mut result = search_field::get_term_ids_in_field(persistence, &mut boost_term_req, &CancellationToken::default())?;
+                result = search_field::resolve_token_to_anchor(persistence, &boost_term_req.request, &None, &result, &CancellationToken::default())?;
                 Ok(result)
             })
             .collect();
         let mut data = r?;
-        res = boost_hits_ids_vec_multi(res, &mut data);
+        res = boost_hits_ids_vec_multi(res, &mut data, ExplainOperation::TermBoost);
         {
             persistence.term_boost_cache.write().insert(boost_term.to_vec(), data);
         }
@@ -194,12 +198,18 @@ pub(crate) fn apply_boost_term(persistence: &Persistence, mut res: SearchFieldRe
     Ok(res)
 }
 
-pub(crate) fn apply_boost_from_iter(mut results: SearchFieldResult, mut boost_iter: &mut dyn Iterator<Item = Hit>) -> SearchFieldResult {
+pub(crate) fn apply_boost_from_iter(
+    mut results: SearchFieldResult,
+    mut boost_iter: &mut dyn Iterator<Item = Hit>,
+    explain_operation: fn(f32) -> ExplainOperation,
+) -> SearchFieldResult {
     let mut explain = FnvHashMap::default();
+    let mut explain_tree = FnvHashMap::default();
     mem::swap(&mut explain, &mut results.explain);
+    mem::swap(&mut explain_tree, &mut results.explain_tree);
     let should_explain = results.request.is_explain();
     {
-        let mut move_boost = |hit: &mut Hit, hit_curr: &mut Hit, boost_iter: &mut dyn Iterator<Item = Hit>| {
+        let mut move_boost = |hit: &mut Hit, hit_curr: &mut Hit, boost_iter: &mut dyn Iterator<Item = Hit>, explain_tree: &mut FnvHashMap<u32, ExplainNode>| {
             //Forward the boost iterator and look for matches
             for b_hit in boost_iter {
                 if b_hit.id > hit.id {
@@ -214,6 +224,7 @@ pub(crate) fn apply_boost_from_iter(mut results: SearchFieldResult, mut boost_it
                         let data = explain.entry(hit.id).or_insert_with(Vec::new);
                         // data.push(format!("boost {:?}", b_hit.score));
                         data.push(Explain::Boost(b_hit.score));
+                        wrap_explain_node(explain_tree, hit.id, explain_operation(b_hit.score), hit.score);
                     }
                 }
             }
@@ -223,16 +234,20 @@ pub(crate) fn apply_boost_from_iter(mut results: SearchFieldResult, mut boost_it
             let mut hit_curr = yep;
             for mut hit in &mut results.hits_scores {
                 if hit_curr.id < hit.id {
-                    move_boost(&mut hit, &mut hit_curr, &mut boost_iter);
+                    move_boost(&mut hit, &mut hit_curr, &mut boost_iter, &mut explain_tree);
                 } else if hit_curr.id == hit.id {
                     hit.score *= hit_curr.score;
-                    move_boost(&mut hit, &mut hit_curr, &mut boost_iter); // Possible multi boosts [id:0->2, id:0->4 ...]
+                    if should_explain {
+                        wrap_explain_node(&mut explain_tree, hit.id, explain_operation(hit_curr.score), hit.score);
+                    }
+                    move_boost(&mut hit, &mut hit_curr, &mut boost_iter, &mut explain_tree); // Possible multi boosts [id:0->2, id:0->4 ...]
                 }
             }
         }
     }
 
     mem::swap(&mut explain, &mut results.explain);
+    mem::swap(&mut explain_tree, &mut results.explain_tree);
     results
 }
 
@@ -247,15 +262,58 @@ fn test_apply_boost_from_iter() {
     let mut boost_values = SearchFieldResult::default();
     boost_values.boost_ids = vec![Hit::new(1, 2.0), Hit::new(2, 20.0), Hit::new(5, 3.0), Hit::new(6, 3.0)];
 
-    apply_boost_values_anchor(&mut res, &boost_req, &mut boost_values.boost_ids.into_iter()).unwrap();
+    apply_boost_values_anchor(&mut res, &boost_req, &None, &mut boost_values.boost_ids.into_iter()).unwrap();
 
     assert_eq!(res.hits_scores, vec![Hit::new(1, 20.0), Hit::new(3, 20.0), Hit::new(5, 60.0)]);
 }
 
-pub(crate) fn apply_boost_values_anchor(results: &mut SearchFieldResult, boost: &RequestBoostPart, mut boost_iter: &mut dyn Iterator<Item = Hit>) -> Result<(), VelociError> {
+/// A `ScoreExpression` of a boost request, with the boost indices of the fields referenced via `doc['field']`
+pub(crate) struct BoostExpression<'a> {
+    expression: ScoreExpression,
+    doc_value_stores: Vec<&'a dyn IndexIdToParent<Output = u32>>,
+}
+
+impl<'a> BoostExpression<'a> {
+    /// Parses the expression of the boost request, returns `VelociError::InvalidRequest` for invalid expressions.
+    ///
+    /// The values of `doc['field']` are looked up with the id of the boosted hit, so the referenced fields need to be on the same level as the hits (e.g. root fields for hits on anchors).
+    pub(crate) fn from_boost_request(persistence: &'a Persistence, boost: &RequestBoostPart) -> Result<Option<Self>, VelociError> {
+        let expression = match boost.expression.as_ref() {
+            Some(expression) => ScoreExpression::new(expression.to_string())?,
+            None => return Ok(None),
+        };
+        let doc_value_stores = expression
+            .doc_fields()
+            .iter()
+            .map(|field| persistence.get_boost(&(field.to_string() + BOOST_VALID_TO_VALUE)))
+            .collect::<Result<Vec<_>, VelociError>>()?;
+        Ok(Some(BoostExpression { expression, doc_value_stores }))
+    }
+
+    pub(crate) fn expression(&self) -> &str {
+        self.expression.expression()
+    }
+
+    pub(crate) fn get_score(&self, id: u32, boost_value: f32) -> f32 {
+        let doc_values: Vec<f32> = self
+            .doc_value_stores
+            .iter()
+            .map(|store| store.get_value(u64::from(id)).map(|val| val as f32).unwrap_or(0.0))
+            .collect();
+        self.expression.get_score(boost_value, &doc_values)
+    }
+}
+
+pub(crate) fn apply_boost_values_anchor(
+    results: &mut SearchFieldResult,
+    boost: &RequestBoostPart,
+    expre: &Option<BoostExpression<'_>>,
+    mut boost_iter: &mut dyn Iterator<Item = Hit>,
+) -> Result<(), VelociError> {
     let boost_param = boost.param.map(|el| el.into_inner()).unwrap_or(0.0);
-    let expre = boost.expression.as_ref().map(|expression| ScoreExpression::new(expression.clone()));
-    let mut explain = if results.request.is_explain() { Some(&mut results.explain) } else { None };
+    let should_explain = results.request.is_explain();
+    let mut explain = if should_explain { Some(&mut results.explain) } else { None };
+    let mut explain_tree = if should_explain { Some(&mut results.explain_tree) } else { None };
     {
         if let Some(yep) = boost_iter.next() {
             let mut hit_curr = yep;
@@ -267,11 +325,11 @@ pub(crate) fn apply_boost_values_anchor(results: &mut SearchFieldResult, boost:
                             break;
                         } else if b_hit.id == hit.id {
                             hit_curr = b_hit.clone();
-                            apply_boost(hit, b_hit.score, boost_param, &boost.boost_fun, &mut explain, &expre)?;
+                            apply_boost(hit, b_hit.score, boost_param, &boost.boost_fun, &mut explain, &mut explain_tree, expre)?;
                         }
                     }
                 } else if hit_curr.id == hit.id {
-                    apply_boost(hit, hit_curr.score, boost_param, &boost.boost_fun, &mut explain, &expre)?;
+                    apply_boost(hit, hit_curr.score, boost_param, &boost.boost_fun, &mut explain, &mut explain_tree, expre)?;
                 }
             }
         }
@@ -286,7 +344,8 @@ pub(crate) fn apply_boost(
     boost_param: f32,
     boost_fun: &Option<BoostFunction>,
     explain: &mut Option<&mut FnvHashMap<u32, Vec<Explain>>>,
-    expre: &Option<ScoreExpression>,
+    explain_tree: &mut Option<&mut FnvHashMap<u32, ExplainNode>>,
+    expre: &Option<BoostExpression<'_>>,
 ) -> Result<(), VelociError> {
     match boost_fun {
         Some(BoostFunction::Log10) => {
@@ -341,18 +400,50 @@ pub(crate) fn apply_boost(
             );
             hit.score += boost_value + boost_param;
         }
+        Some(BoostFunction::Gauss(decay_fun)) => {
+            let DecayParams { distance, scale, decay } = get_decay_params(decay_fun, boost_value)?;
+            let factor = (distance * distance * decay.ln() / (scale * scale)).exp();
+            trace!(
+                "Gauss decay boosting hit.id {:?} hit.score {:?} with {:?} -- boost_value {:?}",
+                hit.id,
+                hit.score,
+                factor,
+                boost_value
+            );
+            hit.score *= factor as f32;
+        }
+        Some(BoostFunction::Exp(decay_fun)) => {
+            let DecayParams { distance, scale, decay } = get_decay_params(decay_fun, boost_value)?;
+            let factor = (decay.ln() / scale * distance).exp();
+            trace!(
+                "Exp decay boosting hit.id {:?} hit.score {:?} with {:?} -- boost_value {:?}",
+                hit.id,
+                hit.score,
+                factor,
+                boost_value
+            );
+            hit.score *= factor as f32;
+        }
+        Some(BoostFunction::Linear(decay_fun)) => {
+            let DecayParams { distance, scale, decay } = get_decay_params(decay_fun, boost_value)?;
+            let range = scale / (1.0 - decay);
+            let factor = ((range - distance) / range).max(0.0);
+            trace!(
+                "Linear decay boosting hit.id {:?} hit.score {:?} with {:?} -- boost_value {:?}",
+                hit.id,
+                hit.score,
+                factor,
+                boost_value
+            );
+            hit.score *= factor as f32;
+        }
         None => {}
     }
     if let Some(exp) = expre.as_ref() {
         let prev_score = hit.score;
-        hit.score += exp.get_score(boost_value);
-        trace!(
-            "boost {:?} to {:?} with boost_fun({:?})={:?}",
-            prev_score,
-            hit.score,
-            boost_value,
-            exp.get_score(boost_value)
-        );
+        let expression_score = exp.get_score(hit.id, boost_value);
+        hit.score += expression_score;
+        trace!("boost {:?} to {:?} with boost_fun({:?})={:?}", prev_score, hit.score, boost_value, expression_score);
     }
 
     debug_assert!(!hit.score.is_nan());
@@ -361,12 +452,80 @@ pub(crate) fn apply_boost(
         let data = explain.entry(hit.id).or_insert_with(Vec::new);
         data.push(Explain::Boost(hit.score));
     }
+    if let Some(explain_tree) = explain_tree {
+        let operation = ExplainOperation::BoostFunction {
+            boost_fun: boost_fun.clone(),
+            boost_value,
+            param: boost_param,
+            expression: expre.as_ref().map(|exp| exp.expression().to_string()),
+        };
+        wrap_explain_node(explain_tree, hit.id, operation, hit.score);
+    }
 
     Ok(())
 }
 
+struct DecayParams {
+    /// distance of the boost value to the origin, minus the offset
+    distance: f64,
+    scale: f64,
+    decay: f64,
+}
+
+fn get_decay_params(decay_fun: &DecayFunction, boost_value: f32) -> Result<DecayParams, VelociError> {
+    let scale = decay_fun.scale.into_inner();
+    let decay = decay_fun.decay.map(|el| el.into_inner()).unwrap_or(0.5);
+    if scale <= 0.0 || decay <= 0.0 || decay >= 1.0 {
+        return Err(VelociError::InvalidRequest {
+            message: format!("Invalid decay function {:?}, scale needs to be > 0 and decay between 0 and 1", decay_fun),
+        });
+    }
+    let origin = decay_fun
+        .origin
+        .map(|el| el.into_inner())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() as f64).unwrap_or(0.0));
+    let offset = decay_fun.offset.map(|el| el.into_inner()).unwrap_or(0.0);
+    let distance = ((f64::from(boost_value) - origin).abs() - offset).max(0.0);
+    Ok(DecayParams { distance, scale, decay })
+}
+
+#[test]
+fn test_decay_boost_functions() {
+    let decay_fun = DecayFunction {
+        origin: Some(ordered_float::OrderedFloat(100.0)),
+        scale: ordered_float::OrderedFloat(10.0),
+        offset: Some(ordered_float::OrderedFloat(5.0)),
+        decay: Some(ordered_float::OrderedFloat(0.5)),
+    };
+    let boost_with = |boost_fun: BoostFunction, boost_value: f32| {
+        let mut hit = Hit::new(1, 2.0);
+        apply_boost(&mut hit, boost_value, 0.0, &Some(boost_fun), &mut None, &mut None, &None).unwrap();
+        hit.score
+    };
+    for boost_fun in &[
+        BoostFunction::Gauss(decay_fun.clone()),
+        BoostFunction::Exp(decay_fun.clone()),
+        BoostFunction::Linear(decay_fun.clone()),
+    ] {
+        // within offset no decay
+        assert_eq!(boost_with(boost_fun.clone(), 104.0), 2.0);
+        // at offset + scale the score is multiplied with decay
+        assert!((boost_with(boost_fun.clone(), 115.0) - 1.0).abs() < 0.0001);
+        assert!((boost_with(boost_fun.clone(), 85.0) - 1.0).abs() < 0.0001);
+        assert!(boost_with(boost_fun.clone(), 130.0) < 1.0);
+    }
+    assert_eq!(boost_with(BoostFunction::Linear(decay_fun.clone()), 130.0), 0.0);
+
+    let mut hit = Hit::new(1, 2.0);
+    let invalid = BoostFunction::Gauss(DecayFunction {
+        scale: ordered_float::OrderedFloat(0.0),
+        ..Default::default()
+    });
+    assert!(apply_boost(&mut hit, 1.0, 0.0, &Some(invalid), &mut None, &mut None, &None).is_err());
+}
+
 /// applies the boost values from the boostparts to the result
-pub(crate) fn boost_hits_ids_vec_multi(mut results: SearchFieldResult, boost: &mut Vec<SearchFieldResult>) -> SearchFieldResult {
+pub(crate) fn boost_hits_ids_vec_multi(mut results: SearchFieldResult, boost: &mut Vec<SearchFieldResult>, explain_operation: fn(f32) -> ExplainOperation) -> SearchFieldResult {
     {
         debug_time!("boost hits sort input");
         results.hits_scores.sort_unstable_by_key(|el| el.id); //TODO SORT NEEDED??
@@ -387,7 +546,7 @@ pub(crate) fn boost_hits_ids_vec_multi(mut results: SearchFieldResult, boost: &m
         .kmerge_by(|a, b| a.id < b.id);
 
     debug_time!("boost_hits_ids_vec_multi");
-    apply_boost_from_iter(results, &mut boost_iter)
+    apply_boost_from_iter(results, &mut boost_iter, explain_operation)
 }
 
 #[test]
@@ -413,6 +572,7 @@ fn boost_intersect_hits_vec_test_multi() {
             ..Default::default()
         },
         &mut boosts,
+        ExplainOperation::TermBoost,
     );
 
     assert_eq!(res.hits_scores, vec![Hit::new(0, 40.0), Hit::new(5, 20.0), Hit::new(10, 160.0), Hit::new(60, 40.0)]);
@@ -461,7 +621,7 @@ pub(crate) fn add_boost(persistence: &Persistence, boost: &RequestBoostPart, hit
     let boostkv_store = persistence.get_boost(&boost_path)?;
     let boost_param = boost.param.map(|el| el.into_inner()).unwrap_or(0.0);
 
-    let expre = boost.expression.as_ref().map(|expression| ScoreExpression::new(expression.clone()));
+    let expre = BoostExpression::from_boost_request(persistence, boost)?;
     let default = vec![];
     let skip_when_score = boost
         .skip_when_score
@@ -469,7 +629,9 @@ pub(crate) fn add_boost(persistence: &Persistence, boost: &RequestBoostPart, hit
         .map(|vecco| vecco.iter().map(|el| el.into_inner()).collect())
         .unwrap_or(default);
 
-    let mut explain = if hits.request.is_explain() { Some(&mut hits.explain) } else { None };
+    let should_explain = hits.request.is_explain();
+    let mut explain = if should_explain { Some(&mut hits.explain) } else { None };
+    let mut explain_tree = if should_explain { Some(&mut hits.explain_tree) } else { None };
     for hit in &mut hits.hits_scores {
         if !skip_when_score.is_empty() && skip_when_score.iter().any(|x| (*x - hit.score).abs() < 0.00001) {
             // float comparisons should usually include a error margin
@@ -481,7 +643,7 @@ pub(crate) fn add_boost(persistence: &Persistence, boost: &RequestBoostPart, hit
             trace!("Found in boosting for value_id {:?}: {:?}", hit.id, val_opt);
             let boost_value = *boost_value as f32;
 
-            apply_boost(hit, boost_value, boost_param, &boost.boost_fun, &mut explain, &expre)?;
+            apply_boost(hit, boost_value, boost_param, &boost.boost_fun, &mut explain, &mut explain_tree, &expre)?;
         }
 
         debug_assert!(!hit.score.is_nan());
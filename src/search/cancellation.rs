@@ -0,0 +1,167 @@
+use crate::error::VelociError;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Number of terms or hits, which are processed between two checks of the `CancellationToken`
+pub(crate) const CANCELLATION_CHECK_INTERVAL: usize = 1024;
+
+/// Cooperative cancellation of a query.
+///
+/// The plan steps check the token while streaming the fst, resolving anchors and before set operations.
+/// When the token is cancelled, they stop and pass on the hits collected so far.
+///
+/// The token also accounts the memory of the hits produced by the plan steps. A query exceeding its memory budget is cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    timeout_ms: u64,
+    /// When set, `check` does not return an error, the steps continue with partial results instead
+    partial_results: bool,
+    /// Bytes in hit vectors and term_id maps produced by the plan steps
+    memory_used: Arc<AtomicUsize>,
+    max_memory: Option<usize>,
+}
+
+impl CancellationToken {
+    pub fn with_timeout(timeout_ms: u64, partial_results: bool) -> Self {
+        CancellationToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Some(Instant::now() + Duration::from_millis(timeout_ms)),
+            timeout_ms,
+            partial_results,
+            ..Default::default()
+        }
+    }
+
+    /// Limits the memory of the hits, which can be produced during the query
+    pub fn with_max_memory(mut self, max_memory: Option<usize>) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+
+    /// Adds the bytes to the memory used by the query. Cancels the query and returns `VelociError::MemoryLimitExceeded` if the budget is exceeded.
+    pub fn add_memory(&self, bytes: usize) -> Result<(), VelociError> {
+        let memory_used = self.memory_used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.check_memory(memory_used)
+    }
+
+    /// Releases the bytes of hits, which were accounted with `add_memory` and are no longer held
+    pub fn release_memory(&self, bytes: usize) {
+        // the closure always returns Some, so the update can't fail
+        let _ = self
+            .memory_used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |memory_used| Some(memory_used.saturating_sub(bytes)));
+    }
+
+    /// Checks if `additional_bytes`, which are not yet accounted, would exceed the budget
+    pub fn check_additional_memory(&self, additional_bytes: usize) -> Result<(), VelociError> {
+        self.check_memory(self.memory_used.load(Ordering::Relaxed) + additional_bytes)
+    }
+
+    fn check_memory(&self, memory_used: usize) -> Result<(), VelociError> {
+        if let Some(max_memory) = self.max_memory {
+            if memory_used > max_memory {
+                self.cancel();
+                return Err(VelociError::MemoryLimitExceeded { memory_used, max_memory });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory_used.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the token was cancelled or the deadline is exceeded
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        if self.is_timed_out() {
+            self.cancel();
+            return true;
+        }
+        false
+    }
+
+    fn is_timed_out(&self) -> bool {
+        self.deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(false)
+    }
+
+    /// Returns true if the cancellation was observed, without checking the deadline again
+    pub fn was_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns `VelociError::MemoryLimitExceeded` if the memory budget was exceeded.
+    /// If the token is cancelled and partial results are not accepted, returns `VelociError::Timeout` when the deadline has passed
+    /// and `VelociError::Cancelled` otherwise, e.g. after `cancel`.
+    pub fn check(&self) -> Result<(), VelociError> {
+        self.check_memory(self.memory_used())?;
+        if !self.partial_results && self.is_cancelled() {
+            if self.is_timed_out() {
+                return Err(VelociError::Timeout { timeout_ms: self.timeout_ms });
+            }
+            return Err(VelociError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_cancellation_token() {
+    let token = CancellationToken::default();
+    assert!(!token.is_cancelled());
+    assert!(token.check().is_ok());
+    token.clone().cancel();
+    assert!(token.is_cancelled());
+    assert!(matches!(token.check(), Err(VelociError::Cancelled)));
+
+    let token = CancellationToken::with_timeout(60_000, false);
+    token.cancel();
+    assert!(matches!(token.check(), Err(VelociError::Cancelled)));
+
+    let token = CancellationToken::with_timeout(0, false);
+    assert!(matches!(token.check(), Err(VelociError::Timeout { timeout_ms: 0 })));
+
+    let token = CancellationToken::with_timeout(0, true);
+    assert!(!token.was_cancelled());
+    assert!(token.is_cancelled());
+    assert!(token.was_cancelled());
+    assert!(token.check().is_ok());
+}
+
+#[test]
+fn test_cancellation_token_memory_budget() {
+    let token = CancellationToken::default().with_max_memory(Some(100));
+    assert!(token.add_memory(60).is_ok());
+    assert!(token.check_additional_memory(40).is_ok());
+    assert!(!token.was_cancelled());
+    match token.add_memory(60) {
+        Err(VelociError::MemoryLimitExceeded { memory_used, max_memory }) => {
+            assert_eq!(memory_used, 120);
+            assert_eq!(max_memory, 100);
+        }
+        other => panic!("expected memory limit error, got {:?}", other),
+    }
+    assert!(token.was_cancelled());
+    assert!(token.check().is_err());
+
+    let token = CancellationToken::default().with_max_memory(Some(100));
+    assert!(token.add_memory(80).is_ok());
+    token.release_memory(80);
+    assert_eq!(token.memory_used(), 0);
+    assert!(token.add_memory(80).is_ok());
+    token.release_memory(100);
+    assert_eq!(token.memory_used(), 0);
+}
@@ -0,0 +1,125 @@
+use crate::{
+    error::VelociError,
+    facet::join_anchor_to_leaf,
+    persistence::{Persistence, ANCHOR_TO_TEXT_ID, PARENT_TO_VALUE_ID, VALUE_ID_TO_PARENT},
+    search::{
+        cancellation::{CancellationToken, CANCELLATION_CHECK_INTERVAL},
+        request::*,
+        result::*,
+        search_field::should_filter,
+        Hit,
+    },
+    util::{self, StringAdd},
+};
+use std::sync::Arc;
+
+pub(crate) fn check_field(persistence: &Persistence, path: &str) -> Result<(), VelociError> {
+    if persistence.metadata.columns.contains_key(path) {
+        Ok(())
+    } else {
+        Err(VelociError::FieldNotFound {
+            field: path.to_string(),
+            all_fields: persistence.metadata.get_all_fields(),
+        })
+    }
+}
+
+/// Anchor ids of the documents with at least one value in the field, sorted
+fn get_anchors_with_value(persistence: &Persistence, path: &str, cancellation_token: &CancellationToken) -> Result<Vec<u32>, VelociError> {
+    let steps = util::get_steps_to_anchor(path);
+    let num_docs = persistence.get_number_of_documents() as u32;
+
+    // like in facets, the anchor is resolved in one step if possible
+    if steps.len() == 1 || persistence.has_index(&(steps.last().unwrap().add(ANCHOR_TO_TEXT_ID))) {
+        let path = if steps.len() == 1 {
+            steps.first().unwrap().add(PARENT_TO_VALUE_ID)
+        } else {
+            steps.last().unwrap().add(ANCHOR_TO_TEXT_ID)
+        };
+        let kv_store = persistence.get_valueid_to_parent(path)?;
+        let mut anchor_ids = vec![];
+        for anchor_id in 0..num_docs {
+            if anchor_id as usize % CANCELLATION_CHECK_INTERVAL == 0 {
+                cancellation_token.check()?;
+            }
+            if kv_store.get_values(u64::from(anchor_id)).map(|values| !values.is_empty()).unwrap_or(false) {
+                anchor_ids.push(anchor_id);
+            }
+        }
+        return Ok(anchor_ids);
+    }
+
+    // join the values of the leaf up to the anchors, instead of joining every document down to the leaf
+    if steps.iter().all(|step| persistence.has_index(&step.add(VALUE_ID_TO_PARENT))) {
+        let num_value_ids = persistence
+            .metadata
+            .columns
+            .get(path)
+            .map(|field_info| field_info.textindex_metadata.num_text_ids + field_info.textindex_metadata.num_long_text_ids)
+            .unwrap_or(0) as u32;
+        let mut ids: Vec<u32> = (0..num_value_ids).collect();
+        for step in steps.iter().rev() {
+            ids = join_ids_to_parent(persistence, &ids, &step.add(VALUE_ID_TO_PARENT), cancellation_token)?;
+        }
+        return Ok(ids);
+    }
+
+    let mut anchor_ids = vec![];
+    for anchor_id in 0..num_docs {
+        if anchor_id as usize % CANCELLATION_CHECK_INTERVAL == 0 {
+            cancellation_token.check()?;
+        }
+        if !join_anchor_to_leaf(persistence, &[anchor_id], &steps)?.is_empty() {
+            anchor_ids.push(anchor_id);
+        }
+    }
+    Ok(anchor_ids)
+}
+
+/// Parent ids of the value ids, sorted and deduplicated
+fn join_ids_to_parent(persistence: &Persistence, ids: &[u32], path: &str, cancellation_token: &CancellationToken) -> Result<Vec<u32>, VelociError> {
+    let kv_store = persistence.get_valueid_to_parent(path)?;
+    let mut parent_ids = Vec::with_capacity(ids.len());
+    for chunk in ids.chunks(CANCELLATION_CHECK_INTERVAL) {
+        cancellation_token.check()?;
+        kv_store.append_values_for_ids(chunk, &mut parent_ids);
+        cancellation_token.check_additional_memory(parent_ids.capacity() * std::mem::size_of::<u32>())?;
+    }
+    parent_ids.sort_unstable();
+    parent_ids.dedup();
+    Ok(parent_ids)
+}
+
+/// Resolves an exists or, with `missing`, a missing request to the anchor ids. All hits have the same score.
+pub(crate) fn resolve_exists_to_anchor(
+    persistence: &Persistence,
+    request: &RequestExists,
+    missing: bool,
+    filter: &Option<Arc<FilterResult>>,
+    ids_only: bool,
+    cancellation_token: &CancellationToken,
+) -> Result<SearchFieldResult, VelociError> {
+    debug_time!("{} {} to anchor", request.path, if missing { "missing" } else { "exists" });
+    let mut anchor_ids = get_anchors_with_value(persistence, &request.path, cancellation_token)?;
+    if missing {
+        let num_docs = persistence.get_number_of_documents() as u32;
+        let mut with_value = anchor_ids.into_iter().peekable();
+        anchor_ids = (0..num_docs).filter(|anchor_id| with_value.next_if_eq(anchor_id).is_none()).collect();
+    }
+    cancellation_token.check_additional_memory(anchor_ids.capacity() * std::mem::size_of::<Hit>())?;
+    anchor_ids.retain(|anchor_id| !should_filter(filter, *anchor_id));
+
+    let mut res = SearchFieldResult::default();
+    res.request = RequestSearchPart {
+        path: request.path.to_string(),
+        terms: vec![format!("_{}_", if missing { "missing" } else { "exists" })],
+        options: request.options.clone(),
+        ..Default::default()
+    };
+    if ids_only {
+        res.hits_ids = anchor_ids;
+    } else {
+        res.hits_scores = anchor_ids.into_iter().map(|anchor_id| Hit::new(anchor_id, 1.0)).collect();
+    }
+    Ok(res)
+}
@@ -0,0 +1,102 @@
+use crate::{
+    error::VelociError,
+    facet::join_anchor_to_leaf,
+    geo::{self, GeoPoint},
+    metadata::FieldType,
+    persistence::Persistence,
+    search::{boost::apply_boost, request::*, result::*, search_field::get_text_for_id, sort::check_apply_top_n_sort, sort_by_score_and_id, Hit},
+    util,
+};
+use ordered_float::OrderedFloat;
+use std::cmp::Ordering;
+
+fn check_geo_point_field(persistence: &Persistence, field: &str) -> Result<(), VelociError> {
+    match persistence.metadata.columns.get(field) {
+        Some(field_info) if field_info.field_type == FieldType::GeoPoint => Ok(()),
+        _ => Err(VelociError::InvalidRequest {
+            message: format!(
+                "distance sort and geo boost are only supported on geo_point fields, but {:?} is not a geo_point field",
+                field
+            ),
+        }),
+    }
+}
+
+/// Distance in meters of the nearest geo point in `field` of the document to the origin
+fn get_min_distance(persistence: &Persistence, field: &str, anchor_id: u32, origin: &GeoPoint) -> Result<Option<f64>, VelociError> {
+    let steps = util::get_steps_to_anchor(field);
+    let text_ids = join_anchor_to_leaf(persistence, &[anchor_id], &steps)?;
+    let min_distance = text_ids
+        .iter()
+        .filter_map(|text_id| geo::decode_geohash(&get_text_for_id(persistence, steps.last().unwrap(), *text_id)))
+        .map(|point| origin.distance_to(&point))
+        .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    Ok(min_distance)
+}
+
+fn sort_by_distance_and_id((distance_a, hit_a): &(Option<f64>, Hit), (distance_b, hit_b): &(Option<f64>, Hit)) -> Ordering {
+    match (distance_a, distance_b) {
+        (Some(distance_a), Some(distance_b)) => distance_a.partial_cmp(distance_b).unwrap_or(Ordering::Equal).then(hit_a.id.cmp(&hit_b.id)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => sort_by_score_and_id(hit_a, hit_b),
+    }
+}
+
+/// Sorts the hits by distance, nearest first. Hits without geo point are sorted last by score.
+///
+/// With `top_n` only the nearest `top_n` hits are kept while sorting and returned.
+pub(crate) fn sort_by_distance(persistence: &Persistence, hits: Vec<Hit>, sort: &RequestGeoDistanceSort, top_n: Option<u32>) -> Result<Vec<Hit>, VelociError> {
+    check_geo_point_field(persistence, &sort.field)?;
+    let origin = sort.origin.to_geo_point()?;
+    let mut hits_with_distance = Vec::with_capacity(top_n.map(|top_n| top_n as usize + 201).unwrap_or(hits.len()).min(hits.len()));
+    for hit in hits {
+        if let Some(top_n) = top_n {
+            check_apply_top_n_sort(&mut hits_with_distance, top_n, &sort_by_distance_and_id, &mut |_| {});
+        }
+        hits_with_distance.push((get_min_distance(persistence, &sort.field, hit.id, &origin)?, hit));
+    }
+    hits_with_distance.sort_unstable_by(sort_by_distance_and_id);
+    if let Some(top_n) = top_n {
+        hits_with_distance.truncate(top_n as usize);
+    }
+    Ok(hits_with_distance.into_iter().map(|(_, hit)| hit).collect())
+}
+
+/// The decay is applied on the distance in meters, so the origin of the decay function is 0
+fn get_distance_decay_function(boost: &RequestGeoDistanceBoost) -> Result<BoostFunction, VelociError> {
+    let with_zero_origin = |decay_fun: &DecayFunction| DecayFunction {
+        origin: Some(OrderedFloat(0.0)),
+        ..decay_fun.clone()
+    };
+    match &boost.boost_fun {
+        BoostFunction::Gauss(decay_fun) => Ok(BoostFunction::Gauss(with_zero_origin(decay_fun))),
+        BoostFunction::Exp(decay_fun) => Ok(BoostFunction::Exp(with_zero_origin(decay_fun))),
+        BoostFunction::Linear(decay_fun) => Ok(BoostFunction::Linear(with_zero_origin(decay_fun))),
+        other => Err(VelociError::InvalidRequest {
+            message: format!("geo_boost requires a decay function (Gauss, Exp or Linear), but got {:?}", other),
+        }),
+    }
+}
+
+/// Multiplies the score of the hits with the decay of the distance to the origin. Hits without geo point are not boosted.
+pub(crate) fn apply_geo_boosts(
+    persistence: &Persistence,
+    mut res: SearchFieldResult,
+    geo_boosts: &[RequestGeoDistanceBoost],
+    explain: bool,
+) -> Result<SearchFieldResult, VelociError> {
+    info_time!("geo_boost");
+    for geo_boost in geo_boosts {
+        check_geo_point_field(persistence, &geo_boost.field)?;
+        let origin = geo_boost.origin.to_geo_point()?;
+        let boost_fun = Some(get_distance_decay_function(geo_boost)?);
+        for hit in res.hits_scores.iter_mut() {
+            if let Some(distance) = get_min_distance(persistence, &geo_boost.field, hit.id, &origin)? {
+                let mut explain_tree = if explain { Some(&mut res.explain_tree) } else { None };
+                apply_boost(hit, distance as f32, 0.0, &boost_fun, &mut None, &mut explain_tree, &None)?;
+            }
+        }
+    }
+    Ok(res)
+}
@@ -0,0 +1,139 @@
+use crate::{
+    error::VelociError,
+    persistence::{Persistence, VALUE_ID_TO_PARENT},
+    search::{request::*, result::*, search_field::*, set_op::*, Hit},
+    util::{self, StringAdd},
+};
+use fnv::FnvHashMap;
+use std::sync::Arc;
+
+/// Joins the hits to their parents, the max score of the children is kept per parent.
+///
+/// The explanations in `explain_tree` are moved to the parents, a parent keeps the explanation of its best child.
+fn join_hits_to_parent(persistence: &Persistence, hits: &[Hit], path: &str, explain_tree: &mut FnvHashMap<u32, ExplainNode>) -> Result<Vec<Hit>, VelociError> {
+    let kv_store = persistence.get_valueid_to_parent(path)?;
+    let mut parent_hits = Vec::with_capacity(hits.len());
+    let mut parent_explain_tree: FnvHashMap<u32, ExplainNode> = FnvHashMap::default();
+    for hit in hits {
+        for parent_id in kv_store.get_values(u64::from(hit.id)).into_iter().flatten() {
+            parent_hits.push(Hit::new(parent_id, hit.score));
+            if let Some(node) = explain_tree.get(&hit.id) {
+                add_best_explain_node(&mut parent_explain_tree, parent_id, node);
+            }
+        }
+    }
+    *explain_tree = parent_explain_tree;
+    parent_hits.sort_unstable_by_key(|hit| hit.id);
+    parent_hits.dedup_by(|a, b| {
+        if a.id == b.id {
+            if a.score > b.score {
+                b.score = a.score; //a will be discarded, store max
+            }
+            true
+        } else {
+            false
+        }
+    });
+    Ok(parent_hits)
+}
+
+/// Keeps the explanation with the higher score for `id`, like the max score kept for the hits
+fn add_best_explain_node(explain_tree: &mut FnvHashMap<u32, ExplainNode>, id: u32, node: &ExplainNode) {
+    match explain_tree.get(&id) {
+        Some(existing) if existing.score >= node.score => {}
+        _ => {
+            explain_tree.insert(id, node.clone());
+        }
+    }
+}
+
+/// Resolves the token hits of a field below the nested path to the value ids of the nested elements
+fn resolve_part_to_nested_elements(
+    persistence: &Persistence,
+    nested_path: &str,
+    part: &RequestSearchPart,
+    mut result: SearchFieldResult,
+) -> Result<SearchFieldResult, VelociError> {
+    resolve_token_hits_to_text_id(persistence, part, &mut result)?;
+    let mut hits = std::mem::replace(&mut result.hits_scores, vec![]);
+    // a text can be hit directly and via its tokens, both are explained with their own tree
+    let mut explain_tree = std::mem::take(&mut result.explain_tree);
+    for (id, node) in &result.text_id_explain_tree {
+        add_best_explain_node(&mut explain_tree, *id, node);
+    }
+    for step in util::get_steps_to_anchor(&part.path).iter().rev().take_while(|step| *step != nested_path) {
+        hits = join_hits_to_parent(persistence, &hits, &step.add(VALUE_ID_TO_PARENT), &mut explain_tree)?;
+    }
+    Ok(SearchFieldResult {
+        hits_scores: hits,
+        explain_tree,
+        request: result.request,
+        term_id_hits_in_field: result.term_id_hits_in_field,
+        term_text_in_field: result.term_text_in_field,
+        ..Default::default()
+    })
+}
+
+/// Evaluates the query tree on the value ids of the nested elements. The field results are consumed in the order of `SearchRequest::get_search_parts`
+fn resolve_request_to_nested_elements(
+    persistence: &Persistence,
+    nested_path: &str,
+    request: &SearchRequest,
+    field_results: &mut impl Iterator<Item = SearchFieldResult>,
+) -> Result<SearchFieldResult, VelociError> {
+    match request {
+        SearchRequest::Or(SearchTree { queries, .. }) => {
+            let results = queries
+                .iter()
+                .map(|query| resolve_request_to_nested_elements(persistence, nested_path, query, field_results))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(union_hits_score(results))
+        }
+        SearchRequest::And(SearchTree { queries, .. }) => {
+            let results = queries
+                .iter()
+                .map(|query| resolve_request_to_nested_elements(persistence, nested_path, query, field_results))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(intersect_hits_score(results))
+        }
+        SearchRequest::Search(part) => {
+            let result = field_results
+                .next()
+                .ok_or_else(|| VelociError::StringError(format!("missing field result for {:?} in the nested query on {:?}", part.path, nested_path)))?;
+            resolve_part_to_nested_elements(persistence, nested_path, part, result)
+        }
+        SearchRequest::Nested(_) | SearchRequest::Exists(_) | SearchRequest::Missing(_) => Err(VelociError::InvalidRequest {
+            message: format!("only search, and and or queries are supported in the nested query on {:?}", nested_path),
+        }),
+    }
+}
+
+/// Matches the subquery on the nested elements and joins the matching elements to the anchor.
+///
+/// `field_results` are the results of the field searches of the subquery, in the order of `SearchRequest::get_search_parts`.
+pub(crate) fn resolve_nested_to_anchor(
+    persistence: &Persistence,
+    nested: &NestedSearch,
+    field_results: Vec<SearchFieldResult>,
+    filter: &Option<Arc<FilterResult>>,
+    ids_only: bool,
+) -> Result<SearchFieldResult, VelociError> {
+    debug_time!("{} nested to anchor", nested.path);
+    let mut res = resolve_request_to_nested_elements(persistence, &nested.path, &nested.query, &mut field_results.into_iter())?;
+
+    let steps = util::get_steps_to_anchor(&nested.path);
+    let mut hits = std::mem::replace(&mut res.hits_scores, vec![]);
+    for step in steps.iter().rev().skip(1) {
+        hits = join_hits_to_parent(persistence, &hits, &step.add(VALUE_ID_TO_PARENT), &mut res.explain_tree)?;
+    }
+    hits.retain(|hit| !should_filter(filter, hit.id));
+
+    if ids_only {
+        res.hits_ids = hits.into_iter().map(|hit| hit.id).collect();
+        res.explain_tree.clear();
+    } else {
+        res.hits_scores = hits;
+    }
+    res.explain.clear();
+    Ok(res)
+}
@@ -10,6 +10,14 @@ pub fn read_data(persistence: &Persistence, id: u32, fields: &[String]) -> Resul
     read_tree(persistence, id, &tree)
 }
 
+/// Converts the indexed term of typed fields back to the json value, e.g. a number instead of a string
+fn to_typed_value(persistence: &Persistence, path: &str, term: String) -> serde_json::Value {
+    match persistence.metadata.columns.get(path) {
+        Some(field_info) => field_info.field_type.term_to_json(term),
+        None => json!(term),
+    }
+}
+
 fn read_tree(persistence: &Persistence, id: u32, tree: &NodeTree) -> Result<serde_json::Value, VelociError> {
     let mut json = json!({});
     match *tree {
@@ -24,13 +32,13 @@ fn read_tree(persistence: &Persistence, id: u32, tree: &NodeTree) -> Result<serd
                                 let mut sub_data = vec![];
                                 for sub_id in sub_ids {
                                     if let Some(texto) = join_and_get_text_for_ids(persistence, sub_id, prop)? {
-                                        sub_data.push(json!(texto));
+                                        sub_data.push(to_typed_value(persistence, prop, texto));
                                     }
                                 }
                                 json[extract_prop_name(prop)] = json!(sub_data);
                             }
                         } else if let Some(texto) = join_and_get_text_for_ids(persistence, id, prop)? {
-                            json[extract_prop_name(prop)] = json!(texto);
+                            json[extract_prop_name(prop)] = to_typed_value(persistence, prop, texto);
                         }
                     }
                     NodeTree::Map(ref _next) => {
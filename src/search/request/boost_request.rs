@@ -15,6 +15,26 @@ pub enum BoostFunction {
     Log10,
     Multiply,
     Add,
+    /// Multiplies the score with a gaussian decay of the distance between the boost value and the origin
+    Gauss(DecayFunction),
+    /// Multiplies the score with a linear decay of the distance between the boost value and the origin, hits beyond the decay range get a score of 0
+    Linear(DecayFunction),
+    /// Multiplies the score with an exponential decay of the distance between the boost value and the origin
+    Exp(DecayFunction),
+}
+
+/// Parameters of the decay boost functions, e.g. to rank newer documents or prices near a target higher.
+///
+/// A boost value with a distance of `offset + scale` to the origin gets the score multiplied with `decay`.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub struct DecayFunction {
+    /// The value with the maximum score. Defaults to the current time in seconds since the unix epoch, for recency boosts on date fields.
+    pub origin: Option<OrderedFloat<f64>>,
+    pub scale: OrderedFloat<f64>,
+    /// Distance to the origin, within which the score is not decayed. Defaults to 0.
+    pub offset: Option<OrderedFloat<f64>>,
+    /// Defaults to 0.5
+    pub decay: Option<OrderedFloat<f64>>,
 }
 
 impl Default for BoostFunction {
@@ -1,9 +1,15 @@
+use crate::date::DateUnit;
+
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct FacetRequest {
     pub field: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default = "default_top")]
     pub top: Option<usize>,
+    /// Counts the values of a date field in buckets of this interval, e.g. "day" or "month". The buckets are sorted by date, `top` is ignored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub date_histogram: Option<DateUnit>,
 }
 
 fn default_top() -> Option<usize> {
@@ -0,0 +1,100 @@
+use crate::{
+    error::VelociError,
+    geo,
+    search::request::{boost_request::BoostFunction, search_request::RequestSearchPart},
+};
+use ordered_float::OrderedFloat;
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub struct RequestGeoPoint {
+    pub lat: OrderedFloat<f64>,
+    pub lon: OrderedFloat<f64>,
+}
+
+impl RequestGeoPoint {
+    pub(crate) fn to_geo_point(&self) -> Result<geo::GeoPoint, VelociError> {
+        geo::GeoPoint::new(self.lat.into_inner(), self.lon.into_inner())
+    }
+}
+
+/// Matches the geo points within the distance to the origin
+#[derive(Serialize, Deserialize, Default, Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub struct RequestGeoDistance {
+    pub origin: RequestGeoPoint,
+    /// Distance with unit, e.g. "10km", "500m" or "2mi". See `geo::parse_distance`
+    pub distance: String,
+}
+
+/// Matches the geo points within the box. If `top_left.lon` is greater than `bottom_right.lon`, the box crosses the date line.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub struct RequestGeoBoundingBox {
+    pub top_left: RequestGeoPoint,
+    pub bottom_right: RequestGeoPoint,
+}
+
+/// Sorts the hits by the distance of the geo point in `field` to the origin, nearest first. Hits without geo point are sorted last.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct RequestGeoDistanceSort {
+    pub field: String,
+    pub origin: RequestGeoPoint,
+}
+
+/// Boosts the hits by the distance of the geo point in `field` to the origin.
+///
+/// `boost_fun` needs to be a decay function (`Gauss`, `Exp` or `Linear`), its `scale` and `offset` are in meters and its `origin` is ignored.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequestGeoDistanceBoost {
+    pub field: String,
+    pub origin: RequestGeoPoint,
+    pub boost_fun: BoostFunction,
+}
+
+/// The geo filters of a `RequestSearchPart`, resolved to bounding boxes
+pub(crate) struct GeoFilter {
+    distance: Option<(geo::GeoPoint, f64)>,
+    bounding_boxes: Vec<geo::BoundingBox>,
+}
+
+impl GeoFilter {
+    pub(crate) fn from_request(part: &RequestSearchPart) -> Result<Option<GeoFilter>, VelociError> {
+        let distance = match part.geo_distance.as_ref() {
+            Some(geo_distance) => Some((geo_distance.origin.to_geo_point()?, geo::parse_distance(&geo_distance.distance)?)),
+            None => None,
+        };
+        let bounding_boxes = match (part.geo_bounding_box.as_ref(), distance) {
+            (Some(bounding_box), _) => {
+                let (top_left, bottom_right) = (bounding_box.top_left.to_geo_point()?, bounding_box.bottom_right.to_geo_point()?);
+                if top_left.lat < bottom_right.lat {
+                    return Err(VelociError::InvalidRequest {
+                        message: format!("Invalid geo_bounding_box {:?}, top_left.lat needs to be greater than bottom_right.lat", bounding_box),
+                    });
+                }
+                geo::BoundingBox::split_at_date_line(bottom_right.lat, top_left.lat, top_left.lon, bottom_right.lon)
+            }
+            (None, Some((origin, meters))) => geo::BoundingBox::around(&origin, meters),
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(GeoFilter { distance, bounding_boxes }))
+    }
+
+    /// Geohash prefixes of the indexed terms, which may match the filter
+    pub(crate) fn covering_geohashes(&self) -> Vec<String> {
+        self.bounding_boxes.iter().flat_map(|bounding_box| bounding_box.covering_geohashes()).collect()
+    }
+
+    pub(crate) fn matches(&self, point: &geo::GeoPoint) -> bool {
+        self.bounding_boxes.iter().any(|bounding_box| bounding_box.contains(point)) && self.distance.map(|(origin, meters)| origin.distance_to(point) <= meters).unwrap_or(true)
+    }
+}
+
+impl std::fmt::Display for RequestGeoDistance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} around {},{}", self.distance, self.origin.lat, self.origin.lon)
+    }
+}
+
+impl std::fmt::Display for RequestGeoBoundingBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{},{} TO {},{}]", self.top_left.lat, self.top_left.lon, self.bottom_right.lat, self.bottom_right.lon)
+    }
+}
@@ -1,11 +1,13 @@
 pub mod boost_request;
 pub mod facet_request;
+pub mod geo_request;
 pub mod search_request;
 pub mod snippet_info;
 
 use crate::search::*;
 pub use boost_request::*;
 pub use facet_request::*;
+pub use geo_request::*;
 pub use search_request::*;
 pub use snippet_info::*;
 
@@ -84,6 +86,53 @@ pub struct Request {
     #[serde(skip_serializing_if = "skip_false")]
     #[serde(default)]
     pub explain: bool,
+
+    /// Profiles the execution of the plan. The result will contain a tree of the executed plan steps, with their hit counts and execution times.
+    #[serde(skip_serializing_if = "skip_false")]
+    #[serde(default)]
+    pub profile: bool,
+
+    /// Aborts the search after the timeout in milliseconds, with `VelociError::Timeout`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+
+    /// On timeout, return the hits found so far, flagged with `timed_out` in the result, instead of an error.
+    #[serde(skip_serializing_if = "skip_false")]
+    #[serde(default)]
+    pub partial_results_on_timeout: bool,
+
+    /// Maximum number of bytes in hit vectors and term_id maps, which can be allocated during the execution of the plan.
+    /// The search is aborted with `VelociError::MemoryLimitExceeded` when the limit is exceeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_memory: Option<usize>,
+
+    /// Sorts the hits by distance instead of score
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub sort_by_distance: Option<RequestGeoDistanceSort>,
+
+    /// Boosts the hits with a decay of the distance to an origin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub geo_boost: Option<Vec<RequestGeoDistanceBoost>>,
+}
+
+impl Request {
+    /// Intersects `filter` with the filter of the request, so that only hits matching both are returned.
+    ///
+    /// Used to enforce a mandatory filter, which the sender of the request can't remove, e.g. a tenant filter bound to an api key.
+    pub fn add_filter(&mut self, filter: SearchRequest) {
+        let filter = match self.filter.take() {
+            Some(existing) => SearchRequest::And(SearchTree {
+                queries: vec![*existing, filter],
+                options: Default::default(),
+            }),
+            None => filter,
+        };
+        self.filter = Some(Box::new(filter));
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug, Hash, PartialEq, Eq)]
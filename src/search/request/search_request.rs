@@ -1,4 +1,11 @@
-use crate::search::request::{boost_request::RequestBoostPart, snippet_info::SnippetInfo};
+use crate::{
+    error::VelociError,
+    search::request::{
+        boost_request::RequestBoostPart,
+        geo_request::{RequestGeoBoundingBox, RequestGeoDistance},
+        snippet_info::SnippetInfo,
+    },
+};
 use core::cmp::Ordering;
 use ordered_float::OrderedFloat;
 
@@ -12,6 +19,12 @@ pub enum SearchRequest {
     ///
     /// `RequestSearchPart` is boxed
     Search(RequestSearchPart),
+    /// Matches the subquery within the same element of an array of objects
+    Nested(NestedSearch),
+    /// Matches the documents with at least one value in the field
+    Exists(RequestExists),
+    /// Matches the documents without value in the field
+    Missing(RequestExists),
 }
 
 // #[derive(Serialize, Deserialize, Default, Clone, Debug)]
@@ -25,6 +38,51 @@ pub struct SearchTree {
     pub options: SearchRequestOptions,
 }
 
+/// Scopes a subquery to the elements of an array of objects, e.g. `variants[]`.
+///
+/// All conditions of the subquery need to match on the same element, so that `variants[].color:red AND variants[].size:XL` doesn't match a document, which
+/// has a red variant and another variant in size XL. The subquery is resolved to the value ids of the elements (via `VALUE_ID_TO_PARENT`) and intersected
+/// there, before joining to the anchor.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NestedSearch {
+    /// Path of the array of objects, e.g. `variants[]`. All paths in the subquery need to be fields of the elements, e.g. `variants[].color`
+    pub path: String,
+    pub query: Box<SearchRequest>,
+    #[serde(default)]
+    pub options: SearchRequestOptions,
+}
+
+impl NestedSearch {
+    pub(crate) fn validate(&self) -> Result<(), VelociError> {
+        if !self.path.ends_with("[]") {
+            return Err(VelociError::InvalidRequest {
+                message: format!("nested path {:?} needs to be an array of objects, e.g. \"variants[]\"", self.path),
+            });
+        }
+        for part in self.query.get_search_parts() {
+            if !part.path.starts_with(&(self.path.to_string() + ".")) {
+                return Err(VelociError::InvalidRequest {
+                    message: format!("path {:?} in nested query is not a field of the nested path {:?}", part.path, self.path),
+                });
+            }
+        }
+        self.query.try_for_each_request(&mut |request| match request {
+            SearchRequest::Nested(_) | SearchRequest::Exists(_) | SearchRequest::Missing(_) => Err(VelociError::InvalidRequest {
+                message: format!("only search, and and or queries are supported in the nested query on {:?}", self.path),
+            }),
+            _ => Ok(()),
+        })
+    }
+}
+
+/// Matches on the existence of a value in the field, e.g. `{"exists": {"path": "ean"}}`
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct RequestExists {
+    pub path: String,
+    #[serde(default)]
+    pub options: SearchRequestOptions,
+}
+
 impl SearchRequest {
     pub fn simplify(&mut self) {
         match self {
@@ -64,6 +122,40 @@ impl SearchRequest {
                 subtree.queries.extend(subitems.into_iter());
             }
             SearchRequest::Search(_req) => {}
+            SearchRequest::Nested(nested) => nested.query.simplify(),
+            SearchRequest::Exists(_) | SearchRequest::Missing(_) => {}
+        }
+    }
+
+    /// Calls `fun` for each `RequestSearchPart` in the tree
+    pub fn try_for_each_search_part<E, F: FnMut(&mut RequestSearchPart) -> Result<(), E>>(&mut self, fun: &mut F) -> Result<(), E> {
+        match self {
+            SearchRequest::Or(SearchTree { queries, .. }) | SearchRequest::And(SearchTree { queries, .. }) => {
+                queries.iter_mut().try_for_each(|query| query.try_for_each_search_part(fun))
+            }
+            SearchRequest::Search(part) => fun(part),
+            SearchRequest::Nested(nested) => nested.query.try_for_each_search_part(fun),
+            SearchRequest::Exists(_) | SearchRequest::Missing(_) => Ok(()),
+        }
+    }
+
+    /// Calls `fun` for each node in the tree, parents before their subqueries
+    pub(crate) fn try_for_each_request<E, F: FnMut(&SearchRequest) -> Result<(), E>>(&self, fun: &mut F) -> Result<(), E> {
+        fun(self)?;
+        match self {
+            SearchRequest::Or(SearchTree { queries, .. }) | SearchRequest::And(SearchTree { queries, .. }) => queries.iter().try_for_each(|query| query.try_for_each_request(fun)),
+            SearchRequest::Nested(nested) => nested.query.try_for_each_request(fun),
+            SearchRequest::Search(_) | SearchRequest::Exists(_) | SearchRequest::Missing(_) => Ok(()),
+        }
+    }
+
+    /// All `RequestSearchPart` in the tree, depth first
+    pub(crate) fn get_search_parts(&self) -> Vec<&RequestSearchPart> {
+        match self {
+            SearchRequest::Or(SearchTree { queries, .. }) | SearchRequest::And(SearchTree { queries, .. }) => queries.iter().flat_map(|query| query.get_search_parts()).collect(),
+            SearchRequest::Search(part) => vec![part],
+            SearchRequest::Nested(nested) => nested.query.get_search_parts(),
+            SearchRequest::Exists(_) | SearchRequest::Missing(_) => vec![],
         }
     }
 
@@ -72,6 +164,8 @@ impl SearchRequest {
             SearchRequest::Or(SearchTree { options, .. }) => options,
             SearchRequest::And(SearchTree { options, .. }) => options,
             SearchRequest::Search(el) => &el.options,
+            SearchRequest::Nested(NestedSearch { options, .. }) => options,
+            SearchRequest::Exists(RequestExists { options, .. }) | SearchRequest::Missing(RequestExists { options, .. }) => options,
         }
     }
 
@@ -80,6 +174,8 @@ impl SearchRequest {
             SearchRequest::Or(SearchTree { options, .. }) => options,
             SearchRequest::And(SearchTree { options, .. }) => options,
             SearchRequest::Search(el) => &mut el.options,
+            SearchRequest::Nested(NestedSearch { options, .. }) => options,
+            SearchRequest::Exists(RequestExists { options, .. }) | SearchRequest::Missing(RequestExists { options, .. }) => options,
         }
     }
 
@@ -122,8 +218,24 @@ fn is_false(val: &bool) -> bool {
 #[derive(Serialize, Deserialize, Default, Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
 pub struct RequestSearchPart {
     pub path: String,
+    /// Can be omitted for `range`, `geo_distance` and `geo_bounding_box` requests
+    #[serde(default)]
     pub terms: Vec<String>, //TODO only first term used currently
 
+    /// Matches all values of a typed field in the range, instead of the terms
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<RequestRange>,
+
+    /// Matches the values of a geo_point field within the distance to an origin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub geo_distance: Option<RequestGeoDistance>,
+
+    /// Matches the values of a geo_point field within the bounding box
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub geo_bounding_box: Option<RequestGeoBoundingBox>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub levenshtein_distance: Option<u32>,
 
@@ -171,7 +283,61 @@ pub struct RequestSearchPart {
     pub options: SearchRequestOptions,
 }
 
+/// Range on the values of a typed field (date, i64, f64 or bool). The bounds of date fields are dates or date math like `now-7d/d`, see `date::parse_date_math`
+#[derive(Serialize, Deserialize, Default, Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub struct RequestRange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "deserialize_range_bound")]
+    pub gte: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "deserialize_range_bound")]
+    pub gt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "deserialize_range_bound")]
+    pub lte: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "deserialize_range_bound")]
+    pub lt: Option<String>,
+    /// The bounds as terms in the fst, resolved from the bounds before the search
+    #[serde(skip)]
+    pub(crate) resolved_terms: Option<(TermBound, TermBound)>,
+}
+
+/// Bound of a range in the fst, like `std::ops::Bound`
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub(crate) enum TermBound {
+    Included(String),
+    Excluded(String),
+    Unbounded,
+}
+
+/// Bounds may be strings, numbers or bools, e.g. `{"gte": 10}`
+fn deserialize_range_bound<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+    Ok(match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(text)) => Some(text),
+        Some(other) => Some(other.to_string()),
+    })
+}
+
+impl std::fmt::Display for RequestRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lower = self.gte.as_ref().map(|el| format!("[{}", el)).or_else(|| self.gt.as_ref().map(|el| format!("{{{}", el)));
+        let upper = self.lte.as_ref().map(|el| format!("{}]", el)).or_else(|| self.lt.as_ref().map(|el| format!("{}}}", el)));
+        write!(f, "{} TO {}", lower.unwrap_or_else(|| "[*".to_string()), upper.unwrap_or_else(|| "*]".to_string()))
+    }
+}
+
 impl RequestSearchPart {
+    /// The part matches values by a range or geo filter instead of the terms
+    pub(crate) fn is_value_filter(&self) -> bool {
+        self.range.is_some() || self.geo_distance.is_some() || self.geo_bounding_box.is_some()
+    }
+
     pub fn is_explain(&self) -> bool {
         self.options.explain
     }
@@ -1,3 +1,6 @@
+use crate::search::request::boost_request::BoostFunction;
+use fnv::FnvHashMap;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Explain {
     Boost(f32),
@@ -19,3 +22,61 @@ pub enum Explain {
         new_score: u32,
     },
 }
+
+/// The operation which produced the score of an `ExplainNode`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExplainOperation {
+    /// Leaf, a term or token in the fst matched the search term
+    TermMatch { term_id: u32, term: String },
+    /// Static boost of the search part (`RequestSearchPart.boost`)
+    SearchPartBoost(f32),
+    /// The score of a term multiplied with the anchor score of the term in the document
+    TermToAnchor { term_id: u32, term_score: f32, anchor_score: f32 },
+    /// Max score of all tokens hitting the same text
+    MaxTokenToTextId,
+    /// Multiple terms hit the same anchor, the best one is kept
+    Max,
+    /// Or connection: sum of the best scores per distinct term, multiplied with num_distinct_terms²
+    UnionSum { sum_over_distinct_terms: f32, num_distinct_terms: u32 },
+    /// And connection: sum of the scores of all subqueries
+    Intersection,
+    /// Boost via a boost function, based on an indexed value
+    BoostFunction {
+        boost_fun: Option<BoostFunction>,
+        boost_value: f32,
+        param: f32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expression: Option<String>,
+    },
+    /// Multiplied with the text locality boost
+    TextLocality(f32),
+    /// Multiplied with the phrase boost
+    PhraseBoost(f32),
+    /// Multiplied with the term boost (`Request.boost_term`)
+    TermBoost(f32),
+}
+
+/// Node in the explanation tree of a hit.
+///
+/// The score is the output of the operation, the inputs are the explanations of the scores the operation was applied on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExplainNode {
+    pub operation: ExplainOperation,
+    pub score: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub inputs: Vec<ExplainNode>,
+}
+
+impl ExplainNode {
+    pub fn new(operation: ExplainOperation, score: f32, inputs: Vec<ExplainNode>) -> Self {
+        ExplainNode { operation, score, inputs }
+    }
+}
+
+/// Replaces the explanation of `id` with a new node, which has the previous explanation as input.
+pub(crate) fn wrap_explain_node(explain_tree: &mut FnvHashMap<u32, ExplainNode>, id: u32, operation: ExplainOperation, score: f32) {
+    let inputs = explain_tree.remove(&id).into_iter().collect();
+    explain_tree.insert(id, ExplainNode::new(operation, score, inputs));
+}
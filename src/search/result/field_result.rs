@@ -7,6 +7,14 @@ use std::iter::FusedIterator;
 pub struct SearchFieldResult {
     #[serde(skip_serializing_if = "FnvHashMap::is_empty")]
     pub explain: FnvHashMap<u32, Vec<Explain>>,
+    /// explanation tree per hit, how the score was calculated
+    #[serde(skip_serializing_if = "FnvHashMap::is_empty")]
+    pub explain_tree: FnvHashMap<u32, ExplainNode>,
+    /// explanation tree per text id, after the token hits are resolved to the texts containing them (`resolve_token_hits_to_text_id`).
+    /// Separate from `explain_tree`, because a term can be hit directly and as a text containing other hit tokens
+    #[serde(skip_serializing_if = "FnvHashMap::is_empty")]
+    #[serde(default)]
+    pub text_id_explain_tree: FnvHashMap<u32, ExplainNode>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub hits_scores: Vec<search::Hit>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -42,6 +50,8 @@ impl SearchFieldResult {
     pub(crate) fn new_from(other: &SearchFieldResult) -> Self {
         let mut res = SearchFieldResult::default();
         res.explain = other.explain.clone();
+        res.explain_tree = other.explain_tree.clone();
+        res.text_id_explain_tree = other.text_id_explain_tree.clone();
         res.terms = other.terms.clone();
         res.highlight = other.highlight.clone();
         res.request = other.request.clone();
@@ -50,6 +60,20 @@ impl SearchFieldResult {
         res.term_text_in_field = other.term_text_in_field.clone();
         res
     }
+
+    /// Approximate number of bytes in the hit vectors and term_id maps, used for the memory budget of a query
+    pub(crate) fn get_memory_size(&self) -> usize {
+        let term_id_hits_size: usize = self
+            .term_id_hits_in_field
+            .values()
+            .flat_map(|term_to_hits| term_to_hits.iter())
+            .map(|(term, hits)| term.len() + hits.capacity() * std::mem::size_of::<TermId>())
+            .sum();
+        self.hits_scores.capacity() * std::mem::size_of::<search::Hit>()
+            + self.boost_ids.capacity() * std::mem::size_of::<search::Hit>()
+            + self.hits_ids.capacity() * std::mem::size_of::<TermId>()
+            + term_id_hits_size
+    }
 }
 
 impl std::fmt::Display for SearchFieldResult {
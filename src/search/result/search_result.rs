@@ -1,4 +1,10 @@
-use crate::search::{result::explain::Explain, Hit};
+use crate::{
+    plan_creator::profile::PlanStepProfile,
+    search::{
+        result::explain::{Explain, ExplainNode},
+        skip_false, Hit,
+    },
+};
 use fnv::FnvHashMap;
 
 /// SearchResult` is the result form a search, without the document itself
@@ -13,8 +19,18 @@ pub struct SearchResult {
     pub ids: Vec<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub facets: Option<FnvHashMap<String, Vec<(String, usize)>>>,
+    /// profile of the executed plan, when `profile` is enabled in the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<PlanStepProfile>,
+    /// the search was aborted by the timeout, the result contains only the hits found until then
+    #[serde(skip_serializing_if = "skip_false")]
+    #[serde(default)]
+    pub timed_out: bool,
     #[serde(skip_serializing_if = "FnvHashMap::is_empty")]
     pub explain: FnvHashMap<u32, Vec<Explain>>,
+    /// explanation tree for the returned hits
+    #[serde(skip_serializing_if = "FnvHashMap::is_empty")]
+    pub explain_tree: FnvHashMap<u32, ExplainNode>,
     #[serde(skip_serializing_if = "FnvHashMap::is_empty")]
     pub why_found_info: FnvHashMap<u32, FnvHashMap<String, Vec<String>>>,
     #[serde(skip_serializing_if = "FnvHashMap::is_empty")]
@@ -1,4 +1,10 @@
-use crate::search::{result::explain::Explain, Hit};
+use crate::{
+    plan_creator::profile::PlanStepProfile,
+    search::{
+        result::explain::{Explain, ExplainNode},
+        skip_false, Hit,
+    },
+};
 use fnv::FnvHashMap;
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
@@ -8,6 +14,13 @@ pub struct SearchResultWithDoc {
     pub data: Vec<DocWithHit>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub facets: Option<FnvHashMap<String, Vec<(String, usize)>>>,
+    /// profile of the executed plan, when `profile` is enabled in the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<PlanStepProfile>,
+    /// the search was aborted by the timeout, the result contains only the hits found until then
+    #[serde(skip_serializing_if = "skip_false")]
+    #[serde(default)]
+    pub timed_out: bool,
 }
 
 // impl SearchResultWithDoc {
@@ -26,6 +39,8 @@ pub struct DocWithHit {
     pub hit: Hit,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub explain: Option<Vec<Explain>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain_tree: Option<ExplainNode>,
     #[serde(skip_serializing_if = "FnvHashMap::is_empty")]
     pub why_found: FnvHashMap<String, Vec<String>>,
 }
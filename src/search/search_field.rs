@@ -1,14 +1,15 @@
 use self::sort::check_apply_top_n_sort;
 use crate::{
     error::VelociError,
+    geo,
     highlight_field::*,
     persistence::{self, Persistence, *},
     plan_creator::execution_plan::*,
-    search::{self, result::*, *},
+    search::{self, cancellation::CANCELLATION_CHECK_INTERVAL, result::*, *},
     util::{self, StringAdd},
 };
 use fnv::FnvHashMap;
-use fst::{automaton::*, raw::Fst, IntoStreamer};
+use fst::{automaton::*, raw::Fst, IntoStreamer, Streamer};
 use itertools::Itertools;
 use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
 use ordered_float::OrderedFloat;
@@ -51,35 +52,70 @@ pub fn ord_to_term<T: AsRef<[u8]>>(fst: &Fst<T>, mut ord: u64, bytes: &mut Vec<u
 }
 
 #[inline]
-fn get_text_lines_with_automat<F, D: AsRef<[u8]>, A: Automaton>(map: &fst::Map<D>, dfa: A, mut fun: F) -> Result<(), VelociError>
+fn get_text_lines_with_automat<F, D: AsRef<[u8]>, A: Automaton>(map: &fst::Map<D>, dfa: A, cancellation_token: &CancellationToken, fun: F) -> Result<(), VelociError>
 where
     F: FnMut(String, u32),
 {
-    let stream = map.search(&dfa).into_stream();
-    let hits = stream.into_str_vec()?;
+    get_text_lines_from_stream(map.search(&dfa), cancellation_token, fun)
+}
 
-    for (term, id) in hits {
-        fun(term, id as u32);
+#[inline]
+fn get_text_lines_from_stream<F, A: Automaton>(stream_builder: fst::map::StreamBuilder<'_, A>, cancellation_token: &CancellationToken, mut fun: F) -> Result<(), VelociError>
+where
+    F: FnMut(String, u32),
+{
+    let mut stream = stream_builder.into_stream();
+    let mut num_terms = 0;
+    while let Some((term, id)) = stream.next() {
+        if num_terms % CANCELLATION_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
+            break;
+        }
+        num_terms += 1;
+        fun(String::from_utf8(term.to_vec())?, id as u32);
     }
     Ok(())
 }
 
 #[inline]
-fn get_text_lines_from_fst<F, D: AsRef<[u8]>>(options: &RequestSearchPart, map: &fst::Map<D>, fun: F) -> Result<(), VelociError>
+fn get_text_lines_from_fst<F, D: AsRef<[u8]>>(options: &RequestSearchPart, map: &fst::Map<D>, cancellation_token: &CancellationToken, mut fun: F) -> Result<(), VelociError>
 where
     F: FnMut(String, u32),
 {
-    if options.is_regex {
+    if let Some(range) = options.range.as_ref() {
+        let (lower, upper) = range.resolved_terms.as_ref().ok_or_else(|| VelociError::InvalidRequest {
+            message: format!("range {} in {:?} is only supported on typed fields", range, options.path),
+        })?;
+        let stream_builder = match lower {
+            TermBound::Included(term) => map.range().ge(term),
+            TermBound::Excluded(term) => map.range().gt(term),
+            TermBound::Unbounded => map.range(),
+        };
+        let stream_builder = match upper {
+            TermBound::Included(term) => stream_builder.le(term),
+            TermBound::Excluded(term) => stream_builder.lt(term),
+            TermBound::Unbounded => stream_builder,
+        };
+        get_text_lines_from_stream(stream_builder, cancellation_token, fun)?;
+    } else if let Some(geo_filter) = GeoFilter::from_request(options)? {
+        for geohash in geo_filter.covering_geohashes() {
+            let upper = geohash.clone() + "{"; // '{' sorts after all geohash chars
+            get_text_lines_from_stream(map.range().ge(&geohash).lt(&upper), cancellation_token, |term, id| {
+                if geo::decode_geohash(&term).map(|point| geo_filter.matches(&point)).unwrap_or(false) {
+                    fun(term, id);
+                }
+            })?;
+        }
+    } else if options.is_regex {
         use regex_automata::dense;
         let dfa = dense::Builder::new()
             .case_insensitive(options.ignore_case.unwrap_or(true))
             .build(&options.terms[0])
             .unwrap();
-        // get_text_lines_with_automat(map, dfa, fun)?;
+        // get_text_lines_with_automat(map, dfa, cancellation_token, fun)?;
         if options.starts_with {
-            get_text_lines_with_automat(map, dfa.starts_with(), fun)?;
+            get_text_lines_with_automat(map, dfa.starts_with(), cancellation_token, fun)?;
         } else {
-            get_text_lines_with_automat(map, dfa, fun)?;
+            get_text_lines_with_automat(map, dfa, cancellation_token, fun)?;
         };
     } else {
         let lev = {
@@ -89,9 +125,9 @@ where
         };
 
         if options.starts_with {
-            get_text_lines_with_automat(map, lev.starts_with(), fun)?;
+            get_text_lines_with_automat(map, lev.starts_with(), cancellation_token, fun)?;
         } else {
-            get_text_lines_with_automat(map, lev, fun)?;
+            get_text_lines_with_automat(map, lev, cancellation_token, fun)?;
         };
     }
 
@@ -113,6 +149,7 @@ fn test_get_text_lines_from_fst_regex_search() {
             ..Default::default()
         },
         &map,
+        &CancellationToken::default(),
         teh_callback,
     )
     .unwrap();
@@ -134,6 +171,7 @@ fn test_get_text_lines_from_fst_regex_search_with_starts_with() {
             ..Default::default()
         },
         &map,
+        &CancellationToken::default(),
         teh_callback,
     )
     .unwrap();
@@ -141,7 +179,7 @@ fn test_get_text_lines_from_fst_regex_search_with_starts_with() {
 }
 
 #[inline]
-fn get_text_lines<F>(persistence: &Persistence, options: &RequestSearchPart, fun: F) -> Result<(), VelociError>
+fn get_text_lines<F>(persistence: &Persistence, options: &RequestSearchPart, cancellation_token: &CancellationToken, fun: F) -> Result<(), VelociError>
 where
     F: FnMut(String, u32),
 {
@@ -151,7 +189,7 @@ where
         .get(&options.path)
         .ok_or_else(|| VelociError::FstNotFound(options.path.to_string()))?;
 
-    get_text_lines_from_fst(options, map, fun)?;
+    get_text_lines_from_fst(options, map, cancellation_token, fun)?;
     Ok(())
 }
 
@@ -197,13 +235,23 @@ pub fn suggest_multi(persistence: &Persistence, req: Request) -> Result<SuggestF
         .suggest
         .ok_or_else(|| VelociError::StringError("only suggest allowed in suggest function".to_string()))?;
 
+    let filter = match req.filter.as_ref() {
+        Some(filter) => Some(search::get_filter_anchor_ids(filter, persistence, &CancellationToken::default())?),
+        None => None,
+    };
+
     let search_results: Result<Vec<_>, VelociError> = search_parts
         .into_par_iter()
-        .map(|search_part| {
+        .map(|mut search_part| {
             // if search_part.token_value.is_none() { //Apply top skip directly if there is no token_boosting, which alters the result afterwards.
             //     search_part.top = top;
             //     search_part.skip = skip;
             // }
+            if filter.is_some() {
+                // the terms are filtered afterwards, top and skip are applied on the filtered terms
+                search_part.top = None;
+                search_part.skip = None;
+            }
             let mut search_part = PlanRequestSearchPart {
                 request: search_part,
                 get_scores: true,
@@ -211,7 +259,18 @@ pub fn suggest_multi(persistence: &Persistence, req: Request) -> Result<SuggestF
                 return_term_lowercase: true,
                 ..Default::default()
             };
-            get_term_ids_in_field(persistence, &mut search_part)
+            let mut result = get_term_ids_in_field(persistence, &mut search_part, &CancellationToken::default())?;
+            if let Some(filter) = filter.as_ref() {
+                // keep only terms, which occur in at least one document matching the filter
+                let token_to_anchor_score = persistence.get_token_to_anchor(&search_part.request.path)?;
+                result.hits_scores.retain(|hit| {
+                    token_to_anchor_score.get_score_iter(hit.id).any(|anchor| {
+                        let anchor_id = anchor.id;
+                        filter.contains(&anchor_id)
+                    })
+                });
+            }
+            Ok(result)
         })
         .collect();
     info_time!("suggest text_id result to vec/sort");
@@ -239,7 +298,7 @@ pub fn highlight(persistence: &Persistence, options: &mut RequestSearchPart) ->
         ..Default::default()
     };
 
-    let mut result = get_term_ids_in_field(persistence, &mut options)?;
+    let mut result = get_term_ids_in_field(persistence, &mut options, &CancellationToken::default())?;
     resolve_token_hits_to_text_id(persistence, &options.request, &mut result)?;
     Ok(get_text_score_id_from_result(false, &[result], options.request.skip, options.request.top))
 }
@@ -274,7 +333,7 @@ pub fn get_anchor_for_phrases_in_field(persistence: &Persistence, path: &str, te
     Ok(result)
 }
 
-pub fn get_term_ids_in_field(persistence: &Persistence, options: &mut PlanRequestSearchPart) -> Result<SearchFieldResult, VelociError> {
+pub fn get_term_ids_in_field(persistence: &Persistence, options: &mut PlanRequestSearchPart, cancellation_token: &CancellationToken) -> Result<SearchFieldResult, VelociError> {
     if !options.request.path.ends_with(TEXTINDEX) {
         options.request.path = options.request.path.add(TEXTINDEX);
     }
@@ -283,7 +342,7 @@ pub fn get_term_ids_in_field(persistence: &Persistence, options: &mut PlanReques
 
     let lower_term = options.request.terms[0].to_lowercase();
     if let Some(d) = options.request.levenshtein_distance.as_mut() {
-        *d = std::cmp::min(*d, lower_term.chars().count() as u32 - 1); //limit levenshtein distance to reasonable values
+        *d = std::cmp::min(*d, (lower_term.chars().count() as u32).saturating_sub(1)); //limit levenshtein distance to reasonable values
     }
 
     trace!("Will distance {:?}", options.request.levenshtein_distance);
@@ -314,7 +373,13 @@ pub fn get_term_ids_in_field(persistence: &Persistence, options: &mut PlanReques
                 // In the case of levenshtein != 0 or starts_with, we want prefix_matches to have a score boost - so that "awe" scores better for awesome than aber
                 let prefix_matches = should_check_prefix_match && line_lower.starts_with(&lower_term);
 
-                let score = get_default_score_for_distance(distance_dfa(&line_lower, &dfa, &lower_term), prefix_matches);
+                // all values in a range or geo filter match equally
+                let distance = if options.request.is_value_filter() {
+                    0
+                } else {
+                    distance_dfa(&line_lower, &dfa, &lower_term)
+                };
+                let score = get_default_score_for_distance(distance, prefix_matches);
                 // if let Some(boost_val) = options.request.boost {
                 //     score *= boost_val
                 // }
@@ -341,6 +406,17 @@ pub fn get_term_ids_in_field(persistence: &Persistence, options: &mut PlanReques
                             text_or_token_id: text_or_token.clone(),
                         }],
                     );
+                    result.explain_tree.insert(
+                        token_text_id,
+                        ExplainNode::new(
+                            ExplainOperation::TermMatch {
+                                term_id: token_text_id,
+                                term: text_or_token.clone(),
+                            },
+                            score,
+                            vec![],
+                        ),
+                    );
                 }
             }
 
@@ -353,13 +429,16 @@ pub fn get_term_ids_in_field(persistence: &Persistence, options: &mut PlanReques
             }
         };
 
-        get_text_lines(persistence, &options.request, teh_callback)?;
+        get_text_lines(persistence, &options.request, cancellation_token, teh_callback)?;
     }
 
     if let Some(boost_val) = options.request.boost {
         let boost_val = boost_val.into_inner();
         for hit in &mut result.hits_scores {
             hit.score *= boost_val;
+            if options.request.is_explain() {
+                wrap_explain_node(&mut result.explain_tree, hit.id, ExplainOperation::SearchPartBoost(boost_val), hit.score);
+            }
         }
     }
 
@@ -403,6 +482,7 @@ pub fn resolve_token_to_anchor(
     // filter: Option<FnvHashSet<u32>>,
     filter: &Option<Arc<FilterResult>>,
     result: &SearchFieldResult,
+    cancellation_token: &CancellationToken,
 ) -> Result<SearchFieldResult, VelociError> {
     let mut options = options.clone();
     if !options.path.ends_with(TEXTINDEX) {
@@ -412,13 +492,21 @@ pub fn resolve_token_to_anchor(
     let mut res = SearchFieldResult::new_from(&result);
     debug_time!("{} token to anchor", &options.path);
     let mut anchor_ids_hits = vec![];
+    let mut anchor_explain_nodes: FnvHashMap<u32, Vec<ExplainNode>> = FnvHashMap::default();
 
     let token_to_anchor_score = persistence.get_token_to_anchor(&options.path)?;
     {
         debug_time!("{} tokens.to_anchor_id_score", &options.path);
-        for hit in &result.hits_scores {
+        for (num_token, hit) in result.hits_scores.iter().enumerate() {
+            if num_token % CANCELLATION_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
+                break;
+            }
             let iter = token_to_anchor_score.get_score_iter(hit.id);
-            anchor_ids_hits.reserve(iter.size_hint().1.unwrap());
+            let (min_anchors, max_anchors) = iter.size_hint();
+            let num_anchors = max_anchors.unwrap_or(min_anchors);
+            // a single frequent token can have a lot of anchors, so the budget is checked for every token, before the hits are allocated
+            cancellation_token.check_additional_memory((anchor_ids_hits.len() + num_anchors) * std::mem::size_of::<search::Hit>())?;
+            anchor_ids_hits.reserve(num_anchors);
             for el in iter {
                 if should_filter(&filter, el.id) {
                     continue;
@@ -436,6 +524,23 @@ pub fn resolve_token_to_anchor(
                     if let Some(exp) = result.explain.get(&hit.id) {
                         vecco.extend_from_slice(exp);
                     }
+                    let node = ExplainNode::new(
+                        ExplainOperation::TermToAnchor {
+                            term_id: hit.id,
+                            term_score: hit.score,
+                            anchor_score: el.score.to_f32() / 100.0,
+                        },
+                        final_score,
+                        // hits resolved to text ids are explained by the tokens hitting the text
+                        result
+                            .text_id_explain_tree
+                            .get(&hit.id)
+                            .or_else(|| result.explain_tree.get(&hit.id))
+                            .cloned()
+                            .into_iter()
+                            .collect(),
+                    );
+                    anchor_explain_nodes.entry(el.id).or_insert_with(Vec::new).push(node);
                 }
                 anchor_ids_hits.push(search::Hit::new(el.id, final_score));
             }
@@ -495,6 +600,19 @@ pub fn resolve_token_to_anchor(
 
     res.hits_ids = fast_field_res_ids;
 
+    if options.is_explain() {
+        res.explain_tree = anchor_explain_nodes
+            .into_iter()
+            .map(|(anchor_id, mut nodes)| {
+                if nodes.len() == 1 {
+                    return (anchor_id, nodes.pop().unwrap());
+                }
+                let max_score = nodes.iter().map(|node| node.score).fold(f32::MIN, f32::max);
+                (anchor_id, ExplainNode::new(ExplainOperation::Max, max_score, nodes))
+            })
+            .collect();
+    }
+
     trace!("anchor id hits {:?}", anchor_ids_hits);
     res.hits_scores = anchor_ids_hits;
 
@@ -535,7 +653,7 @@ pub fn get_id_text_map_for_ids(persistence: &Persistence, path: &str, ids: &[u32
 }
 
 #[inline]
-fn should_filter(filter: &Option<Arc<FilterResult>>, id: u32) -> bool {
+pub(crate) fn should_filter(filter: &Option<Arc<FilterResult>>, id: u32) -> bool {
     filter
         .as_ref()
         .map(|filter| match **filter {
@@ -611,18 +729,22 @@ pub fn resolve_token_hits_to_text_id(
 
         for (parent_id, group) in &token_hits.iter().group_by(|el| el.0) {
             //Group by anchor
-            let (t1, t2) = group.tee();
-            let max_score = t1.max_by_key(|el| OrderedFloat(el.1.abs())).unwrap().1;
+            let group = group.collect_vec();
+            let max_score = group.iter().max_by_key(|el| OrderedFloat(el.1.abs())).unwrap().1;
 
             result.hits_scores.push(Hit::new(parent_id, max_score));
 
             if options.is_explain() {
                 // result.explain.insert(parent_id, vec![format!("max_score from token_hits score {:?}", max_score)]);
                 result.explain.insert(parent_id, vec![Explain::MaxTokenToTextId(max_score)]);
+                let inputs = group.iter().filter_map(|el| result.explain_tree.get(&el.2).cloned()).collect();
+                result
+                    .text_id_explain_tree
+                    .insert(parent_id, ExplainNode::new(ExplainOperation::MaxTokenToTextId, max_score, inputs));
             }
             if add_snippets {
                 let snippet_config = options.snippet_info.as_ref().unwrap_or(&search::DEFAULT_SNIPPETINFO);
-                let highlighted_document = highlight_document(persistence, &path, u64::from(parent_id), &t2.map(|el| el.2).collect_vec(), snippet_config)?;
+                let highlighted_document = highlight_document(persistence, &path, u64::from(parent_id), &group.iter().map(|el| el.2).collect_vec(), snippet_config)?;
                 if let Some(highlighted_document) = highlighted_document {
                     result.highlight.insert(parent_id, highlighted_document);
                 }
@@ -130,6 +130,7 @@ pub fn union_hits_score(mut or_results: Vec<SearchFieldResult>) -> SearchFieldRe
 
     let mut union_hits = Vec::with_capacity(longest_len as usize + sum_other_len as usize / 2);
     let mut explain_hits: FnvHashMap<u32, Vec<Explain>> = FnvHashMap::default();
+    let mut explain_tree: FnvHashMap<u32, ExplainNode> = FnvHashMap::default();
     if should_explain {
         for res in &or_results {
             explain_hits.extend(res.explain.clone());
@@ -188,6 +189,12 @@ pub fn union_hits_score(mut or_results: Vec<SearchFieldResult>) -> SearchFieldRe
                 let explain = explain_hits.entry(id).or_insert_with(Vec::new);
                 // explain.push(format!("or sum_over_distinct_terms {:?}", max_scores_per_term.iter().sum::<f32>()));
                 explain.push(Explain::OrSumOverDistinctTerms(max_scores_per_term.iter().sum::<f32>()));
+                let operation = ExplainOperation::UnionSum {
+                    sum_over_distinct_terms: max_scores_per_term.iter().sum::<f32>(),
+                    num_distinct_terms: num_distinct_terms as u32,
+                };
+                let inputs = or_results.iter().filter_map(|res| res.explain_tree.get(&id).cloned()).collect();
+                explain_tree.insert(id, ExplainNode::new(operation, sum_over_distinct_with_distinct_term_boost, inputs));
                 if num_distinct_terms > 1. {
                     // explain.push(format!("num_distinct_terms boost {:?} to {:?}", num_distinct_terms * num_distinct_terms, sum_over_distinct_with_distinct_term_boost));
                     // explain.push(Explain::NumDistinctTermsBoost{distinct_boost:num_distinct_terms * num_distinct_terms, new_score:sum_over_distinct_with_distinct_term_boost});
@@ -212,6 +219,7 @@ pub fn union_hits_score(mut or_results: Vec<SearchFieldResult>) -> SearchFieldRe
         term_text_in_field,
         hits_scores: union_hits,
         explain: explain_hits,
+        explain_tree,
         request: or_results[0].request.clone(), // set this to transport fields like explain
         ..Default::default()
     };
@@ -390,7 +398,9 @@ pub fn intersect_hits_score(mut and_results: Vec<SearchFieldResult>) -> SearchFi
     for res in &mut and_results {
         res.hits_scores.sort_unstable_by_key(|el| el.id); //TODO ALSO DEDUP???
     }
-    let mut shortest_result = and_results.swap_remove(index_shortest).hits_scores;
+    let shortest = and_results.swap_remove(index_shortest);
+    let shortest_explain_tree = shortest.explain_tree;
+    let mut shortest_result = shortest.hits_scores;
 
     // let mut iterators = &and_results.iter().map(|el| el.hits_scores.iter()).collect::<Vec<_>>();
 
@@ -419,6 +429,7 @@ pub fn intersect_hits_score(mut and_results: Vec<SearchFieldResult>) -> SearchFi
         }
     }
     let mut explain_hits = FnvHashMap::default();
+    let mut explain_tree = FnvHashMap::default();
     if should_explain {
         for hit in intersected_hits.iter() {
             for res in and_results.iter() {
@@ -427,6 +438,11 @@ pub fn intersect_hits_score(mut and_results: Vec<SearchFieldResult>) -> SearchFi
                     explain.extend_from_slice(exp);
                 }
             }
+            let inputs = std::iter::once(&shortest_explain_tree)
+                .chain(and_results.iter().map(|res| &res.explain_tree))
+                .filter_map(|tree| tree.get(&hit.id).cloned())
+                .collect();
+            explain_tree.insert(hit.id, ExplainNode::new(ExplainOperation::Intersection, hit.score, inputs));
         }
     }
 
@@ -435,6 +451,7 @@ pub fn intersect_hits_score(mut and_results: Vec<SearchFieldResult>) -> SearchFi
         term_id_hits_in_field,
         term_text_in_field,
         explain: explain_hits,
+        explain_tree,
         hits_scores: intersected_hits,
         request: and_results[0].request.clone(), // set this to transport fields like explain TODO FIX - ALL AND TERMS should be reflected
         ..Default::default()
@@ -598,6 +615,7 @@ mod bench_intersect {
                     hits_scores: hits2.clone(),
                     ..Default::default()
                 }],
+                ExplainOperation::TermBoost,
             )
         })
     }
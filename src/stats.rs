@@ -0,0 +1,180 @@
+use crate::{
+    error::VelociError,
+    indices::metadata::*,
+    metadata::FieldType,
+    persistence::{Persistence, TEXTINDEX},
+    search::exists::check_field,
+    util::{get_file_path, StringAdd},
+};
+use fst::Streamer;
+use std::{cmp::Reverse, collections::BinaryHeap, fs};
+
+/// Statistics of an index, as returned by `Persistence::get_stats`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PersistenceStats {
+    pub num_docs: u64,
+    pub bytes_indexed: u64,
+    /// Size of the document store `data` and `data.offsets`
+    pub doc_store_size: u64,
+    /// Sum of the sizes of all fsts and indices
+    pub indices_size: u64,
+    /// Sorted by field name
+    pub fields: Vec<FieldStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldStats {
+    pub name: String,
+    pub field_type: FieldType,
+    /// Number of terms in the fst, 0 for fields without fst
+    pub num_terms: u64,
+    pub fst_size: u64,
+    pub num_text_ids: usize,
+    /// Texts longer than `do_not_store_text_longer_than`, which are not in the fst
+    pub num_long_text_ids: usize,
+    pub indices: Vec<IndexStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IndexStats {
+    pub path: String,
+    pub index_category: IndexCategory,
+    pub index_cardinality: IndexCardinality,
+    /// The loading type in the metadata, without the override of `load_with_loading_type` or the env
+    pub loading_type: LoadingType,
+    pub is_empty: bool,
+    /// Size on disk of all files of the index
+    pub size: u64,
+    pub metadata: IndexValuesMetadata,
+}
+
+/// A term of a field with the number of anchors (documents) containing it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TermFrequency {
+    pub term: String,
+    pub num_anchors: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TermPostings {
+    pub term: String,
+    pub term_id: u32,
+    pub postings: Vec<TermPosting>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TermPosting {
+    pub anchor_id: u32,
+    /// Score of the term in the anchor, as used in the search
+    pub score: f32,
+}
+
+impl Persistence {
+    pub fn get_stats(&self) -> Result<PersistenceStats, VelociError> {
+        let mut fields = self
+            .metadata
+            .columns
+            .iter()
+            .map(|(name, field_info)| {
+                let (num_terms, fst_size) = match self.indices.fst.get(&name.add(TEXTINDEX)) {
+                    Some(map) => (map.len() as u64, map.as_fst().size() as u64),
+                    None => (0, 0),
+                };
+                let indices = field_info
+                    .indices
+                    .iter()
+                    .map(|index| IndexStats {
+                        path: index.path.to_string(),
+                        index_category: index.index_category,
+                        index_cardinality: index.index_cardinality.clone(),
+                        loading_type: index.loading_type,
+                        is_empty: index.is_empty,
+                        size: self.get_index_file_size(&index.path),
+                        metadata: index.metadata,
+                    })
+                    .collect();
+                FieldStats {
+                    name: name.to_string(),
+                    field_type: field_info.field_type,
+                    num_terms,
+                    fst_size,
+                    num_text_ids: field_info.textindex_metadata.num_text_ids,
+                    num_long_text_ids: field_info.textindex_metadata.num_long_text_ids,
+                    indices,
+                }
+            })
+            .collect::<Vec<_>>();
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let indices_size = fields.iter().map(|field| field.fst_size + field.indices.iter().map(|index| index.size).sum::<u64>()).sum();
+        Ok(PersistenceStats {
+            num_docs: self.get_number_of_documents(),
+            bytes_indexed: self.get_bytes_indexed(),
+            doc_store_size: self.get_file_size("data") + self.get_file_size("data.offsets"),
+            indices_size,
+            fields,
+        })
+    }
+
+    /// The `top` terms of a field with the most anchors, sorted descending by the number of anchors
+    pub fn get_top_terms(&self, field: &str, top: usize) -> Result<Vec<TermFrequency>, VelociError> {
+        check_field(self, field)?;
+        let path = field.add(TEXTINDEX);
+        let map = self.indices.fst.get(&path).ok_or_else(|| VelociError::FstNotFound(field.to_string()))?;
+        let token_to_anchor = self.get_token_to_anchor(&path)?;
+
+        // min heap of the current top terms, ties are resolved in favor of the smaller term
+        let mut heap = BinaryHeap::with_capacity(top + 1);
+        let mut stream = map.stream();
+        while let Some((term, term_id)) = stream.next() {
+            let num_anchors = token_to_anchor.get_score_iter(term_id as u32).count() as u32;
+            heap.push(Reverse((num_anchors, Reverse(term.to_vec()))));
+            if heap.len() > top {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((num_anchors, Reverse(term)))| {
+                Ok(TermFrequency {
+                    term: String::from_utf8(term)?,
+                    num_anchors,
+                })
+            })
+            .collect()
+    }
+
+    /// The anchors containing the exact term with their scores, `None` if the term is not in the field
+    pub fn get_term_postings(&self, field: &str, term: &str) -> Result<Option<TermPostings>, VelociError> {
+        check_field(self, field)?;
+        let path = field.add(TEXTINDEX);
+        let map = self.indices.fst.get(&path).ok_or_else(|| VelociError::FstNotFound(field.to_string()))?;
+        let term_id = match map.get(term) {
+            Some(term_id) => term_id as u32,
+            None => return Ok(None),
+        };
+        let postings = self
+            .get_token_to_anchor(&path)?
+            .get_score_iter(term_id)
+            .map(|anchor_score| TermPosting {
+                anchor_id: anchor_score.id,
+                score: anchor_score.score.to_f32() / 100.0,
+            })
+            .collect();
+        Ok(Some(TermPostings {
+            term: term.to_string(),
+            term_id,
+            postings,
+        }))
+    }
+
+    /// Sum of the sizes of the files of an index, depending on the index type these are `path`, `path.indirect` and `path.data`
+    pub(crate) fn get_index_file_size(&self, path: &str) -> u64 {
+        ["", ".indirect", ".data"].iter().map(|ext| self.get_file_size(&(path.to_string() + ext))).sum()
+    }
+
+    /// Size of a file in the index folder, 0 if it does not exist
+    pub(crate) fn get_file_size(&self, path: &str) -> u64 {
+        fs::metadata(get_file_path(&self.db, path)).map(|metadata| metadata.len()).unwrap_or(0)
+    }
+}
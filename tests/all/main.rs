@@ -17,6 +17,12 @@ mod test_query_generator;
 mod test_scores;
 mod test_why_found;
 mod tests;
+mod tests_date;
+mod tests_exists;
 mod tests_facet;
+mod tests_geo;
 mod tests_large;
 mod tests_minimal;
+mod tests_nested;
+mod tests_stats;
+mod tests_typed_fields;
@@ -128,6 +128,9 @@ fn token_code_search_disable_parser_query_generator() {
         no_parentheses: true,
         no_attributes: true,
         no_levensthein: true,
+        no_wildcards: false,
+        no_boost: true,
+        lenient: false,
     });
     params.search_term = "*myfun(param1: Type1)*".to_string();
 
@@ -319,6 +319,30 @@ fn simple_search_wildcard_starts_with() {
     assert_eq!(hits.len(), 3);
 }
 
+#[test]
+fn lone_wildcard_matches_all_values_of_field() {
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "ent_seq:*".to_string();
+    params.top = Some(100);
+
+    let requesto = query_generator::search_query(&TEST_PERSISTENCE, params.clone()).unwrap();
+    match requesto.search_req {
+        Some(search::SearchRequest::Exists(ref exists)) => assert_eq!(exists.path, "ent_seq"),
+        ref other => panic!("expected exists query, got {:?}", other),
+    }
+    let with_ent_seq = search_testo_to_doco_qp!(params.clone()).data;
+    assert!(!with_ent_seq.is_empty());
+    params.search_term = "_exists_:ent_seq".to_string();
+    assert_eq!(search_testo_to_doco_qp!(params.clone()).data.len(), with_ent_seq.len());
+
+    params.search_term = r#"ent_seq:"*""#.to_string();
+    assert_eq!(search_testo_to_doco_qp!(params.clone()).data.len(), with_ent_seq.len());
+
+    // without field all fields are searched
+    params.search_term = "*".to_string();
+    assert_eq!(search_testo_to_doco_qp!(params).data.len() as u64, TEST_PERSISTENCE.get_number_of_documents());
+}
+
 #[test]
 fn simple_search_wildcard_starts_with_with_levenshtein() {
     let mut params = query_generator::SearchQueryGeneratorParameters::default();
@@ -348,6 +372,75 @@ fn contains_search_with_regex() {
     assert_eq!(hits.len(), 1);
 }
 
+#[test]
+fn query_boost_changes_order() {
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "ent_seq:1337^10 OR ent_seq:1587690".to_string();
+
+    let hits = search_testo_to_doco_qp!(params.clone()).data;
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].doc["ent_seq"], "1337");
+
+    params.search_term = "ent_seq:1337 OR ent_seq:(1587690)^10".to_string();
+    let hits = search_testo_to_doco_qp!(params).data;
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].doc["ent_seq"], "1587690");
+}
+
+#[test]
+fn invalid_query_returns_parse_error() {
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "(awesome AND ".to_string();
+
+    let requesto = query_generator::search_query(&TEST_PERSISTENCE, params);
+    assert_contains!(requesto.unwrap_err().to_string(), "Unexpected end of query at position 13");
+}
+
+#[test]
+fn invalid_query_lenient() {
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "(awesome AND ".to_string();
+    params.parser_options = Some(query_generator::QueryParserOptions {
+        lenient: true,
+        ..Default::default()
+    });
+
+    let hits = search_testo_to_doco_qp!(params).data;
+    assert_eq!(hits.len(), 1);
+}
+
+#[test]
+fn single_char_wildcard_search() {
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "tags[]:awe?ome".to_string();
+
+    let hits = search_testo_to_doco_qp!(params.clone()).data;
+    assert_eq!(hits.len(), 1);
+
+    // ? matches exactly one character
+    params.search_term = "tags[]:awe?some".to_string();
+    let hits = search_testo_to_doco_qp!(params.clone()).data;
+    assert_eq!(hits.len(), 0);
+
+    params.search_term = "tags[]:awe?o*".to_string();
+    let hits = search_testo_to_doco_qp!(params).data;
+    assert_eq!(hits.len(), 1);
+}
+
+#[test]
+fn wildcards_disabled() {
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "tags[]:*wesome".to_string();
+    params.levenshtein = Some(0);
+    params.parser_options = Some(query_generator::QueryParserOptions {
+        no_wildcards: true,
+        ..Default::default()
+    });
+
+    let hits = search_testo_to_doco_qp!(params).data;
+    assert_eq!(hits.len(), 0);
+}
+
 /// There is no levenshtein with regex searches
 #[test]
 fn contains_search_has_no_levenshtein() {
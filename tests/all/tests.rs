@@ -394,6 +394,47 @@ fn or_query_explained() {
     assert_eq!(hits[0].explain.as_ref().unwrap().len(), 5);
 }
 
+#[test]
+fn or_query_explain_tree() {
+    let req = json!({
+        "search_req": {
+            "or":{
+                "queries": [
+                    {"search": {
+                        "terms":["begeistern"],
+                        "path": "meanings.ger[]"
+                    }},
+                    {"search": {
+                        "terms":["urge"],
+                        "path": "meanings.eng[]"
+                    }}
+                ]
+            }
+        },
+        "explain":true
+    });
+    let hits = search_testo_to_doc!(req).data;
+    assert_eq!(hits.len(), 1);
+    let tree = hits[0].explain_tree.as_ref().unwrap();
+    assert_eq!(tree.score, hits[0].hit.score);
+    match tree.operation {
+        search::ExplainOperation::UnionSum { num_distinct_terms, .. } => assert_eq!(num_distinct_terms, 2),
+        _ => panic!("expected union on top of the explain tree, got {:?}", tree.operation),
+    }
+    assert_eq!(tree.inputs.len(), 2);
+    for input in &tree.inputs {
+        match input.operation {
+            search::ExplainOperation::TermToAnchor { .. } => {}
+            _ => panic!("expected term to anchor, got {:?}", input.operation),
+        }
+    }
+
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).unwrap();
+    let explained = search::explain_hit(requesto.clone(), hits[0].hit.id, &TEST_PERSISTENCE).unwrap();
+    assert_eq!(explained.as_ref(), Some(tree));
+    assert_eq!(search::explain_hit(requesto, 12345, &TEST_PERSISTENCE).unwrap(), None);
+}
+
 #[test]
 fn test_float() {
     let req = json!({
@@ -765,6 +806,40 @@ fn or_connect_hits_with_filter_reuse_query() {
     assert_eq!(hits.len(), 1);
 }
 
+#[test]
+fn add_filter_intersects_with_filter_of_request() {
+    let req = json!({
+        "search_req": {"or":{ "queries": [
+            {"search": {
+                "terms":["majestät"],
+                "path": "meanings.ger[]"
+            }},
+            {"search": {
+                "terms":["urge"],
+                "path": "meanings.eng[]"
+            }}
+        ]}},
+        "filter":{
+            "search": {
+                "terms":["urge"],
+                "path": "meanings.eng[]"
+            }
+        }
+    });
+
+    let mut requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    let pers = &TEST_PERSISTENCE;
+    requesto.add_filter(query_generator::filter_query(&pers, "ent_seq:1587680", None).unwrap());
+    let hits = search::search(requesto, &pers).unwrap();
+    assert_eq!(hits.num_hits, 0);
+
+    let mut requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    requesto.add_filter(query_generator::filter_query(&pers, "ent_seq:1587690", None).unwrap());
+    let hits = search::to_search_result(&pers, search::search(requesto, &pers).unwrap(), &None).data;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].doc["ent_seq"], "1587690");
+}
+
 #[test]
 fn should_find_2_values_from_token() {
     let req = json!({
@@ -819,6 +894,63 @@ fn should_search_and_double_boost() {
     assert_eq!(hits.len(), 2);
 }
 
+#[test]
+fn should_search_and_boost_with_expression_on_doc_values() {
+    let req = json!({
+        "search_req": { "search": {
+            "terms":["意慾"],
+            "path": "kanji[].text",
+            "levenshtein_distance": 0,
+            "firstCharExactMatch":true
+        }},
+        "boost" : [{
+            "path":"commonness",
+            "expression": "max(log(doc['commonness'] + 1), 1) * (2 - 1)"
+        }]
+    });
+
+    let hits = search_testo_to_doc!(req).data;
+    assert_eq!(hits[0].doc["commonness"], 500);
+}
+
+#[test]
+fn should_search_and_boost_with_decay_near_origin() {
+    let req = json!({
+        "search_req": { "search": {
+            "terms":["意慾"],
+            "path": "kanji[].text",
+            "levenshtein_distance": 0,
+            "firstCharExactMatch":true
+        }},
+        "boost" : [{
+            "path":"commonness",
+            "boost_fun": {"Gauss": {"origin": 20, "scale": 10, "decay": 0.5}}
+        }]
+    });
+
+    let hits = search_testo_to_doc!(req).data;
+    assert_eq!(hits[0].doc["commonness"], 20);
+}
+
+#[test]
+fn should_return_invalid_request_for_invalid_boost_expression() {
+    let req = json!({
+        "search_req": { "search": {
+            "terms":["awesome"],
+            "path": "field1[].text"
+        }},
+        "boost" : [{
+            "path":"commonness",
+            "expression": "log($SCORE"
+        }]
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    match search::search(requesto, &TEST_PERSISTENCE) {
+        Err(error::VelociError::InvalidRequest { message }) => assert!(message.contains("log($SCORE")),
+        other => panic!("expected invalid request error, got {:?}", other),
+    }
+}
+
 #[test]
 fn should_search_and_boost_anchor() {
     let req = json!({
@@ -915,7 +1047,7 @@ fn should_use_search_on_field_for_suggest_without_sorting_etc() {
         return_term: true,
         ..Default::default()
     };
-    let results = search_field::get_term_ids_in_field(&pers, &mut requesto).unwrap();
+    let results = search_field::get_term_ids_in_field(&pers, &mut requesto, &search::CancellationToken::default()).unwrap();
 
     let mut all_terms = results.terms.values().collect::<Vec<&String>>();
     all_terms.sort();
@@ -968,6 +1100,55 @@ fn should_highlight_on_field() {
     );
 }
 
+#[test]
+fn should_not_panic_on_empty_term_with_levenshtein() {
+    let req = json!({
+        "search_req": { "search": {
+            "terms":[""],
+            "path": "meanings.ger[]",
+            "levenshtein_distance": 1
+        }}
+    });
+    assert_eq!(search_testo_to_doc!(req).data.len(), 0);
+}
+
+#[test]
+fn should_keep_text_id_explain_tree_separate_from_term_hits() {
+    // "text localität" is hit as term and as text containing the hit term "text", both have the same id
+    let req = json!({
+        "terms":["text"],
+        "path": "meanings.ger[]",
+        "levenshtein_distance": 0,
+        "starts_with": true,
+        "options": {"explain": true}
+    });
+    let requesto: search::RequestSearchPart = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    let mut options = plan_creator::execution_plan::PlanRequestSearchPart {
+        request: requesto,
+        get_scores: true,
+        ..Default::default()
+    };
+    let pers = &TEST_PERSISTENCE;
+    let mut result = search_field::get_term_ids_in_field(&pers, &mut options, &search::CancellationToken::default()).unwrap();
+    let text_term_id = pers.indices.fst["meanings.ger[].textindex"].get("text").unwrap() as u32;
+    let text_id = pers.indices.fst["meanings.ger[].textindex"].get("text localität").unwrap() as u32;
+    search_field::resolve_token_hits_to_text_id(&pers, &options.request, &mut result).unwrap();
+
+    assert!(matches!(result.explain_tree[&text_id].operation, search::ExplainOperation::TermMatch { .. }));
+    let text_id_node = &result.text_id_explain_tree[&text_id];
+    assert_eq!(text_id_node.operation, search::ExplainOperation::MaxTokenToTextId);
+    assert_eq!(text_id_node.inputs, vec![result.explain_tree[&text_term_id].clone()]);
+
+    // the anchors of the text are explained by the tokens hitting the text
+    result.hits_scores = vec![search::Hit::new(text_id, text_id_node.score)];
+    let anchor_result = search_field::resolve_token_to_anchor(&pers, &options.request, &None, &result, &search::CancellationToken::default()).unwrap();
+    assert!(!anchor_result.explain_tree.is_empty());
+    for node in anchor_result.explain_tree.values() {
+        assert!(matches!(node.operation, search::ExplainOperation::TermToAnchor { .. }));
+        assert_eq!(node.inputs, vec![text_id_node.clone()]);
+    }
+}
+
 #[test]
 fn should_highlight_on_1_n_field() {
     let req = json!({
@@ -1073,6 +1254,29 @@ fn multi_real_suggest_with_score() {
     assert_eq!(results.iter().map(|el| el.0.clone()).collect::<Vec<String>>(), ["will", "wille", "wille (m)", "will testo"]);
 }
 
+#[test]
+fn multi_real_suggest_with_filter() {
+    let req = json!({
+        "suggest" : [
+            {"terms":["will"], "path": "meanings.eng[]", "levenshtein_distance": 0, "starts_with":true}
+        ],
+        "filter":{
+            "search": {
+                "terms":["1587690"],
+                "path": "ent_seq"
+            }
+        },
+        "top":10,
+        "skip":0
+    });
+
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    let pers = &TEST_PERSISTENCE;
+    let results = search_field::suggest_multi(&pers, requesto).unwrap();
+    // "will testo" is only in 1587680
+    assert_eq!(results.iter().map(|el| el.0.clone()).collect::<Vec<String>>(), ["will"]);
+}
+
 #[test]
 fn real_suggest_with_boosting_score_of_begeisterung_and_token_value() {
     let req = json!({
@@ -1171,6 +1375,163 @@ fn should_check_explain_plan_contents() {
     assert_contains!(explain, "boost");
 }
 
+#[test]
+fn should_check_explain_plan_contains_estimates() {
+    let req = json!({
+        "search_req": {"and": {"queries": [
+            {"search": {"terms":["will"], "path": "meanings.eng[]", "starts_with":true}},
+            {"search": {"terms":["urge"], "path": "meanings.eng[]"}}
+        ]}}
+    });
+
+    let explain = search_testo_to_explain!(req).to_lowercase();
+    assert_contains!(explain, "intersect");
+    assert_contains!(explain, "est. terms");
+    assert_contains!(explain, "est. hits");
+}
+
+#[test]
+fn should_return_timeout_error_or_partial_result() {
+    let req = json!({
+        "search_req": {"search": {"terms":["urge"], "path": "meanings.eng[]"}},
+        "timeout_ms": 0
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    match search::search(requesto, &TEST_PERSISTENCE) {
+        Err(error::VelociError::Timeout { timeout_ms }) => assert_eq!(timeout_ms, 0),
+        other => panic!("expected timeout error, got {:?}", other),
+    }
+
+    let req = json!({
+        "search_req": {"search": {"terms":["urge"], "path": "meanings.eng[]"}},
+        "timeout_ms": 0,
+        "partial_results_on_timeout": true
+    });
+    let res = search_testo_to_doc!(req);
+    assert!(res.timed_out);
+
+    let req = json!({
+        "search_req": {"search": {"terms":["urge"], "path": "meanings.eng[]"}},
+        "timeout_ms": 60_000
+    });
+    let res = search_testo_to_doc!(req);
+    assert!(!res.timed_out);
+    assert_eq!(res.data[0].doc["ent_seq"], "1587690");
+}
+
+#[test]
+fn should_abort_search_exceeding_max_memory() {
+    let req = json!({
+        "search_req": {"search": {"terms":["urge"], "path": "meanings.eng[]"}},
+        "max_memory": 1
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    match search::search(requesto, &TEST_PERSISTENCE) {
+        Err(error::VelociError::MemoryLimitExceeded { max_memory, .. }) => assert_eq!(max_memory, 1),
+        other => panic!("expected memory limit error, got {:?}", other),
+    }
+
+    let req = json!({
+        "search_req": {"search": {"terms":["urge"], "path": "meanings.eng[]"}},
+        "max_memory": 1_000_000
+    });
+    let res = search_testo_to_doc!(req);
+    assert_eq!(res.data[0].doc["ent_seq"], "1587690");
+}
+
+#[test]
+fn should_profile_plan_steps() {
+    let req = json!({
+        "search_req": {"or": {"queries": [
+            {"search": {"terms":["begeistern"], "path": "meanings.ger[]"}},
+            {"search": {"terms":["urge"], "path": "meanings.eng[]"}}
+        ]}},
+        "profile": true
+    });
+
+    let res = search_testo_to_doc!(req);
+    let profile = res.profile.expect("profile missing");
+    assert_eq!(profile.step_type, "Union");
+    assert_eq!(profile.num_output_hits, 1);
+    assert_eq!(profile.inputs.len(), 2);
+    assert_eq!(profile.num_input_hits, 2);
+    for input in &profile.inputs {
+        assert_eq!(input.step_type, "ResolveTokenIdToAnchor");
+        assert_eq!(input.num_output_hits, 1);
+        assert_eq!(input.inputs[0].step_type, "PlanStepFieldSearchToTokenIds");
+        assert_eq!(input.inputs[0].field, input.field);
+    }
+
+    let req = json!({
+        "search_req": {"search": {"terms":["urge"], "path": "meanings.eng[]"}}
+    });
+    assert_eq!(search_testo_to_doc!(req).profile, None);
+}
+
+fn collect_profiled_steps<'a>(profile: &'a veloci::plan_creator::profile::PlanStepProfile, steps: &mut Vec<&'a veloci::plan_creator::profile::PlanStepProfile>) {
+    if !steps.iter().any(|step| step.step_id == profile.step_id) {
+        steps.push(profile);
+    }
+    for input in &profile.inputs {
+        collect_profiled_steps(input, steps);
+    }
+}
+
+#[test]
+fn should_count_each_hit_once_in_profile() {
+    // the field search of "text" is shared with the phrase boost, which needs the term ids in addition to the scores
+    let req = json!({
+        "search_req": {"search": {"terms":["text"], "path": "meanings.ger[]"}},
+        "phrase_boosts": [{
+            "path":"meanings.ger[]",
+            "search1":{"terms":["text"], "path": "meanings.ger[]"},
+            "search2":{"terms":["localität"], "path": "meanings.ger[]"}
+        }],
+        "profile": true
+    });
+    let res = search_testo_to_doc!(req);
+    let profile = res.profile.expect("profile missing");
+    assert_eq!(profile.num_output_hits as u64, res.num_hits);
+
+    let num_term_hits = |term: &str| {
+        let mut options = plan_creator::execution_plan::PlanRequestSearchPart {
+            request: serde_json::from_value(json!({"terms":[term], "path": "meanings.ger[]"})).unwrap(),
+            get_scores: true,
+            ..Default::default()
+        };
+        search_field::get_term_ids_in_field(&TEST_PERSISTENCE, &mut options, &search::CancellationToken::default())
+            .unwrap()
+            .hits_scores
+            .len()
+    };
+    let mut steps = vec![];
+    collect_profiled_steps(&profile, &mut steps);
+    let mut field_search_hits: Vec<_> = steps
+        .iter()
+        .filter(|step| step.step_type == "PlanStepFieldSearchToTokenIds")
+        .map(|step| step.num_output_hits)
+        .collect();
+    field_search_hits.sort();
+    let mut expected = vec![num_term_hits("text"), num_term_hits("localität")];
+    expected.sort();
+    assert_eq!(field_search_hits, expected);
+}
+
+#[test]
+fn and_connect_hits_broad_and_selective_subquery() {
+    let req = json!({
+        "and":{
+            "queries":[
+            {"search": {"terms":["w"], "path": "meanings.eng[]", "starts_with":true}},
+            {"search": {"terms":["urge"], "path": "meanings.eng[]"}}
+        ]}
+    });
+
+    let hits = search_request_json_to_doc!(req).data;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].doc["ent_seq"], "1587690");
+}
+
 #[test]
 fn should_boost_terms_and_from_cache() {
     let req = json!({
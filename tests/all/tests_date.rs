@@ -0,0 +1,134 @@
+use serde_json::Value;
+use veloci::*;
+
+use super::common;
+
+static TEST_FOLDER: &str = "dateTest";
+lazy_static! {
+    static ref TEST_PERSISTENCE: persistence::Persistence = {
+        let indices = r#"
+        ["*GLOBAL*"]
+        features = ['All']
+
+        ["created"]
+        field_type = "date"
+        date_formats = ["rfc3339", "%Y-%m-%d", "%d.%m.%Y"]
+        facet = true
+
+        "#;
+        common::create_test_persistence(TEST_FOLDER, indices, get_test_data().to_string().as_bytes(), None)
+    };
+}
+
+pub fn get_test_data() -> Value {
+    json!([
+        {
+            "title": "old news",
+            "created": "2018-12-24"
+        },
+        {
+            "title": "news from january",
+            "created": "2019-01-15T10:00:00Z"
+        },
+        {
+            "title": "other news from january",
+            "created": "31.01.2019"
+        },
+        {
+            "title": "news from february",
+            "created": "2019-02-01T00:00:00+01:00"
+        },
+        {
+            "title": "news from the future",
+            "created": "2999-01-01"
+        }
+    ])
+}
+
+fn get_titles(hits: &[search::DocWithHit]) -> Vec<String> {
+    let mut titles: Vec<String> = hits.iter().map(|hit| hit.doc["title"].as_str().unwrap().to_string()).collect();
+    titles.sort();
+    titles
+}
+
+#[test]
+fn should_search_date_range() {
+    let req = json!({
+        "search_req": {"search": {"path": "created", "range": {"gte": "2019-01-01", "lt": "2019-02-01"}}}
+    });
+
+    let hits = search_testo_to_doc!(req).data;
+    // 2019-02-01T00:00:00+01:00 is still in january in UTC
+    assert_eq!(get_titles(&hits), vec!["news from february", "news from january", "other news from january"]);
+}
+
+#[test]
+fn should_search_date_range_with_date_math() {
+    let req = json!({
+        "search_req": {"search": {"path": "created", "range": {"gt": "now"}}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["news from the future"]);
+
+    let req = json!({
+        "search_req": {"search": {"path": "created", "range": {"lte": "2019-01-01||-1d/d"}}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["old news"]);
+}
+
+#[test]
+fn should_search_date_term_in_any_format() {
+    let req = json!({
+        "search_req": {"search": {"path": "created", "terms": ["2019-01-31"]}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["other news from january"]);
+}
+
+#[test]
+fn should_filter_search_with_date_range() {
+    let req = json!({
+        "search_req": {"search": {"path": "title", "terms": ["news"]}},
+        "filter": {"search": {"path": "created", "range": {"gte": "2019-01-01", "lte": "2019-12-31"}}}
+    });
+    assert_eq!(
+        get_titles(&search_testo_to_doc!(req).data),
+        vec!["news from february", "news from january", "other news from january"]
+    );
+}
+
+#[test]
+fn should_return_error_for_range_on_text_field() {
+    let req = json!({
+        "search_req": {"search": {"path": "title", "range": {"gte": "2019-01-01"}}}
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    assert!(search::search(requesto, &TEST_PERSISTENCE).is_err());
+}
+
+#[test]
+fn should_get_date_histogram_facet() {
+    let req = json!({
+        "search_req": {"search": {"path": "title", "terms": ["news"]}},
+        "facets": [{"field": "created", "date_histogram": "month"}]
+    });
+
+    let facets = search_testo_to_doc!(req).facets.unwrap();
+    assert_eq!(
+        facets.get("created").unwrap(),
+        &vec![
+            ("2018-12-01T00:00:00+00:00".to_string(), 1),
+            ("2019-01-01T00:00:00+00:00".to_string(), 3),
+            ("2999-01-01T00:00:00+00:00".to_string(), 1)
+        ]
+    );
+}
+
+#[test]
+fn should_get_date_facet_as_rfc3339() {
+    let req = json!({
+        "search_req": {"search": {"path": "title", "terms": ["old"]}},
+        "facets": [{"field": "created"}]
+    });
+
+    let facets = search_testo_to_doc!(req).facets.unwrap();
+    assert_eq!(facets.get("created").unwrap(), &vec![("2018-12-24T00:00:00+00:00".to_string(), 1)]);
+}
@@ -0,0 +1,127 @@
+use serde_json::Value;
+use veloci::*;
+
+use super::common;
+
+static TEST_FOLDER: &str = "existsTest";
+lazy_static! {
+    static ref TEST_PERSISTENCE: persistence::Persistence = {
+        let indices = r#"
+        ["*GLOBAL*"]
+        features = ['All']
+
+        ["tags[]"]
+        facet = true
+
+        "#;
+        common::create_test_persistence(TEST_FOLDER, indices, get_test_data().to_string().as_bytes(), None)
+    };
+}
+
+pub fn get_test_data() -> Value {
+    json!([
+        {
+            "name": "shirt",
+            "ean": "4006381333931",
+            "tags": ["cotton"],
+            "variants": [{"size": "XL", "stores": [{"city": "berlin"}]}]
+        },
+        {
+            "name": "sweater",
+            "variants": [{"color": "red"}]
+        },
+        {
+            "name": "jacket",
+            "ean": "4006381333948",
+            "tags": []
+        }
+    ])
+}
+
+fn get_names(hits: &[search::DocWithHit]) -> Vec<String> {
+    let mut names: Vec<String> = hits.iter().map(|hit| hit.doc["name"].as_str().unwrap().to_string()).collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn should_search_exists_and_missing() {
+    let req = json!({ "search_req": {"exists": {"path": "ean"}} });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["jacket", "shirt"]);
+
+    let req = json!({ "search_req": {"missing": {"path": "ean"}} });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["sweater"]);
+}
+
+#[test]
+fn should_search_exists_in_arrays() {
+    let req = json!({ "search_req": {"exists": {"path": "tags[]"}} });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["shirt"]);
+
+    let req = json!({ "search_req": {"exists": {"path": "variants[].size"}} });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["shirt"]);
+
+    let req = json!({ "search_req": {"missing": {"path": "variants[].size"}} });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["jacket", "sweater"]);
+}
+
+#[test]
+fn should_search_exists_in_nested_arrays() {
+    let req = json!({ "search_req": {"exists": {"path": "variants[].stores[].city"}} });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["shirt"]);
+
+    let req = json!({ "search_req": {"missing": {"path": "variants[].stores[].city"}} });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["jacket", "sweater"]);
+}
+
+#[test]
+fn should_estimate_exists_and_missing_from_the_index() {
+    let req = json!({ "search_req": {"exists": {"path": "ean"}} });
+    assert_contains!(search_testo_to_explain!(req), "est. hits 2");
+
+    let req = json!({ "search_req": {"missing": {"path": "ean"}} });
+    assert_contains!(search_testo_to_explain!(req), "est. hits 1");
+}
+
+#[test]
+fn should_filter_with_exists_and_missing() {
+    let req = json!({
+        "search_req": {"or": {"queries": [
+            {"search": {"path": "name", "terms": ["shirt"]}},
+            {"search": {"path": "name", "terms": ["sweater"]}}
+        ]}},
+        "filter": {"missing": {"path": "ean"}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["sweater"]);
+
+    let req = json!({
+        "search_req": {"and": {"queries": [
+            {"search": {"path": "name", "terms": ["shirt"]}},
+            {"exists": {"path": "ean"}}
+        ]}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["shirt"]);
+}
+
+#[test]
+fn should_search_exists_with_query_generator() {
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "_exists_:ean".to_string();
+    assert_eq!(get_names(&search_testo_to_doco_qp!(params).data), vec!["jacket", "shirt"]);
+
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "sweater OR shirt".to_string();
+    params.filter = Some("_exists_:\"variants[].color\"".to_string());
+    assert_eq!(get_names(&search_testo_to_doco_qp!(params).data), vec!["sweater"]);
+}
+
+#[test]
+fn should_return_error_for_exists_on_unknown_field() {
+    let req = json!({ "search_req": {"exists": {"path": "gtin"}} });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    assert!(search::search(requesto, &TEST_PERSISTENCE).is_err());
+
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "_exists_:gtin".to_string();
+    assert!(query_generator::search_query(&TEST_PERSISTENCE, params).is_err());
+}
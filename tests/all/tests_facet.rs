@@ -107,6 +107,7 @@ fn facets() {
         &search::FacetRequest {
             field: "tags[]".to_string(),
             top: Some(10),
+            ..Default::default()
         },
         &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
     )
@@ -0,0 +1,135 @@
+use serde_json::Value;
+use veloci::*;
+
+use super::common;
+
+static TEST_FOLDER: &str = "geoTest";
+lazy_static! {
+    static ref TEST_PERSISTENCE: persistence::Persistence = {
+        let indices = r#"
+        ["*GLOBAL*"]
+        features = ['All']
+
+        ["location"]
+        field_type = "geo_point"
+
+        "#;
+        common::create_test_persistence(TEST_FOLDER, indices, get_test_data().to_string().as_bytes(), None)
+    };
+}
+
+pub fn get_test_data() -> Value {
+    json!([
+        {
+            "name": "store alexanderplatz",
+            "location": {"lat": 52.5219, "lon": 13.4132}
+        },
+        {
+            "name": "store brandenburger tor",
+            "location": "52.5163,13.3777"
+        },
+        {
+            "name": "store potsdam",
+            "location": {"lat": 52.3906, "lon": 13.0645}
+        },
+        {
+            "name": "store munich",
+            "location": {"lat": 48.1351, "lon": 11.582}
+        },
+        {
+            "name": "store without location"
+        }
+    ])
+}
+
+fn get_names(hits: &[search::DocWithHit]) -> Vec<String> {
+    let mut names: Vec<String> = hits.iter().map(|hit| hit.doc["name"].as_str().unwrap().to_string()).collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn should_filter_by_geo_distance() {
+    let req = json!({
+        "search_req": {"search": {"path": "location", "geo_distance": {"origin": {"lat": 52.52, "lon": 13.405}, "distance": "5km"}}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["store alexanderplatz", "store brandenburger tor"]);
+
+    let req = json!({
+        "search_req": {"search": {"path": "name", "terms": ["store"]}},
+        "filter": {"search": {"path": "location", "geo_distance": {"origin": {"lat": 52.52, "lon": 13.405}, "distance": "30km"}}}
+    });
+    assert_eq!(
+        get_names(&search_testo_to_doc!(req).data),
+        vec!["store alexanderplatz", "store brandenburger tor", "store potsdam"]
+    );
+}
+
+#[test]
+fn should_filter_by_geo_bounding_box() {
+    let req = json!({
+        "search_req": {"search": {"path": "location", "geo_bounding_box": {"top_left": {"lat": 53.0, "lon": 10.0}, "bottom_right": {"lat": 47.0, "lon": 13.2}}}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["store munich", "store potsdam"]);
+}
+
+#[test]
+fn should_sort_by_distance() {
+    let req = json!({
+        "search_req": {"search": {"path": "name", "terms": ["store"]}},
+        "sort_by_distance": {"field": "location", "origin": {"lat": 52.39, "lon": 13.06}}
+    });
+    let names: Vec<_> = search_testo_to_doc!(req).data.iter().map(|hit| hit.doc["name"].as_str().unwrap().to_string()).collect();
+    assert_eq!(
+        names,
+        vec!["store potsdam", "store brandenburger tor", "store alexanderplatz", "store munich", "store without location"]
+    );
+
+    let req = json!({
+        "search_req": {"search": {"path": "name", "terms": ["store"]}},
+        "sort_by_distance": {"field": "location", "origin": {"lat": 52.39, "lon": 13.06}},
+        "top": 2,
+        "skip": 1
+    });
+    let result = search_testo_to_doc!(req);
+    let names: Vec<_> = result.data.iter().map(|hit| hit.doc["name"].as_str().unwrap().to_string()).collect();
+    assert_eq!(names, vec!["store brandenburger tor", "store alexanderplatz"]);
+    assert_eq!(result.num_hits, 5);
+}
+
+#[test]
+fn should_boost_by_distance_decay() {
+    let req = json!({
+        "search_req": {"search": {"path": "name", "terms": ["store"]}},
+        "geo_boost": [{"field": "location", "origin": {"lat": 48.13, "lon": 11.58}, "boost_fun": {"Gauss": {"scale": 100000.0}}}]
+    });
+    let hits = search_testo_to_doc!(req).data;
+    assert_eq!(hits[0].doc["name"], "store munich");
+    // hits without location are not boosted
+    assert_eq!(hits[1].doc["name"], "store without location");
+}
+
+#[test]
+fn should_read_geo_point() {
+    let req = json!({
+        "search_req": {"search": {"path": "name", "terms": ["brandenburger"]}},
+        "select": ["location"]
+    });
+    let hits = search_testo_to_doc!(req).data;
+    assert_eq!(hits[0].doc, json!({"location": {"lat": 52.5163, "lon": 13.3777}}));
+}
+
+#[test]
+fn should_return_error_for_geo_filter_on_text_field() {
+    let req = json!({
+        "search_req": {"search": {"path": "name", "geo_distance": {"origin": {"lat": 52.52, "lon": 13.405}, "distance": "5km"}}}
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    assert!(search::search(requesto, &TEST_PERSISTENCE).is_err());
+
+    let req = json!({
+        "search_req": {"search": {"path": "location", "geo_distance": {"origin": {"lat": 52.52, "lon": 13.405}, "distance": "far"}}}
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    assert!(search::search(requesto, &TEST_PERSISTENCE).is_err());
+}
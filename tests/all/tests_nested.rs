@@ -0,0 +1,141 @@
+use serde_json::Value;
+use veloci::*;
+
+use super::common;
+
+static TEST_FOLDER: &str = "nestedTest";
+lazy_static! {
+    static ref TEST_PERSISTENCE: persistence::Persistence = {
+        let indices = r#"
+        ["*GLOBAL*"]
+        features = ['All']
+
+        "#;
+        common::create_test_persistence(TEST_FOLDER, indices, get_test_data().to_string().as_bytes(), None)
+    };
+}
+
+pub fn get_test_data() -> Value {
+    json!([
+        {
+            "name": "shirt",
+            "variants": [
+                {"color": "red", "size": "XL"},
+                {"color": "blue", "size": "M"}
+            ]
+        },
+        {
+            "name": "sweater",
+            "variants": [
+                {"color": "red", "size": "M"},
+                {"color": "blue", "size": "XL"}
+            ]
+        },
+        {
+            "name": "jacket",
+            "variants": [
+                {"color": "dark red", "size": "XL", "tags": ["waterproof", "hooded"]}
+            ]
+        }
+    ])
+}
+
+fn get_names(hits: &[search::DocWithHit]) -> Vec<String> {
+    let mut names: Vec<String> = hits.iter().map(|hit| hit.doc["name"].as_str().unwrap().to_string()).collect();
+    names.sort();
+    names
+}
+
+fn color_and_size(color: &str, size: &str) -> Value {
+    json!({"and": {"queries": [
+        {"search": {"path": "variants[].color", "terms": [color]}},
+        {"search": {"path": "variants[].size", "terms": [size]}}
+    ]}})
+}
+
+#[test]
+fn should_match_and_on_different_elements_without_nested() {
+    let req = json!({ "search_req": color_and_size("red", "XL") });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["jacket", "shirt", "sweater"]);
+}
+
+#[test]
+fn should_match_nested_and_within_same_element() {
+    let req = json!({
+        "search_req": {"nested": {"path": "variants[]", "query": color_and_size("red", "XL")}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["jacket", "shirt"]);
+
+    let req = json!({
+        "search_req": {"nested": {"path": "variants[]", "query": color_and_size("blue", "XL")}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["sweater"]);
+}
+
+#[test]
+fn should_match_nested_or_and_deeper_arrays() {
+    let req = json!({
+        "search_req": {"nested": {"path": "variants[]", "query": {"and": {"queries": [
+            {"or": {"queries": [
+                {"search": {"path": "variants[].color", "terms": ["blue"]}},
+                {"search": {"path": "variants[].tags[]", "terms": ["hooded"]}}
+            ]}},
+            {"search": {"path": "variants[].size", "terms": ["XL"]}}
+        ]}}}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["jacket", "sweater"]);
+}
+
+#[test]
+fn should_filter_with_nested() {
+    let req = json!({
+        "search_req": {"search": {"path": "name", "terms": ["s"], "starts_with": true}},
+        "filter": {"nested": {"path": "variants[]", "query": color_and_size("red", "M")}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["sweater"]);
+}
+
+#[test]
+fn should_combine_nested_with_other_queries() {
+    let req = json!({
+        "search_req": {"and": {"queries": [
+            {"search": {"path": "name", "terms": ["shirt"]}},
+            {"nested": {"path": "variants[]", "query": color_and_size("red", "XL")}}
+        ]}}
+    });
+    assert_eq!(get_names(&search_testo_to_doc!(req).data), vec!["shirt"]);
+}
+
+#[test]
+fn should_return_error_for_path_outside_of_nested() {
+    let req = json!({
+        "search_req": {"nested": {"path": "variants[]", "query": {"search": {"path": "name", "terms": ["shirt"]}}}}
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    assert!(search::search(requesto, &TEST_PERSISTENCE).is_err());
+
+    let req = json!({
+        "search_req": {"nested": {"path": "variants", "query": color_and_size("red", "XL")}}
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    assert!(search::search(requesto, &TEST_PERSISTENCE).is_err());
+}
+
+fn contains_operation(node: &search::ExplainNode, operation: &search::ExplainOperation) -> bool {
+    &node.operation == operation || node.inputs.iter().any(|input| contains_operation(input, operation))
+}
+
+#[test]
+fn should_explain_token_hits_resolved_to_texts_in_nested() {
+    // "red" hits the text "dark red" of the jacket via its token
+    let req = json!({
+        "search_req": {"nested": {"path": "variants[]", "query": color_and_size("red", "XL")}},
+        "explain": true
+    });
+    let hits = search_testo_to_doc!(req).data;
+    assert_eq!(get_names(&hits), vec!["jacket", "shirt"]);
+    let jacket = hits.iter().find(|hit| hit.doc["name"] == "jacket").unwrap();
+    let tree = jacket.explain_tree.as_ref().unwrap();
+    assert_eq!(tree.operation, search::ExplainOperation::Intersection);
+    assert!(contains_operation(tree, &search::ExplainOperation::MaxTokenToTextId));
+}
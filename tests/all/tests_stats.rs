@@ -0,0 +1,68 @@
+use serde_json::Value;
+use veloci::*;
+
+use super::common;
+
+static TEST_FOLDER: &str = "mochaTest_stats";
+lazy_static! {
+    static ref TEST_PERSISTENCE: persistence::Persistence = common::create_test_persistence(TEST_FOLDER, "{}", &get_test_data().to_string().as_bytes(), None);
+}
+
+pub fn get_test_data() -> Value {
+    json!([
+        { "title": "red apple", "count": 1 },
+        { "title": "red cherry", "count": 2 },
+        { "title": "green apple red", "count": 3 }
+    ])
+}
+
+#[test]
+fn stats_of_fields_and_indices() {
+    let stats = TEST_PERSISTENCE.get_stats().unwrap();
+    assert_eq!(stats.num_docs, 3);
+    assert_gt!(stats.doc_store_size, 0);
+
+    let title = stats.fields.iter().find(|field| field.name == "title").unwrap();
+    // 4 words, the separator " " and 3 texts
+    assert_eq!(title.num_terms, 8);
+    assert_gt!(title.fst_size, 0);
+    assert_eq!(title.num_long_text_ids, 0);
+    // transient indices are not written and have no index metadata
+    if TEST_PERSISTENCE.persistence_type == persistence::PersistenceType::Persistent {
+        assert!(!title.indices.is_empty());
+        assert!(title.indices.iter().any(|index| !index.is_empty && index.size > 0));
+    }
+    let sizes = stats.fields.iter().map(|field| field.fst_size + field.indices.iter().map(|index| index.size).sum::<u64>());
+    assert_eq!(stats.indices_size, sizes.sum::<u64>());
+}
+
+#[test]
+fn index_sizes_of_all_indices() {
+    let index_sizes = TEST_PERSISTENCE.get_index_sizes();
+    assert!(index_sizes.iter().any(|(size, index_type, path)| *size > 0 && index_type == "FST" && path == "title.textindex"));
+    assert!(index_sizes.iter().any(|(size, index_type, path)| *size > 0 && index_type == "DocStore" && path == "data"));
+    if TEST_PERSISTENCE.persistence_type == persistence::PersistenceType::Persistent {
+        assert!(index_sizes.iter().any(|(_, index_type, path)| index_type == "AnchorScore" && path == "title.textindex.to_anchor_id_score"));
+    }
+    assert!(index_sizes.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+}
+
+#[test]
+fn top_terms_sorted_by_number_of_anchors() {
+    let top_terms = TEST_PERSISTENCE.get_top_terms("title", 3).unwrap();
+    let top_terms: Vec<(&str, u32)> = top_terms.iter().map(|term| (term.term.as_str(), term.num_anchors)).collect();
+    // separators are terms too
+    assert_eq!(top_terms, vec![(" ", 3), ("red", 3), ("apple", 2)]);
+
+    assert!(TEST_PERSISTENCE.get_top_terms("not_a_field", 2).is_err());
+}
+
+#[test]
+fn term_postings_with_scores() {
+    let postings = TEST_PERSISTENCE.get_term_postings("title", "apple").unwrap().unwrap();
+    let anchor_ids: Vec<u32> = postings.postings.iter().map(|posting| posting.anchor_id).collect();
+    assert_eq!(anchor_ids, vec![0, 2]);
+    assert!(postings.postings.iter().all(|posting| posting.score > 0.0));
+
+    assert_eq!(TEST_PERSISTENCE.get_term_postings("title", "banana").unwrap(), None);
+}
@@ -0,0 +1,162 @@
+use serde_json::Value;
+use veloci::*;
+
+use super::common;
+
+static TEST_FOLDER: &str = "typedFieldsTest";
+lazy_static! {
+    static ref TEST_PERSISTENCE: persistence::Persistence = {
+        let indices = r#"
+        ["*GLOBAL*"]
+        features = ['All']
+
+        ["price"]
+        field_type = "i64"
+        facet = true
+
+        ["rating"]
+        field_type = "f64"
+
+        ["in_stock"]
+        field_type = "bool"
+        facet = true
+
+        "#;
+        common::create_test_persistence(TEST_FOLDER, indices, get_test_data().to_string().as_bytes(), None)
+    };
+}
+
+pub fn get_test_data() -> Value {
+    json!([
+        {
+            "title": "cheap shirt",
+            "price": 9,
+            "rating": 3.5,
+            "in_stock": true
+        },
+        {
+            "title": "shirt",
+            "price": 10,
+            "rating": -1.25,
+            "in_stock": false
+        },
+        {
+            "title": "expensive shirt",
+            "price": 100,
+            "rating": 4.75,
+            "in_stock": true
+        },
+        {
+            "title": "discounted shirt",
+            "price": -5,
+            "rating": 10,
+            "in_stock": true
+        }
+    ])
+}
+
+fn get_titles(hits: &[search::DocWithHit]) -> Vec<String> {
+    let mut titles: Vec<String> = hits.iter().map(|hit| hit.doc["title"].as_str().unwrap().to_string()).collect();
+    titles.sort();
+    titles
+}
+
+#[test]
+fn should_search_exact_integer() {
+    let req = json!({
+        "search_req": {"search": {"path": "price", "terms": ["10"]}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["shirt"]);
+}
+
+#[test]
+fn should_search_integer_range_in_numeric_order() {
+    let req = json!({
+        "search_req": {"search": {"path": "price", "range": {"gte": 9, "lt": 100}}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["cheap shirt", "shirt"]);
+
+    let req = json!({
+        "search_req": {"search": {"path": "price", "range": {"lte": "9"}}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["cheap shirt", "discounted shirt"]);
+}
+
+#[test]
+fn should_search_float_range() {
+    let req = json!({
+        "search_req": {"search": {"path": "rating", "range": {"gt": 3.5}}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["discounted shirt", "expensive shirt"]);
+
+    let req = json!({
+        "search_req": {"search": {"path": "rating", "range": {"lt": 0}}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["shirt"]);
+}
+
+#[test]
+fn should_explain_plan_of_range_request() {
+    let req = json!({
+        "search_req": {"search": {"path": "price", "range": {"gte": 9, "lt": 100}}}
+    });
+    assert!(search_testo_to_explain!(req).contains("search price"));
+
+    let req = json!({
+        "search_req": {"search": {"path": "price", "terms": ["ten"]}}
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    assert!(search::explain_plan(requesto, &TEST_PERSISTENCE).is_err());
+}
+
+#[test]
+fn should_search_bool() {
+    let req = json!({
+        "search_req": {"search": {"path": "in_stock", "terms": ["false"]}}
+    });
+    assert_eq!(get_titles(&search_testo_to_doc!(req).data), vec!["shirt"]);
+}
+
+#[test]
+fn should_return_error_for_invalid_integer() {
+    let req = json!({
+        "search_req": {"search": {"path": "price", "terms": ["ten"]}}
+    });
+    let requesto: search::Request = serde_json::from_str(&req.to_string()).expect("Can't parse json");
+    assert!(search::search(requesto, &TEST_PERSISTENCE).is_err());
+}
+
+#[test]
+fn should_search_typed_fields_with_query_generator() {
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "price:10".to_string();
+    assert_eq!(get_titles(&search_testo_to_doco_qp!(params).data), vec!["shirt"]);
+
+    // terms without attribute are only searched in text fields
+    let mut params = query_generator::SearchQueryGeneratorParameters::default();
+    params.search_term = "expensive".to_string();
+    assert_eq!(get_titles(&search_testo_to_doco_qp!(params).data), vec!["expensive shirt"]);
+}
+
+#[test]
+fn should_read_typed_values() {
+    let req = json!({
+        "search_req": {"search": {"path": "title", "terms": ["discounted"]}},
+        "select": ["title", "price", "rating", "in_stock"]
+    });
+    let hits = search_testo_to_doc!(req).data;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].doc, json!({"title": "discounted shirt", "price": -5, "rating": 10.0, "in_stock": true}));
+}
+
+#[test]
+fn should_get_typed_facets() {
+    let req = json!({
+        "search_req": {"search": {"path": "title", "terms": ["shirt"]}},
+        "facets": [{"field": "in_stock"}, {"field": "price", "top": 1}]
+    });
+
+    let facets = search_testo_to_doc!(req).facets.unwrap();
+    assert_eq!(facets.get("in_stock").unwrap(), &vec![("true".to_string(), 3), ("false".to_string(), 1)]);
+    assert_eq!(facets.get("price").unwrap().len(), 1);
+}
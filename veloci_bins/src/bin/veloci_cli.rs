@@ -0,0 +1,302 @@
+use argh::FromArgs;
+use prettytable::{format, Cell, Row, Table};
+use std::io::{self, prelude::*};
+use veloci::{doc_store::DocLoader, error::VelociError, persistence::Persistence, query_generator, search};
+
+#[derive(FromArgs)]
+/// Interactive query tool for an index, enter `:help` for the commands
+struct Opt {
+    /// index folder
+    #[argh(positional)]
+    index: String,
+
+    /// number of hits shown per query, defaults to 10
+    #[argh(option, short = 't', default = "10")]
+    top: usize,
+}
+
+const HELP: &str = "\
+<query>              search with the query language, e.g. `title:fancy~2 OR tags:nice`
+:explain <query>     search and show the score explanation of the hits
+:plan <query>        show the execution plan as dot graph
+:facet <field> [q]   show the facet counts of the field for the query, defaults to the last query
+:doc <id>            show the document
+:fields              list the fields of the index
+:top <n>             set the number of shown hits
+:help                show this help
+:quit                exit";
+
+/// Maximum length of a value in the result table
+const MAX_CELL_LENGTH: usize = 60;
+
+#[derive(Debug, PartialEq)]
+enum Command<'a> {
+    Search(&'a str),
+    Explain(&'a str),
+    Plan(&'a str),
+    Facet { field: &'a str, query: Option<&'a str> },
+    Doc(u32),
+    Fields,
+    Top(usize),
+    Help,
+    Quit,
+}
+
+fn parse_command(line: &str) -> Result<Command<'_>, String> {
+    let line = line.trim();
+    if !line.starts_with(':') {
+        return Ok(Command::Search(line));
+    }
+    let (command, args) = match line.find(char::is_whitespace) {
+        Some(pos) => (&line[..pos], line[pos..].trim()),
+        None => (line, ""),
+    };
+    let required = |name: &str| {
+        if args.is_empty() {
+            Err(format!("{} needs an argument, see :help", name))
+        } else {
+            Ok(args)
+        }
+    };
+    match command {
+        ":explain" => required(command).map(Command::Explain),
+        ":plan" => required(command).map(Command::Plan),
+        ":facet" => {
+            let mut parts = required(command)?.splitn(2, char::is_whitespace);
+            let field = parts.next().unwrap_or_default();
+            let query = parts.next().map(str::trim).filter(|query| !query.is_empty());
+            Ok(Command::Facet { field, query })
+        }
+        ":doc" => required(command)?.parse().map(Command::Doc).map_err(|_| format!("invalid document id {:?}", args)),
+        ":fields" => Ok(Command::Fields),
+        ":top" => required(command)?.parse().map(Command::Top).map_err(|_| format!("invalid number {:?}", args)),
+        ":help" => Ok(Command::Help),
+        ":quit" | ":q" | ":exit" => Ok(Command::Quit),
+        _ => Err(format!("unknown command {:?}, see :help", command)),
+    }
+}
+
+struct Cli {
+    persistence: Persistence,
+    top: usize,
+    last_query: Option<String>,
+}
+
+impl Cli {
+    fn query_params(&self, query: &str) -> query_generator::SearchQueryGeneratorParameters {
+        query_generator::SearchQueryGeneratorParameters {
+            search_term: query.to_string(),
+            top: Some(self.top),
+            ..Default::default()
+        }
+    }
+
+    fn search(&self, params: query_generator::SearchQueryGeneratorParameters) -> Result<search::SearchResultWithDoc, VelociError> {
+        let request = query_generator::search_query(&self.persistence, params)?;
+        let hits = search::search(request.clone(), &self.persistence)?;
+        Ok(search::to_search_result(&self.persistence, hits, &request.select))
+    }
+
+    fn execute(&mut self, command: Command<'_>) -> Result<(), VelociError> {
+        match command {
+            Command::Search("") => {}
+            Command::Search(query) => {
+                let result = self.search(self.query_params(query))?;
+                self.last_query = Some(query.to_string());
+                print_hits(&result);
+            }
+            Command::Explain(query) => {
+                let mut params = self.query_params(query);
+                params.explain = Some(true);
+                let result = self.search(params)?;
+                self.last_query = Some(query.to_string());
+                for hit in &result.data {
+                    println!("id {} score {}", hit.hit.id, hit.hit.score);
+                    if let Some(explain_tree) = hit.explain_tree.as_ref() {
+                        print_explain_node(explain_tree, 1);
+                    }
+                }
+            }
+            Command::Plan(query) => {
+                let request = query_generator::search_query(&self.persistence, self.query_params(query))?;
+                println!("{}", search::explain_plan(request, &self.persistence)?);
+            }
+            Command::Facet { field, query } => {
+                let query = match query.map(str::to_string).or_else(|| self.last_query.clone()) {
+                    Some(query) => query,
+                    None => {
+                        println!(":facet needs a query, when no query was run before");
+                        return Ok(());
+                    }
+                };
+                let mut params = self.query_params(&query);
+                params.facets = Some(vec![field.to_string()]);
+                params.facetlimit = Some(self.top);
+                let result = self.search(params)?;
+                let mut table = new_table(&["Value", "Count"]);
+                for (value, count) in result.facets.iter().flat_map(|facets| facets.get(field)).flatten() {
+                    table.add_row(Row::new(vec![Cell::new(value), Cell::new(&count.to_string())]));
+                }
+                print!("{}", table);
+            }
+            Command::Doc(id) => {
+                if u64::from(id) >= self.persistence.get_number_of_documents() {
+                    println!("document {} not found, the index has {} documents", id, self.persistence.get_number_of_documents());
+                    return Ok(());
+                }
+                let offsets = match self.persistence.indices.doc_offsets.as_ref() {
+                    Some(offsets) => offsets,
+                    None => {
+                        println!("the index has no document store");
+                        return Ok(());
+                    }
+                };
+                let data = self.persistence.get_mmap_handle("data")?;
+                let doc: serde_json::Value = serde_json::from_str(&DocLoader::get_doc(&data, offsets, id as usize)?)?;
+                println!("{}", serde_json::to_string_pretty(&doc)?);
+            }
+            Command::Fields => {
+                let mut table = new_table(&["Field", "Type", "Fulltext"]);
+                let mut columns: Vec<_> = self.persistence.metadata.columns.iter().collect();
+                columns.sort_by_key(|(name, _)| name.as_str());
+                for (name, field_info) in columns {
+                    table.add_row(Row::new(vec![
+                        Cell::new(name),
+                        Cell::new(&format!("{:?}", field_info.field_type)),
+                        Cell::new(&field_info.has_fst.to_string()),
+                    ]));
+                }
+                print!("{}", table);
+                println!("{} documents", self.persistence.get_number_of_documents());
+            }
+            Command::Top(top) => self.top = top,
+            Command::Help => println!("{}", HELP),
+            Command::Quit => {}
+        }
+        Ok(())
+    }
+}
+
+fn new_table(titles: &[&str]) -> Table {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(Row::new(titles.iter().map(|title| Cell::new(title)).collect()));
+    table
+}
+
+/// One row per hit, with a column for each top level field of the documents
+fn print_hits(result: &search::SearchResultWithDoc) {
+    let mut fields: Vec<&String> = result.data.iter().filter_map(|hit| hit.doc.as_object()).flat_map(|doc| doc.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    let mut titles = vec!["Id", "Score"];
+    titles.extend(fields.iter().map(|field| field.as_str()));
+    let mut table = new_table(&titles);
+    for hit in &result.data {
+        let mut cells = vec![Cell::new(&hit.hit.id.to_string()), Cell::new(&format!("{:.3}", hit.hit.score))];
+        cells.extend(fields.iter().map(|field| Cell::new(&hit.doc.get(field.as_str()).map(format_value).unwrap_or_default())));
+        table.add_row(Row::new(cells));
+    }
+    print!("{}", table);
+    println!("{} hits in {:.3}ms", result.num_hits, result.execution_time_ns as f64 / 1_000_000.0);
+}
+
+fn format_value(value: &serde_json::Value) -> String {
+    let text = match value {
+        serde_json::Value::String(text) => text.to_string(),
+        other => other.to_string(),
+    };
+    if text.chars().count() > MAX_CELL_LENGTH {
+        text.chars().take(MAX_CELL_LENGTH - 3).collect::<String>() + "..."
+    } else {
+        text
+    }
+}
+
+fn print_explain_node(node: &search::ExplainNode, depth: usize) {
+    println!("{}{:.3} {:?}", "  ".repeat(depth), node.score, node.operation);
+    for input in &node.inputs {
+        print_explain_node(input, depth + 1);
+    }
+}
+
+fn main() {
+    let opt: Opt = argh::from_env();
+    let persistence = Persistence::load(&opt.index).unwrap_or_else(|err| panic!("Could not load index {:?}: {:?}", opt.index, err));
+    let mut cli = Cli {
+        persistence,
+        top: opt.top,
+        last_query: None,
+    };
+    println!("{} documents in {:?}, enter :help for the commands", cli.persistence.get_number_of_documents(), opt.index);
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        match parse_command(&line) {
+            Ok(Command::Quit) => break,
+            Ok(command) => {
+                if let Err(err) = cli.execute(command) {
+                    println!("Error: {}", err);
+                }
+            }
+            Err(message) => println!("{}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search() {
+        assert_eq!(parse_command("  title:fancy \n"), Ok(Command::Search("title:fancy")));
+        assert_eq!(parse_command(""), Ok(Command::Search("")));
+    }
+
+    #[test]
+    fn test_help_example_is_valid() {
+        let example = HELP.split('`').nth(1).unwrap();
+        assert!(veloci::query_parser::parse(example).is_ok(), "invalid example {:?}", example);
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse_command(":explain title:fancy"), Ok(Command::Explain("title:fancy")));
+        assert_eq!(parse_command(":plan  a OR b "), Ok(Command::Plan("a OR b")));
+        assert_eq!(parse_command(":doc 5"), Ok(Command::Doc(5)));
+        assert_eq!(parse_command(":fields"), Ok(Command::Fields));
+        assert_eq!(parse_command(":top 20"), Ok(Command::Top(20)));
+        assert_eq!(parse_command(":help"), Ok(Command::Help));
+        assert_eq!(parse_command(":q"), Ok(Command::Quit));
+        assert_eq!(parse_command(":exit"), Ok(Command::Quit));
+    }
+
+    #[test]
+    fn test_parse_facet() {
+        assert_eq!(parse_command(":facet tags"), Ok(Command::Facet { field: "tags", query: None }));
+        assert_eq!(
+            parse_command(":facet tags  title:fancy OR nice"),
+            Ok(Command::Facet {
+                field: "tags",
+                query: Some("title:fancy OR nice")
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_commands() {
+        assert_eq!(parse_command(":explain"), Err(":explain needs an argument, see :help".to_string()));
+        assert_eq!(parse_command(":facet "), Err(":facet needs an argument, see :help".to_string()));
+        assert_eq!(parse_command(":doc first"), Err("invalid document id \"first\"".to_string()));
+        assert_eq!(parse_command(":top -1"), Err("invalid number \"-1\"".to_string()));
+        assert_eq!(parse_command(":nope"), Err("unknown command \":nope\", see :help".to_string()));
+    }
+}
@@ -0,0 +1,172 @@
+use argh::FromArgs;
+use prettytable::{format, Cell, Row, Table};
+use veloci::{error::VelociError, persistence::Persistence, stats::PersistenceStats};
+
+#[derive(FromArgs)]
+/// Prints statistics of an index: fields, terms, fsts and the sizes of the indices
+struct Opt {
+    /// index folder
+    #[argh(positional)]
+    index: String,
+
+    /// only show this field
+    #[argh(option, short = 'f')]
+    field: Option<String>,
+
+    /// show the n most frequent terms of each field
+    #[argh(option, short = 't')]
+    top_terms: Option<usize>,
+
+    /// show the postings with scores of the term, requires `--field`
+    #[argh(option)]
+    term: Option<String>,
+
+    /// also list the indices of each field
+    #[argh(switch, short = 'i')]
+    indices: bool,
+
+    /// print json instead of tables
+    #[argh(switch)]
+    json: bool,
+}
+
+fn main() {
+    let opt: Opt = argh::from_env();
+    if opt.term.is_some() && opt.field.is_none() {
+        eprintln!("--term requires --field");
+        std::process::exit(1);
+    }
+    let persistence = Persistence::load(&opt.index).unwrap_or_else(|err| panic!("Could not load index {:?}: {:?}", opt.index, err));
+    if let Err(err) = inspect(&persistence, &opt) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn inspect(persistence: &Persistence, opt: &Opt) -> Result<(), VelociError> {
+    let mut stats = persistence.get_stats()?;
+    if let Some(field) = opt.field.as_ref() {
+        stats.fields.retain(|field_stats| &field_stats.name == field);
+        if stats.fields.is_empty() {
+            return Err(VelociError::FieldNotFound {
+                field: field.to_string(),
+                all_fields: persistence.metadata.get_all_fields(),
+            });
+        }
+    }
+
+    if let Some(term) = opt.term.as_ref() {
+        let field = opt.field.as_ref().unwrap();
+        let postings = persistence.get_term_postings(field, term)?;
+        if opt.json {
+            println!("{}", serde_json::to_string_pretty(&postings)?);
+            return Ok(());
+        }
+        match postings {
+            Some(postings) => {
+                let mut table = new_table(&["Anchor", "Score"]);
+                for posting in &postings.postings {
+                    table.add_row(Row::new(vec![Cell::new(&posting.anchor_id.to_string()), Cell::new(&format!("{:.3}", posting.score))]));
+                }
+                print!("{}", table);
+                println!("term {:?} with term id {} in {} anchors", term, postings.term_id, postings.postings.len());
+            }
+            None => println!("term {:?} not found in {:?}", term, field),
+        }
+        return Ok(());
+    }
+
+    if opt.json {
+        let mut json = serde_json::to_value(&stats)?;
+        if let Some(top) = opt.top_terms {
+            for (field_json, field) in json["fields"].as_array_mut().unwrap().iter_mut().zip(stats.fields.iter()) {
+                field_json["top_terms"] = serde_json::to_value(top_terms(persistence, &field.name, top)?)?;
+            }
+        }
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    print_stats(&stats, opt.indices);
+    if let Some(top) = opt.top_terms {
+        for field in &stats.fields {
+            let terms = top_terms(persistence, &field.name, top)?;
+            if terms.is_empty() {
+                continue;
+            }
+            println!("\nTop terms of {}", field.name);
+            let mut table = new_table(&["Term", "Anchors"]);
+            for term in terms {
+                table.add_row(Row::new(vec![Cell::new(&format!("{:?}", term.term)), Cell::new(&term.num_anchors.to_string())]));
+            }
+            print!("{}", table);
+        }
+    }
+    Ok(())
+}
+
+/// Fields without fst, e.g. boost fields, have no terms
+fn top_terms(persistence: &Persistence, field: &str, top: usize) -> Result<Vec<veloci::stats::TermFrequency>, VelociError> {
+    match persistence.get_top_terms(field, top) {
+        Err(VelociError::FstNotFound(_)) => Ok(vec![]),
+        res => res,
+    }
+}
+
+fn print_stats(stats: &PersistenceStats, with_indices: bool) {
+    println!(
+        "{} documents, {} indexed, doc store {}, indices {}",
+        stats.num_docs,
+        readable_size(stats.bytes_indexed),
+        readable_size(stats.doc_store_size),
+        readable_size(stats.indices_size)
+    );
+    let mut table = new_table(&["Field", "Type", "Terms", "FST", "Text Ids", "Long Text Ids", "Indices"]);
+    for field in &stats.fields {
+        table.add_row(Row::new(vec![
+            Cell::new(&field.name),
+            Cell::new(&format!("{:?}", field.field_type)),
+            Cell::new(&field.num_terms.to_string()),
+            Cell::new(&readable_size(field.fst_size)),
+            Cell::new(&field.num_text_ids.to_string()),
+            Cell::new(&field.num_long_text_ids.to_string()),
+            Cell::new(&readable_size(field.indices.iter().map(|index| index.size).sum())),
+        ]));
+    }
+    print!("{}", table);
+
+    if with_indices {
+        let mut table = new_table(&["Path", "Category", "Cardinality", "Loading", "Ids", "Values", "Avg Join", "Size"]);
+        for index in stats.fields.iter().flat_map(|field| field.indices.iter()) {
+            table.add_row(Row::new(vec![
+                Cell::new(&index.path),
+                Cell::new(&format!("{:?}", index.index_category)),
+                Cell::new(&format!("{:?}", index.index_cardinality)),
+                Cell::new(&format!("{:?}", index.loading_type)),
+                Cell::new(&index.metadata.num_ids.to_string()),
+                Cell::new(&index.metadata.num_values.to_string()),
+                Cell::new(&format!("{:.2}", index.metadata.avg_join_size)),
+                Cell::new(&if index.is_empty { "empty".to_string() } else { readable_size(index.size) }),
+            ]));
+        }
+        print!("{}", table);
+    }
+}
+
+fn new_table(titles: &[&str]) -> Table {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(Row::new(titles.iter().map(|title| Cell::new(title)).collect()));
+    table
+}
+
+fn readable_size(bytes: u64) -> String {
+    let bytes = bytes as f64;
+    if bytes >= 1_000_000.0 {
+        format!("{:.2} MB", bytes / 1_000_000.0)
+    } else if bytes >= 1_000.0 {
+        format!("{:.2} KB", bytes / 1_000.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
@@ -0,0 +1,248 @@
+use argh::FromArgs;
+use rayon::prelude::*;
+use std::{
+    fs::File,
+    io::{prelude::*, BufReader},
+    time::{Duration, Instant},
+};
+use veloci::{error::VelociError, persistence::Persistence, search, search_field};
+
+#[derive(FromArgs)]
+/// Replays a request log against an index and reports throughput and latencies
+struct Opt {
+    /// ndjson file with one request per line, either a `search::Request` or a line of the server query log
+    #[argh(option, short = 'l')]
+    log: String,
+
+    /// index folder to run the requests against
+    #[argh(option, short = 'i')]
+    index: String,
+
+    /// second index folder, the result ids of every request are compared with the ones of `index`
+    #[argh(option)]
+    compare: Option<String>,
+
+    /// number of requests executed in parallel, defaults to 1
+    #[argh(option, short = 'c', default = "1")]
+    concurrency: usize,
+
+    /// only replay requests of this database, for query logs with multiple databases
+    #[argh(option, short = 'd')]
+    database: Option<String>,
+
+    /// number of times the log is replayed, defaults to 1
+    #[argh(option, default = "1")]
+    repeat: usize,
+
+    /// maximum number of printed result differences, defaults to 20
+    #[argh(option, default = "20")]
+    max_diffs: usize,
+}
+
+/// A request of the log with its line number
+struct LoggedRequest {
+    line: usize,
+    request: search::Request,
+}
+
+/// The ids of the hits or the suggested terms, in the order of the result
+#[derive(Debug, PartialEq)]
+enum ResultIds {
+    Hits(Vec<u32>),
+    Terms(Vec<String>),
+}
+
+fn main() {
+    veloci::trace::enable_log();
+    let opt: Opt = argh::from_env();
+
+    let requests = read_log(&opt.log, opt.database.as_deref()).unwrap_or_else(|err| panic!("Could not read request log {:?}: {}", opt.log, err));
+    println!("{} requests", requests.len());
+
+    let persistence = Persistence::load(&opt.index).unwrap_or_else(|err| panic!("Could not load index {:?}: {:?}", opt.index, err));
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(opt.concurrency.max(1)).build().unwrap();
+
+    let start = Instant::now();
+    let results: Vec<Result<Duration, VelociError>> = pool.install(|| {
+        (0..opt.repeat)
+            .flat_map(|_| requests.iter())
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|logged| {
+                let start = Instant::now();
+                execute(&persistence, &logged.request).map(|_| start.elapsed())
+            })
+            .collect()
+    });
+    let total_time = start.elapsed();
+
+    let mut latencies: Vec<Duration> = results.iter().filter_map(|res| res.as_ref().ok().cloned()).collect();
+    latencies.sort();
+    let num_errors = results.len() - latencies.len();
+    println!(
+        "{} requests in {:.2}s, {:.1} requests/s, {} errors",
+        results.len(),
+        total_time.as_secs_f64(),
+        results.len() as f64 / total_time.as_secs_f64(),
+        num_errors
+    );
+    for (name, percentile) in &[("p50", 50.0), ("p95", 95.0), ("p99", 99.0)] {
+        println!("{} {:.3}ms", name, percentile_of(&latencies, *percentile).as_secs_f64() * 1000.0);
+    }
+    println!("max {:.3}ms", latencies.last().cloned().unwrap_or_default().as_secs_f64() * 1000.0);
+
+    if let Some(compare) = opt.compare.as_ref() {
+        let other = Persistence::load(compare).unwrap_or_else(|err| panic!("Could not load index {:?}: {:?}", compare, err));
+        let num_diffs = compare_results(&pool, &requests, &persistence, &other, opt.max_diffs);
+        println!("{} of {} requests have different results in {:?}", num_diffs, requests.len(), compare);
+        if num_diffs != 0 {
+            std::process::exit(1);
+        }
+    }
+}
+
+fn read_log(path: &str, database: Option<&str>) -> Result<Vec<LoggedRequest>, VelociError> {
+    let mut requests = vec![];
+    for (line_number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut value: serde_json::Value = serde_json::from_str(&line)?;
+        // lines of the query log contain the request with database, timings, ...
+        if let Some(request) = value.get_mut("request").map(serde_json::Value::take) {
+            if database.is_some() && value.get("database").and_then(|database| database.as_str()) != database {
+                continue;
+            }
+            value = request;
+        }
+        requests.push(LoggedRequest {
+            line: line_number + 1,
+            request: serde_json::from_value(value)?,
+        });
+    }
+    Ok(requests)
+}
+
+fn execute(persistence: &Persistence, request: &search::Request) -> Result<ResultIds, VelociError> {
+    if request.suggest.is_some() {
+        let hits = search_field::suggest_multi(persistence, request.clone())?;
+        Ok(ResultIds::Terms(hits.into_iter().map(|(term, _score, _id)| term).collect()))
+    } else {
+        let result = search::search(request.clone(), persistence)?;
+        Ok(ResultIds::Hits(result.data.iter().map(|hit| hit.id).collect()))
+    }
+}
+
+/// Nearest rank percentile of sorted values
+fn percentile_of(sorted: &[Duration], percentile: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::default();
+    }
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1) - 1]
+}
+
+/// Executes the requests on both indices and prints the first `max_diffs` differences, returns the number of requests with different results
+fn compare_results(pool: &rayon::ThreadPool, requests: &[LoggedRequest], persistence: &Persistence, other: &Persistence, max_diffs: usize) -> usize {
+    let diffs: Vec<(usize, String)> = pool.install(|| {
+        requests
+            .par_iter()
+            .filter_map(|logged| {
+                let expected = execute(persistence, &logged.request);
+                let actual = execute(other, &logged.request);
+                let diff = match (expected, actual) {
+                    (Ok(expected), Ok(actual)) if expected == actual => return None,
+                    (Ok(ResultIds::Hits(expected)), Ok(ResultIds::Hits(actual))) => describe_diff(&expected, &actual),
+                    (Ok(ResultIds::Terms(expected)), Ok(ResultIds::Terms(actual))) => describe_diff(&expected, &actual),
+                    (Err(err), Ok(_)) => format!("fails only in the first index: {}", err),
+                    (Ok(_), Err(err)) => format!("fails only in the second index: {}", err),
+                    (Err(err), Err(other_err)) if err.to_string() == other_err.to_string() => return None,
+                    (expected, actual) => format!("{:?} != {:?}", expected, actual),
+                };
+                Some((logged.line, diff))
+            })
+            .collect()
+    });
+    for (line, diff) in diffs.iter().take(max_diffs) {
+        println!("line {}: {}", line, diff);
+    }
+    diffs.len()
+}
+
+fn describe_diff<T: PartialEq + std::fmt::Debug>(expected: &[T], actual: &[T]) -> String {
+    let missing: Vec<&T> = expected.iter().filter(|el| !actual.contains(el)).collect();
+    let added: Vec<&T> = actual.iter().filter(|el| !expected.contains(el)).collect();
+    if missing.is_empty() && added.is_empty() {
+        format!("different order {:?} != {:?}", expected, actual)
+    } else {
+        format!("missing {:?}, added {:?}", missing, added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_log(lines: &[&str]) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for line in lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+        file
+    }
+
+    #[test]
+    fn test_percentile_of() {
+        let sorted: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile_of(&sorted, 50.0), Duration::from_millis(5));
+        assert_eq!(percentile_of(&sorted, 95.0), Duration::from_millis(10));
+        assert_eq!(percentile_of(&sorted, 0.0), Duration::from_millis(1));
+        assert_eq!(percentile_of(&sorted, 100.0), Duration::from_millis(10));
+        assert_eq!(percentile_of(&[Duration::from_millis(3)], 99.0), Duration::from_millis(3));
+        assert_eq!(percentile_of(&[], 50.0), Duration::default());
+    }
+
+    #[test]
+    fn test_read_log_plain_and_query_log_lines() {
+        let file = write_log(&[
+            r#"{"search_req": {"search": {"terms": ["fancy"], "path": "title"}}, "top": 3}"#,
+            "",
+            r#"{"database": "jmdict", "request": {"suggest": [{"terms": ["fan"], "path": "title"}]}, "took_ms": 2}"#,
+        ]);
+        let requests = read_log(file.path().to_str().unwrap(), None).unwrap();
+        assert_eq!(requests.iter().map(|logged| logged.line).collect::<Vec<_>>(), vec![1, 3]);
+        assert!(requests[0].request.search_req.is_some());
+        assert_eq!(requests[0].request.top, Some(3));
+        assert!(requests[1].request.suggest.is_some());
+        assert!(requests[1].request.search_req.is_none());
+    }
+
+    #[test]
+    fn test_read_log_filters_database() {
+        let file = write_log(&[
+            r#"{"database": "jmdict", "request": {"search_req": {"search": {"terms": ["a"], "path": "title"}}}}"#,
+            r#"{"database": "other", "request": {"search_req": {"search": {"terms": ["b"], "path": "title"}}}}"#,
+            r#"{"search_req": {"search": {"terms": ["c"], "path": "title"}}}"#,
+        ]);
+        let path = file.path().to_str().unwrap();
+        // plain requests have no database and are always replayed
+        let lines = |database| read_log(path, database).unwrap().iter().map(|logged| logged.line).collect::<Vec<_>>();
+        assert_eq!(lines(Some("jmdict")), vec![1, 3]);
+        assert_eq!(lines(Some("other")), vec![2, 3]);
+        assert_eq!(lines(None), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_read_log_invalid_line() {
+        let file = write_log(&[r#"{"search_req": "#]);
+        assert!(read_log(file.path().to_str().unwrap(), None).is_err());
+    }
+
+    #[test]
+    fn test_describe_diff() {
+        assert_eq!(describe_diff(&[1, 2, 3], &[2, 4]), "missing [1, 3], added [4]");
+        assert_eq!(describe_diff(&[1, 2], &[2, 1]), "different order [1, 2] != [2, 1]");
+        assert_eq!(describe_diff(&["a".to_string()], &["a".to_string(), "b".to_string()]), r#"missing [], added ["b"]"#);
+    }
+}